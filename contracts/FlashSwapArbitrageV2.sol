@@ -23,6 +23,7 @@ contract FlashSwapArbitrageV2 is IUniswapV2Callee {
         address tokenIn;
         address tokenOut;
         uint256 amountIn;
+        uint256 minProfit;
         bool zeroForOne;
     }
 
@@ -32,7 +33,8 @@ contract FlashSwapArbitrageV2 is IUniswapV2Callee {
         address pool2,
         address tokenIn,
         address tokenOut,
-        uint256 amountIn
+        uint256 amountIn,
+        uint256 minProfit
     ) external {
         require(msg.sender == owner, "Only owner");
 
@@ -51,6 +53,7 @@ contract FlashSwapArbitrageV2 is IUniswapV2Callee {
                 tokenIn: tokenIn,
                 tokenOut: tokenOut,
                 amountIn: amountIn,
+                minProfit: minProfit,
                 zeroForOne: zeroForOne
             })
         );
@@ -98,6 +101,7 @@ contract FlashSwapArbitrageV2 is IUniswapV2Callee {
         uint totalOwed = loanAmount + fee;
 
         require(amountOut2 > totalOwed, "Unprofitable");
+        require(amountOut2 - totalOwed >= params.minProfit, "Below min profit");
 
         IERC20(params.tokenIn).transfer(params.pool0, totalOwed);
 
@@ -20,6 +20,18 @@ contract FlashSwapArbitrageV3 {
     uint160 private constant MIN_SQRT_RATIO = 4295128739 + 1;
     uint160 private constant MAX_SQRT_RATIO = 1461446703485210103287273052203988822378723970342 - 1;
 
+    struct FlashParams {
+        address initiator;
+        address pool0;
+        address pool1;
+        address pool2;
+        address tokenIn;
+        address tokenOut;
+        uint256 amountIn;
+        uint256 minProfit;
+        bool zeroForOne;
+    }
+
     /**
      * @notice Entry point for the arbitrage with 3 pools
      * @param pool0 Uniswap V3 pool to borrow from (flashloan)
@@ -28,6 +40,7 @@ contract FlashSwapArbitrageV3 {
      * @param tokenIn Token to borrow (and return at the end)
      * @param tokenOut Token used for intermediate step
      * @param amountIn Amount of tokenIn to borrow
+     * @param minProfit Smallest profit in tokenIn the caller accepts; the swap reverts below it
      */
     function flashSwap(
         address pool0,
@@ -35,7 +48,8 @@ contract FlashSwapArbitrageV3 {
         address pool2,
         address tokenIn,
         address tokenOut,
-        uint256 amountIn
+        uint256 amountIn,
+        uint256 minProfit
     ) external {
         emit CallbackEntered(pool0, pool1, pool2, tokenIn, tokenOut, amountIn);
 
@@ -49,14 +63,17 @@ contract FlashSwapArbitrageV3 {
         // These bytes will be passed to swap() as a data argument, and inside Uniswap V3 they will be saved and passed to your uniswapV3SwapCallback().
         // This allows us to drag our data through Uniswap into the callback, because Uniswap itself doesn't know what to do with it.
         bytes memory data = abi.encode(
-            msg.sender,
-            pool0,
-            pool1,
-            pool2,
-            tokenIn,
-            tokenOut,
-            amountIn,
-            zeroForOne
+            FlashParams({
+                initiator: msg.sender,
+                pool0: pool0,
+                pool1: pool1,
+                pool2: pool2,
+                tokenIn: tokenIn,
+                tokenOut: tokenOut,
+                amountIn: amountIn,
+                minProfit: minProfit,
+                zeroForOne: zeroForOne
+            })
         );
 
         // Call swap() with non-standard logic where:
@@ -80,52 +97,44 @@ contract FlashSwapArbitrageV3 {
         int256 amount1,
         bytes calldata data
     ) external {
-        (
-            address initiator,
-            address pool0,
-            address pool1,
-            address pool2,
-            address tokenIn,
-            address tokenOut,
-            uint256 amountIn,
-            bool zeroForOne
-        ) = abi.decode(data, (address, address, address, address, address, address, uint256, bool));
-        require(msg.sender == pool0, "Unauthorized callback");
+        FlashParams memory params = abi.decode(data, (FlashParams));
+        require(msg.sender == params.pool0, "Unauthorized callback");
 
         // ~ <ilya.berdar> update arbitrage loop [223803d0]
-        uint256 tokenOutAmount = zeroForOne ? uint256(-amount1) : uint256(-amount0);
+        uint256 tokenOutAmount = params.zeroForOne ? uint256(-amount1) : uint256(-amount0);
 
         // -------- Step 1: Sell tokenIn for tokenOut in pool1 --------
-        IERC20(tokenIn).approve(pool1, amountIn);
+        IERC20(params.tokenIn).approve(params.pool1, params.amountIn);
 
-        IUniswapV3Pool(pool1).swap(
+        IUniswapV3Pool(params.pool1).swap(
             address(this),
-            zeroForOne,
-            int256(amountIn),
-            zeroForOne ? MIN_SQRT_RATIO + 1 : MAX_SQRT_RATIO - 1,
+            params.zeroForOne,
+            int256(params.amountIn),
+            params.zeroForOne ? MIN_SQRT_RATIO + 1 : MAX_SQRT_RATIO - 1,
             ""
         );
 
         // -------- Step 2: Buy tokenIn back in pool2 using tokenOut --------
-        IERC20(tokenOut).approve(pool2, tokenOutAmount);
+        IERC20(params.tokenOut).approve(params.pool2, tokenOutAmount);
 
-        IUniswapV3Pool(pool2).swap(
+        IUniswapV3Pool(params.pool2).swap(
             address(this),
-            !zeroForOne, // reverse direction
+            !params.zeroForOne, // reverse direction
             int256(tokenOutAmount),
-            !zeroForOne ? MIN_SQRT_RATIO : MAX_SQRT_RATIO,
+            !params.zeroForOne ? MIN_SQRT_RATIO : MAX_SQRT_RATIO,
             ""
         );
 
         // -------- Step 3: Repay the flashloan --------
-        IERC20(tokenIn).transfer(pool0, amountIn);
+        IERC20(params.tokenIn).transfer(params.pool0, params.amountIn);
 
         // -------- Step 4: Transfer profit to caller --------
-        uint256 remaining = IERC20(tokenIn).balanceOf(address(this));
-        require(remaining > amountIn, "No profit from arbitrage");
+        uint256 remaining = IERC20(params.tokenIn).balanceOf(address(this));
+        require(remaining > params.amountIn, "No profit from arbitrage");
+        require(remaining - params.amountIn >= params.minProfit, "Below min profit");
 
-        uint256 profit = remaining - amountIn;
-        IERC20(tokenIn).transfer(initiator, profit);
+        uint256 profit = remaining - params.amountIn;
+        IERC20(params.tokenIn).transfer(params.initiator, profit);
         // ~ <ilya.berdar>
     }
 }
@@ -1,13 +1,57 @@
 // SPDX-License-Identifier: MIT
 pragma solidity ^0.8.20;
 
+import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
+
 interface IUniswapV3FlashCallback {
     function uniswapV3FlashCallback(uint256 fee0, uint256 fee1, bytes calldata data) external;
 }
 
+interface IUniswapV3SwapCallbackMock {
+    function uniswapV3SwapCallback(int256 amount0Delta, int256 amount1Delta, bytes calldata data) external;
+}
+
 contract UniswapPoolMock {
     address public target;
 
+    address public token0;
+    address public token1;
+    /// token1 paid per token0 by `swap`, scaled by 1e18.
+    uint256 public price;
+
+    function setTokens(address _token0, address _token1, uint256 _price) external {
+        token0 = _token0;
+        token1 = _token1;
+        price = _price;
+    }
+
+    /**
+     * @notice Exact-input swap at the fixed `price`, with the V3 pool interface. Pays the output
+     * first, then takes the input: through `uniswapV3SwapCallback` when `data` is set (a flash
+     * swap), with `transferFrom` on the caller's approval otherwise.
+     */
+    function swap(address recipient, bool zeroForOne, int256 amountSpecified, uint160, bytes calldata data)
+        external
+        returns (int256 amount0, int256 amount1)
+    {
+        require(amountSpecified > 0, "Exact input only");
+        uint256 amountIn = uint256(amountSpecified);
+        uint256 amountOut = zeroForOne ? amountIn * price / 1e18 : amountIn * 1e18 / price;
+        (address tokenIn, address tokenOut) = zeroForOne ? (token0, token1) : (token1, token0);
+        (amount0, amount1) = zeroForOne
+            ? (amountSpecified, -int256(amountOut))
+            : (-int256(amountOut), amountSpecified);
+
+        IERC20(tokenOut).transfer(recipient, amountOut);
+        if (data.length > 0) {
+            uint256 before = IERC20(tokenIn).balanceOf(address(this));
+            IUniswapV3SwapCallbackMock(msg.sender).uniswapV3SwapCallback(amount0, amount1, data);
+            require(IERC20(tokenIn).balanceOf(address(this)) >= before + amountIn, "Not repaid");
+        } else {
+            IERC20(tokenIn).transferFrom(msg.sender, address(this), amountIn);
+        }
+    }
+
     function setTarget(address _target) external {
         target = _target;
     }
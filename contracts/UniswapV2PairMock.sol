@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
+
+interface IUniswapV2CalleeMock {
+    function uniswapV2Call(address sender, uint amount0, uint amount1, bytes calldata data) external;
+}
+
+/**
+ * @notice Minimal constant-product pair with the Uniswap V2 swap/getReserves interface.
+ * Used by the Rust devnet harness to compare simulated amounts with real swaps.
+ * No LP tokens: seed it by transferring tokens in and calling sync().
+ */
+contract UniswapV2PairMock {
+    address public token0;
+    address public token1;
+    /// Swap fee in hundredths of a bip, as V3 pools count it: 3000 is Uniswap V2's 0.3%.
+    uint32 public immutable fee;
+
+    uint112 private reserve0;
+    uint112 private reserve1;
+    uint32 private blockTimestampLast;
+
+    event Swap(address indexed sender, uint amount0In, uint amount1In, uint amount0Out, uint amount1Out, address indexed to);
+    event Sync(uint112 reserve0, uint112 reserve1);
+
+    constructor(address _token0, address _token1, uint32 _fee) {
+        require(_fee < 1_000_000, "FEE");
+        token0 = _token0;
+        token1 = _token1;
+        fee = _fee;
+    }
+
+    function getReserves() external view returns (uint112 _reserve0, uint112 _reserve1, uint32 _blockTimestampLast) {
+        return (reserve0, reserve1, blockTimestampLast);
+    }
+
+    function sync() external {
+        _update(IERC20(token0).balanceOf(address(this)), IERC20(token1).balanceOf(address(this)));
+    }
+
+    function swap(uint amount0Out, uint amount1Out, address to, bytes calldata data) external {
+        require(amount0Out > 0 || amount1Out > 0, "INSUFFICIENT_OUTPUT_AMOUNT");
+        require(amount0Out < reserve0 && amount1Out < reserve1, "INSUFFICIENT_LIQUIDITY");
+
+        if (amount0Out > 0) IERC20(token0).transfer(to, amount0Out);
+        if (amount1Out > 0) IERC20(token1).transfer(to, amount1Out);
+        if (data.length > 0) IUniswapV2CalleeMock(to).uniswapV2Call(msg.sender, amount0Out, amount1Out, data);
+
+        uint balance0 = IERC20(token0).balanceOf(address(this));
+        uint balance1 = IERC20(token1).balanceOf(address(this));
+
+        uint amount0In = balance0 > reserve0 - amount0Out ? balance0 - (reserve0 - amount0Out) : 0;
+        uint amount1In = balance1 > reserve1 - amount1Out ? balance1 - (reserve1 - amount1Out) : 0;
+        require(amount0In > 0 || amount1In > 0, "INSUFFICIENT_INPUT_AMOUNT");
+
+        // Same invariant check as UniswapV2Pair, with the fee counted in millionths
+        uint balance0Adjusted = balance0 * 1e6 - amount0In * fee;
+        uint balance1Adjusted = balance1 * 1e6 - amount1In * fee;
+        require(balance0Adjusted * balance1Adjusted >= uint(reserve0) * uint(reserve1) * 1e12, "K");
+
+        _update(balance0, balance1);
+        emit Swap(msg.sender, amount0In, amount1In, amount0Out, amount1Out, to);
+    }
+
+    function _update(uint balance0, uint balance1) private {
+        require(balance0 <= type(uint112).max && balance1 <= type(uint112).max, "OVERFLOW");
+        reserve0 = uint112(balance0);
+        reserve1 = uint112(balance1);
+        blockTimestampLast = uint32(block.timestamp);
+        emit Sync(reserve0, reserve1);
+    }
+}
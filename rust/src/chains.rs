@@ -0,0 +1,239 @@
+use crate::rpc;
+use crate::ArbitrageDirection;
+use anyhow::{anyhow, Result};
+use ethers::providers::Middleware;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Multicall3 is deployed at the same address on every chain we support.
+pub const MULTICALL3: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainInfo {
+    pub chain_id: u64,
+    pub name: &'static str,
+    pub native_token: &'static str,
+    pub wrapped_native: &'static str,
+    pub multicall3: &'static str,
+    pub block_time_ms: u64,
+    pub explorer_url: &'static str,
+}
+
+pub static CHAINS: &[ChainInfo] = &[
+    ChainInfo {
+        chain_id: 1,
+        name: "ethereum",
+        native_token: "ETH",
+        wrapped_native: "0xC02aaa39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+        multicall3: MULTICALL3,
+        block_time_ms: 12_000,
+        explorer_url: "https://etherscan.io",
+    },
+    ChainInfo {
+        chain_id: 42161,
+        name: "arbitrum",
+        native_token: "ETH",
+        wrapped_native: "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1",
+        multicall3: MULTICALL3,
+        block_time_ms: 250,
+        explorer_url: "https://arbiscan.io",
+    },
+    ChainInfo {
+        chain_id: 10,
+        name: "optimism",
+        native_token: "ETH",
+        wrapped_native: "0x4200000000000000000000000000000000000006",
+        multicall3: MULTICALL3,
+        block_time_ms: 2_000,
+        explorer_url: "https://optimistic.etherscan.io",
+    },
+    ChainInfo {
+        chain_id: 8453,
+        name: "base",
+        native_token: "ETH",
+        wrapped_native: "0x4200000000000000000000000000000000000006",
+        multicall3: MULTICALL3,
+        block_time_ms: 2_000,
+        explorer_url: "https://basescan.org",
+    },
+    ChainInfo {
+        chain_id: 56,
+        name: "bsc",
+        native_token: "BNB",
+        wrapped_native: "0xbb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c",
+        multicall3: MULTICALL3,
+        block_time_ms: 3_000,
+        explorer_url: "https://bscscan.com",
+    },
+    ChainInfo {
+        chain_id: 137,
+        name: "polygon",
+        native_token: "POL",
+        wrapped_native: "0x0d500B1d8E8eF31E21C99d1Db9A6444d3ADf1270",
+        multicall3: MULTICALL3,
+        block_time_ms: 2_000,
+        explorer_url: "https://polygonscan.com",
+    },
+    // Hardhat and Anvil both default to 31337; no canonical WETH or Multicall3 there.
+    ChainInfo {
+        chain_id: 31337,
+        name: "hardhat",
+        native_token: "ETH",
+        wrapped_native: "",
+        multicall3: "",
+        block_time_ms: 1_000,
+        explorer_url: "",
+    },
+];
+
+pub fn by_id(chain_id: u64) -> Option<&'static ChainInfo> {
+    CHAINS.iter().find(|c| c.chain_id == chain_id)
+}
+
+pub fn by_name(name: &str) -> Option<&'static ChainInfo> {
+    CHAINS.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+}
+
+/// Accepts either a chain name ("arbitrum") or a numeric chain id ("42161").
+pub fn resolve(chain: &str) -> Result<&'static ChainInfo> {
+    let found = match chain.trim().parse::<u64>() {
+        Ok(id) => by_id(id),
+        Err(_) => by_name(chain.trim()),
+    };
+    found.ok_or_else(|| anyhow!("Unknown chain: {}", chain))
+}
+
+static CHAIN_IDS: OnceLock<Mutex<HashMap<String, u64>>> = OnceLock::new();
+
+/// Asks the node behind `rpc_url` for its chain id; the answer is cached per URL.
+pub async fn fetch_chain_id(rpc_url: &str) -> Result<u64> {
+    let cache = CHAIN_IDS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(id) = cache.lock().unwrap().get(rpc_url) {
+        return Ok(*id);
+    }
+
+    let provider = rpc::provider(rpc_url)?;
+    let id = provider.get_chainid().await?.as_u64();
+    cache.lock().unwrap().insert(rpc_url.to_string(), id);
+    Ok(id)
+}
+
+/// Drops every direction whose provider doesn't serve the chain it declares.
+///
+/// `eth_chainId` is called once per distinct provider URL. Directions without a declared
+/// chain are kept, with a warning naming the chain their provider actually serves.
+pub async fn verify_directions(directions: Vec<ArbitrageDirection>) -> Vec<ArbitrageDirection> {
+    let mut chain_ids: HashMap<String, Option<u64>> = HashMap::new();
+    for dir in &directions {
+        if !chain_ids.contains_key(&dir.provider) {
+            let id = match fetch_chain_id(&dir.provider).await {
+                Ok(id) => Some(id),
+                Err(e) => {
+                    error!("eth_chainId failed for provider: {e:#}");
+                    None
+                }
+            };
+            chain_ids.insert(dir.provider.clone(), id);
+        }
+    }
+
+    directions
+        .into_iter()
+        .filter(|dir| {
+            let Some(actual) = chain_ids[&dir.provider] else {
+                error!("Skipping direction {:?}: provider chain id unknown", dir.path);
+                return false;
+            };
+            let actual_name = by_id(actual).map_or("unknown", |c| c.name);
+
+            let Some(declared) = &dir.chain else {
+                warn!("Direction {:?} declares no chain; provider serves {} ({})", dir.path, actual_name, actual);
+                return true;
+            };
+
+            match resolve(declared) {
+                Ok(chain) if chain.chain_id == actual => true,
+                Ok(chain) => {
+                    error!(
+                        "Skipping direction {:?}: declared {} ({}) but provider serves {} ({})",
+                        dir.path, chain.name, chain.chain_id, actual_name, actual
+                    );
+                    false
+                }
+                Err(e) => {
+                    error!("Skipping direction {:?}: {e:#}", dir.path);
+                    false
+                }
+            }
+        })
+        .inspect(|dir| info!("Chain verified for direction {:?}", dir.path))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{FixtureClient, RpcClient};
+    use serde_json::json;
+
+    fn replay(name: &str) -> RpcClient {
+        let path = format!("{}/tests/fixtures/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+        RpcClient::Fixture(FixtureClient::replay(path).unwrap())
+    }
+
+    fn direction(provider: &str, chain: Option<&str>, tag: &str) -> ArbitrageDirection {
+        serde_json::from_value(json!({
+            "pool_type": "V2",
+            "token0": "0x0000000000000000000000000000000000000001",
+            "token1": "0x0000000000000000000000000000000000000002",
+            "path": [tag],
+            "roi": 0.0,
+            "profit": 0.0,
+            "priceDifference": 0.0,
+            "pool_fee": 0.003,
+            "provider": provider,
+            "chain": chain,
+        }))
+        .unwrap()
+    }
+
+    fn tags(directions: &[ArbitrageDirection]) -> Vec<&str> {
+        directions.iter().map(|d| d.path[0].as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn directions_must_declare_the_chain_their_provider_serves() {
+        // Chain ids are cached per URL, so these URLs are used by this test only.
+        let (mainnet, arbitrum) = ("fixture://chains-mainnet", "fixture://chains-arbitrum");
+        let directions = vec![
+            direction(mainnet, Some("ethereum"), "mainnet-by-name"),
+            direction(mainnet, Some("1"), "mainnet-by-id"),
+            direction(mainnet, Some("arbitrum"), "mainnet-declared-arbitrum"),
+            direction(arbitrum, Some("arbitrum"), "arbitrum"),
+            direction(arbitrum, Some("ethereum"), "arbitrum-declared-mainnet"),
+            direction(arbitrum, Some("goerli"), "unknown-chain"),
+            direction(arbitrum, None, "undeclared"),
+        ];
+
+        let verify = rpc::scoped(arbitrum, replay("v3_arbitrum"), verify_directions(directions));
+        let verified = rpc::scoped(mainnet, replay("v2_ethereum"), verify).await;
+        assert_eq!(tags(&verified), vec!["mainnet-by-name", "mainnet-by-id", "arbitrum", "undeclared"]);
+    }
+
+    #[tokio::test]
+    async fn directions_are_dropped_when_the_chain_id_is_unknown() {
+        let url = "fixture://chains-empty";
+        let silent = RpcClient::Fixture(FixtureClient::replay_entries(Vec::new()));
+        let verified = rpc::scoped(url, silent, verify_directions(vec![direction(url, Some("ethereum"), "dropped")])).await;
+        assert!(verified.is_empty());
+    }
+
+    #[test]
+    fn resolve_accepts_names_and_ids() {
+        assert_eq!(resolve("arbitrum").unwrap().chain_id, 42161);
+        assert_eq!(resolve("Ethereum").unwrap().chain_id, 1);
+        assert_eq!(resolve("8453").unwrap().name, "base");
+        assert!(resolve("goerli").is_err());
+    }
+}
@@ -1,13 +1,39 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
+use std::time::Duration;
 
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub cache: CacheSettings,
+    #[allow(dead_code)]
     #[serde(default)]
     pub test: bool,
+    #[serde(default)]
+    pub rpc: RpcSettings,
+    #[serde(default)]
+    pub devnet: DevnetSettings,
+    #[serde(default)]
+    pub pool_verification: PoolVerificationSettings,
+    #[serde(default)]
+    pub discovery: DiscoverySettings,
+    #[serde(default)]
+    pub route: RouteSettings,
+    #[serde(default)]
+    pub quote: QuoteSettings,
+    #[serde(default)]
+    pub ticks: TickSettings,
+    #[serde(default)]
+    pub verify: VerifySettings,
+    #[serde(default)]
+    pub dry_run: DryRunSettings,
+    /// Deployed FlashSwapArbitrage contracts per chain name ("ethereum", "arbitrum", ...).
+    #[serde(default)]
+    pub contracts: HashMap<String, DeployedContracts>,
+    #[serde(default)]
+    pub execution: ExecutionSettings,
 }
 
 pub fn read_config_or_default() -> Config {
@@ -27,16 +53,7 @@ pub fn read_config_or_default() -> Config {
         Err(e) => log::error!("Failed to read config file: {}", e),
     }
 
-    return config;
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            cache: CacheSettings::default(),
-            test: false
-        }
-    }
+    config
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -47,6 +64,32 @@ pub struct CacheSettings {
     pub save_every: String,
 }
 
+impl CacheSettings {
+    /// Parses `save_every` ("90s", "15m", "2h", "1d"); falls back to one hour.
+    pub fn save_interval(&self) -> Duration {
+        parse_duration(&self.save_every).unwrap_or_else(|| {
+            log::error!("Invalid cache.save_every '{}', using 1h", self.save_every);
+            Duration::from_secs(3600)
+        })
+    }
+}
+
+/// `None` for anything unparsable and for zero, which `tokio::time::interval` rejects.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount.parse().ok()?;
+    let secs = match unit {
+        "s" => amount,
+        "m" => amount * 60,
+        "h" => amount * 3600,
+        "d" => amount * 86400,
+        _ => return None,
+    };
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
 impl Default for CacheSettings {
     fn default() -> Self {
         CacheSettings {
@@ -55,3 +98,461 @@ impl Default for CacheSettings {
         }
     }
 }
+
+/// Per-chain RPC endpoints, keyed by chain name ("ethereum", "arbitrum", ...).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RpcSettings {
+    #[serde(default)]
+    pub chains: HashMap<String, ChainRpcSettings>,
+    #[serde(default)]
+    pub fixtures: FixtureSettings,
+}
+
+impl RpcSettings {
+    /// First endpoint of `chain`; `rpc::provider` maps it back to the chain's failover set.
+    pub fn primary_url(&self, chain: &str) -> Option<String> {
+        self.chains
+            .get(chain)
+            .and_then(|c| c.endpoints.first())
+            .map(|e| e.url.clone())
+    }
+}
+
+/// Record/replay of RPC traffic, one `<dir>/<chain>.json` file per chain.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureSettings {
+    #[serde(default)]
+    pub mode: FixtureMode,
+    #[serde(default = "default_fixtures_dir")]
+    pub dir: String,
+}
+
+impl Default for FixtureSettings {
+    fn default() -> Self {
+        FixtureSettings {
+            mode: FixtureMode::Off,
+            dir: default_fixtures_dir(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FixtureMode {
+    #[default]
+    Off,
+    Record,
+    Replay,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChainRpcSettings {
+    #[serde(default)]
+    pub endpoints: Vec<RpcEndpointSettings>,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    #[serde(default = "default_base_backoff_ms")]
+    pub base_backoff_ms: u64,
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+impl Default for ChainRpcSettings {
+    fn default() -> Self {
+        ChainRpcSettings {
+            endpoints: Vec::new(),
+            max_retries: default_max_retries(),
+            base_backoff_ms: default_base_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RpcEndpointSettings {
+    pub url: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    #[serde(default = "default_requests_per_second")]
+    pub requests_per_second: f64,
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+    /// How long a request may wait for the endpoint's answer before it counts as failed.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_max_retries() -> u32 { 5 }
+fn default_base_backoff_ms() -> u64 { 200 }
+fn default_max_backoff_ms() -> u64 { 5_000 }
+fn default_weight() -> u32 { 1 }
+fn default_requests_per_second() -> f64 { 10.0 }
+fn default_burst() -> u32 { 20 }
+fn default_timeout_ms() -> u64 { 10_000 }
+fn default_fixtures_dir() -> String { "fixtures".to_string() }
+
+/// Local Hardhat/Anvil node running the repo's mock contracts instead of mainnet pools.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DevnetSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_devnet_rpc_url")]
+    pub rpc_url: String,
+    /// Hardhat `artifacts/` directory (`npx hardhat compile`).
+    #[serde(default = "default_artifacts_dir")]
+    pub artifacts_dir: String,
+    /// Defaults to the well-known Hardhat/Anvil account #0.
+    #[serde(default = "default_devnet_private_key")]
+    pub private_key: String,
+    /// Existing token addresses to attach to; mock tokens are deployed when empty.
+    #[serde(default)]
+    pub tokens: Option<[String; 2]>,
+    #[serde(default = "default_devnet_pools")]
+    pub pools: Vec<DevnetPoolSettings>,
+    /// Swap on every pair once, inside a reverted snapshot, and refuse to start unless the
+    /// V2 simulator predicted each output exactly.
+    #[serde(default = "default_true")]
+    pub check_swaps: bool,
+}
+
+impl Default for DevnetSettings {
+    fn default() -> Self {
+        DevnetSettings {
+            enabled: false,
+            rpc_url: default_devnet_rpc_url(),
+            artifacts_dir: default_artifacts_dir(),
+            private_key: default_devnet_private_key(),
+            tokens: None,
+            pools: default_devnet_pools(),
+            check_swaps: true,
+        }
+    }
+}
+
+/// A pair to attach to (`address`) or to deploy and seed with the given whole-token reserves.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DevnetPoolSettings {
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub reserve0: f64,
+    #[serde(default)]
+    pub reserve1: f64,
+    /// Swap fee of a deployed pair; attached pairs are assumed to charge it too.
+    #[serde(default = "default_devnet_fee")]
+    pub fee: f64,
+}
+
+fn default_true() -> bool { true }
+fn default_devnet_rpc_url() -> String { "http://127.0.0.1:8545".to_string() }
+fn default_artifacts_dir() -> String { "artifacts".to_string() }
+fn default_devnet_private_key() -> String {
+    "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80".to_string()
+}
+fn default_devnet_fee() -> f64 { 0.003 }
+fn default_devnet_pools() -> Vec<DevnetPoolSettings> {
+    [(1_000.0, 2_500_000.0), (1_000.0, 2_520_000.0), (1_000.0, 2_480_000.0)]
+        .into_iter()
+        .map(|(reserve0, reserve1)| DevnetPoolSettings { address: None, reserve0, reserve1, fee: default_devnet_fee() })
+        .collect()
+}
+
+/// Factory lookups confirming each pool in a direction is the canonical one for its tokens.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PoolVerificationSettings {
+    #[serde(default)]
+    pub mode: VerificationMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationMode {
+    Off,
+    Warn,
+    #[default]
+    Reject,
+}
+
+/// Pool discovery from factory creation events, per chain name ("ethereum", "arbitrum", ...).
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscoverySettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Blocks per `eth_getLogs` request; halved automatically when a provider rejects a range.
+    #[serde(default = "default_block_chunk")]
+    pub block_chunk: u64,
+    #[serde(default = "default_poll_every")]
+    pub poll_every: String,
+    #[serde(default)]
+    pub chains: HashMap<String, ChainDiscoverySettings>,
+}
+
+impl DiscoverySettings {
+    pub fn poll_interval(&self) -> Duration {
+        parse_duration(&self.poll_every).unwrap_or_else(|| {
+            log::error!("Invalid discovery.poll_every '{}', using 1m", self.poll_every);
+            Duration::from_secs(60)
+        })
+    }
+}
+
+impl Default for DiscoverySettings {
+    fn default() -> Self {
+        DiscoverySettings {
+            enabled: false,
+            block_chunk: default_block_chunk(),
+            poll_every: default_poll_every(),
+            chains: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChainDiscoverySettings {
+    /// First block of the initial scan; when unset, discovery starts at the current head.
+    #[serde(default)]
+    pub from_block: Option<u64>,
+    /// Allowlisted token address -> minimum whole-token balance a pool must hold.
+    /// Both tokens of a pool must be listed; an empty map accepts every pair.
+    #[serde(default)]
+    pub tokens: HashMap<String, f64>,
+    /// Minimum whole-token balance for tokens without an allowlisted minimum.
+    #[serde(default = "default_min_balance")]
+    pub min_balance: f64,
+}
+
+impl Default for ChainDiscoverySettings {
+    fn default() -> Self {
+        ChainDiscoverySettings {
+            from_block: None,
+            tokens: HashMap::new(),
+            min_balance: default_min_balance(),
+        }
+    }
+}
+
+fn default_block_chunk() -> u64 { 2_000 }
+fn default_min_balance() -> f64 { 1.0 }
+
+/// Multi-hop cycle search over the pools of all directions on a provider.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouteSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub search: RouteSearch,
+    /// Longest cycle the exhaustive search enumerates.
+    #[serde(default = "default_max_hops")]
+    pub max_hops: usize,
+    /// Largest input the exhaustive search sizes a cycle with, in whole start tokens.
+    #[serde(default = "default_route_amount")]
+    pub amount: f64,
+    /// How often the graph search re-reads pools that emitted logs.
+    #[serde(default = "default_poll_every")]
+    pub poll_every: String,
+    /// Blocks per `eth_getLogs` request when the graph search looks for those logs.
+    #[serde(default = "default_block_chunk")]
+    pub block_chunk: u64,
+}
+
+impl RouteSettings {
+    pub fn poll_interval(&self) -> Duration {
+        parse_duration(&self.poll_every).unwrap_or_else(|| {
+            log::error!("Invalid route.poll_every '{}', using 1m", self.poll_every);
+            Duration::from_secs(60)
+        })
+    }
+}
+
+impl Default for RouteSettings {
+    fn default() -> Self {
+        RouteSettings {
+            enabled: false,
+            search: RouteSearch::default(),
+            max_hops: default_max_hops(),
+            amount: default_route_amount(),
+            poll_every: default_poll_every(),
+            block_chunk: default_block_chunk(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteSearch {
+    /// Every cycle up to `max_hops` from each direction's token0, once per batch.
+    #[default]
+    Exhaustive,
+    /// Bellman-Ford over a token graph per provider, kept current in the background.
+    Graph,
+}
+
+fn default_max_hops() -> usize { 3 }
+fn default_route_amount() -> f64 { 1.0 }
+
+/// Split-routed quotes per pair over the directions' pools.
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuoteSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Order size quoted, in whole token0 of the pair.
+    #[serde(default = "default_route_amount")]
+    pub amount: f64,
+}
+
+impl Default for QuoteSettings {
+    fn default() -> Self {
+        QuoteSettings {
+            enabled: false,
+            amount: default_route_amount(),
+        }
+    }
+}
+fn default_poll_every() -> String { "1m".to_string() }
+
+/// Initialized-tick fetching for concentrated-liquidity pools.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TickSettings {
+    /// Watch cached pools for Mint/Burn and drop their ticks when liquidity changes.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Bitmap words read on each side of the current tick's word; one word spans 256 tick spacings.
+    #[serde(default = "default_word_range")]
+    pub word_range: u16,
+    #[serde(default = "default_tick_poll_every")]
+    pub poll_every: String,
+    /// Periphery TickLens address per chain name. Pools on chains without one are read
+    /// through `ticks(int24)`, one call per initialized tick.
+    #[serde(default)]
+    pub lens: HashMap<String, String>,
+}
+
+impl TickSettings {
+    pub fn poll_interval(&self) -> Duration {
+        parse_duration(&self.poll_every).unwrap_or_else(|| {
+            log::error!("Invalid ticks.poll_every '{}', using 15s", self.poll_every);
+            Duration::from_secs(15)
+        })
+    }
+}
+
+impl Default for TickSettings {
+    fn default() -> Self {
+        TickSettings {
+            enabled: false,
+            word_range: default_word_range(),
+            poll_every: default_tick_poll_every(),
+            lens: HashMap::new(),
+        }
+    }
+}
+
+fn default_word_range() -> u16 { 2 }
+fn default_tick_poll_every() -> String { "15s".to_string() }
+
+/// Differential check of the simulators against the DEXes' own quoters instead of a
+/// simulation run.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VerifySettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Input per leg, in whole tokens of the leg's input token.
+    #[serde(default = "default_verify_amount")]
+    pub amount: f64,
+}
+
+impl Default for VerifySettings {
+    fn default() -> Self {
+        VerifySettings { enabled: false, amount: default_verify_amount() }
+    }
+}
+
+fn default_verify_amount() -> f64 { 1.0 }
+
+fn default_max_price_impact() -> f64 { 0.01 }
+
+/// `eth_call` of the flash-swap contracts for directions the simulators find profitable.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DryRunSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Largest flash loan the sizer may pick, in whole tokens of the direction's `token0`.
+    #[serde(default = "default_verify_amount")]
+    pub amount: f64,
+    /// Largest move of the sell pool's price the loan may cause, as a fraction (0.01 = 1%).
+    #[serde(default = "default_max_price_impact")]
+    pub max_price_impact: f64,
+}
+
+impl Default for DryRunSettings {
+    fn default() -> Self {
+        DryRunSettings {
+            enabled: false,
+            amount: default_verify_amount(),
+            max_price_impact: default_max_price_impact(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeployedContracts {
+    #[serde(default)]
+    pub v2: Option<String>,
+    #[serde(default)]
+    pub v3: Option<String>,
+}
+
+/// Turning dry-run-confirmed opportunities into `initiateFlashSwap` transactions. With
+/// `send` off (the default) transactions are built and gas-estimated but never signed.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecutionSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub send: bool,
+    /// Environment variable holding the signer's private key; the key itself never goes
+    /// in config.json.
+    #[serde(default = "default_private_key_env")]
+    pub private_key_env: String,
+    /// Smallest expected profit worth sending, in whole tokens of the borrowed token. Also
+    /// passed to the contract as `minProfit`, so a worse fill reverts on-chain.
+    #[serde(default)]
+    pub min_profit: f64,
+    /// Headroom added on top of `eth_estimateGas`, e.g. 0.2 for +20%.
+    #[serde(default = "default_gas_margin")]
+    pub gas_margin: f64,
+}
+
+impl Default for ExecutionSettings {
+    fn default() -> Self {
+        ExecutionSettings {
+            enabled: false,
+            send: false,
+            private_key_env: default_private_key_env(),
+            min_profit: 0.0,
+            gas_margin: default_gas_margin(),
+        }
+    }
+}
+
+fn default_private_key_env() -> String { "ARB_PRIVATE_KEY".to_string() }
+fn default_gas_margin() -> f64 { 0.2 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_parse_per_unit_and_reject_zero() {
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 15m "), Some(Duration::from_secs(900)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), Some(Duration::from_secs(86400)));
+        for invalid in ["0s", "0h", "", "15", "m", "1w", "-1s"] {
+            assert_eq!(parse_duration(invalid), None, "{invalid:?}");
+        }
+
+        let cache = CacheSettings { save_every: "0s".to_string(), ..Default::default() };
+        assert_eq!(cache.save_interval(), Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,3 @@
+use ethers::contract::abigen;
+
+abigen!(ERC20Mock, "abi/ERC20Mock.json");
@@ -0,0 +1,3 @@
+use ethers::contract::abigen;
+
+abigen!(FlashSwapArbitrageV2, "abi/FlashSwapArbitrageV2.json");
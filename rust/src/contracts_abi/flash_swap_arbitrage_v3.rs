@@ -0,0 +1,3 @@
+use ethers::contract::abigen;
+
+abigen!(FlashSwapArbitrageV3, "abi/FlashSwapArbitrageV3.json");
@@ -0,0 +1,70 @@
+// Bindings for the contracts in `contracts/`, generated from the Hardhat artifacts checked
+// in under `rust/abi`. After changing a contract run `npm run export-abi` in the repository
+// root to recompile and refresh them.
+pub mod flash_swap_arbitrage_v2;
+pub mod flash_swap_arbitrage_v3;
+pub mod erc20_mock;
+pub mod uniswap_pool_mock;
+pub mod swap_router_mock;
+
+use anyhow::{anyhow, Result};
+use ethers::types::Bytes;
+
+const ARTIFACTS: &[(&str, &str)] = &[
+    ("FlashSwapArbitrageV2", include_str!("../../abi/FlashSwapArbitrageV2.json")),
+    ("FlashSwapArbitrageV3", include_str!("../../abi/FlashSwapArbitrageV3.json")),
+    ("ERC20Mock", include_str!("../../abi/ERC20Mock.json")),
+    ("UniswapPoolMock", include_str!("../../abi/UniswapPoolMock.json")),
+    ("SwapRouterMock", include_str!("../../abi/SwapRouterMock.json")),
+];
+
+/// Creation bytecode from the checked-in artifact of `name`.
+pub fn creation_code(name: &str) -> Result<Bytes> {
+    let (_, artifact) = ARTIFACTS
+        .iter()
+        .find(|(contract, _)| *contract == name)
+        .ok_or_else(|| anyhow!("No artifact for {}", name))?;
+    let json: serde_json::Value = serde_json::from_str(artifact)?;
+    let bytecode: Bytes = json["bytecode"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Artifact {} has no bytecode", name))?
+        .parse()?;
+    if bytecode.is_empty() {
+        return Err(anyhow!("rust/abi/{}.json has no bytecode (run `npm run export-abi`)", name));
+    }
+    Ok(bytecode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::erc20_mock::TransferFilter;
+    use super::flash_swap_arbitrage_v2::InitiateFlashSwapCall;
+    use super::flash_swap_arbitrage_v3::FlashSwapCall;
+    use super::*;
+    use ethers::contract::{EthCall, EthEvent};
+    use ethers::utils::keccak256;
+
+    #[test]
+    fn bindings_match_the_contract_signatures() {
+        assert_eq!(
+            InitiateFlashSwapCall::abi_signature(),
+            "initiateFlashSwap(address,address,address,address,address,uint256,uint256)"
+        );
+        assert_eq!(FlashSwapCall::abi_signature(), "flashSwap(address,address,address,address,address,uint256,uint256)");
+        assert_eq!(TransferFilter::signature().0, keccak256("Transfer(address,address,uint256)"));
+    }
+
+    #[test]
+    #[ignore = "needs compiled artifacts: run `npm run export-abi` in the repository root"]
+    fn creation_code_comes_from_the_checked_in_artifacts() {
+        for (name, _) in ARTIFACTS {
+            let code = creation_code(name).unwrap_or_else(|e| panic!("{}: {e:#}", name));
+            assert!(!code.is_empty(), "{} has no creation code", name);
+        }
+    }
+
+    #[test]
+    fn unknown_contracts_have_no_artifact() {
+        assert!(creation_code("Missing").is_err());
+    }
+}
@@ -0,0 +1,3 @@
+use ethers::contract::abigen;
+
+abigen!(SwapRouterMock, "abi/SwapRouterMock.json");
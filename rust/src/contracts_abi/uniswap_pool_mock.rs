@@ -0,0 +1,3 @@
+use ethers::contract::abigen;
+
+abigen!(UniswapPoolMock, "abi/UniswapPoolMock.json");
@@ -0,0 +1,367 @@
+use crate::config::DevnetSettings;
+use crate::dexes::{AmmKind, PoolVariant};
+use crate::pools_abi::erc20_abi::ERC20;
+use crate::pools::PoolDescriptor;
+use crate::pools_abi::uniswap_v2_pair_mock::UniswapV2PairMock;
+use crate::rpc;
+use crate::simulator::amm::fee_pips;
+use crate::threads::SimulateTradeLoopV2;
+use crate::units::{TokenAmount, TokenRef};
+use crate::ArbitrageDirection;
+use anyhow::{anyhow, Context, Result};
+use ethers::abi::{Abi, Tokenize};
+use ethers::contract::ContractFactory;
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, Bytes, U256};
+use log::{info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub type DevnetClient = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+/// ABI and creation bytecode from a Hardhat artifact.
+pub struct Artifact {
+    pub abi: Abi,
+    pub bytecode: Bytes,
+}
+
+/// Reads `<artifacts_dir>/contracts/<Name>.sol/<Name>.json`.
+pub fn load_artifact(artifacts_dir: impl AsRef<Path>, name: &str) -> Result<Artifact> {
+    let path = artifacts_dir
+        .as_ref()
+        .join("contracts")
+        .join(format!("{}.sol", name))
+        .join(format!("{}.json", name));
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read artifact {} (run `npx hardhat compile`)", path.display()))?;
+    let json: serde_json::Value = serde_json::from_str(&content)?;
+
+    let abi: Abi = serde_json::from_value(json["abi"].clone())?;
+    let bytecode: Bytes = json["bytecode"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Artifact {} has no bytecode", name))?
+        .parse()?;
+
+    Ok(Artifact { abi, bytecode })
+}
+
+/// Connection to a local Hardhat/Anvil node with a funded signer.
+///
+/// Writes (deploy, seed, swap) go through `client`; the simulators keep reading through
+/// `crate::rpc::provider(rpc_url)` exactly as they do against mainnet.
+pub struct Devnet {
+    pub rpc_url: String,
+    pub client: Arc<DevnetClient>,
+    artifacts_dir: PathBuf,
+}
+
+impl Devnet {
+    pub async fn connect(settings: &DevnetSettings) -> Result<Self> {
+        let provider = Provider::<Http>::try_from(settings.rpc_url.clone())?;
+        let chain_id = provider
+            .get_chainid()
+            .await
+            .with_context(|| format!("No local node at {}", settings.rpc_url))?;
+        let wallet = settings
+            .private_key
+            .parse::<LocalWallet>()?
+            .with_chain_id(chain_id.as_u64());
+
+        info!("Devnet {} (chain id {}), signer {:?}", settings.rpc_url, chain_id, wallet.address());
+
+        Ok(Self {
+            rpc_url: settings.rpc_url.clone(),
+            client: Arc::new(SignerMiddleware::new(provider, wallet)),
+            artifacts_dir: PathBuf::from(&settings.artifacts_dir),
+        })
+    }
+
+    pub fn signer(&self) -> Address {
+        self.client.address()
+    }
+
+    pub async fn deploy<T: Tokenize>(&self, name: &str, args: T) -> Result<Address> {
+        let artifact = load_artifact(&self.artifacts_dir, name)?;
+        let factory = ContractFactory::new(artifact.abi, artifact.bytecode, self.client.clone());
+        let contract = factory.deploy(args)?.send().await?;
+        info!("Deployed {} at {:?}", name, contract.address());
+        Ok(contract.address())
+    }
+
+    /// `ERC20Mock` (18 decimals) with the whole supply minted to the signer.
+    pub async fn deploy_token(&self, name: &str, symbol: &str, supply: U256) -> Result<Address> {
+        self.deploy("ERC20Mock", (name.to_string(), symbol.to_string(), self.signer(), supply))
+            .await
+    }
+
+    /// `UniswapV2PairMock` charging `fee`, with tokens sorted the way a real factory would.
+    pub async fn deploy_pair(&self, token_a: Address, token_b: Address, fee: f64) -> Result<Address> {
+        let (token0, token1) = if token_a < token_b { (token_a, token_b) } else { (token_b, token_a) };
+        self.deploy("UniswapV2PairMock", (token0, token1, fee_pips(fee).as_u32())).await
+    }
+
+    /// Transfers raw amounts of each pair token from the signer into the pair and syncs reserves.
+    pub async fn seed_pair(&self, pair: Address, amount0: U256, amount1: U256) -> Result<()> {
+        let pair = UniswapV2PairMock::new(pair, self.client.clone());
+        let token0 = pair.token_0().call().await?;
+        let token1 = pair.token_1().call().await?;
+
+        ERC20::new(token0, self.client.clone())
+            .transfer(pair.address(), amount0)
+            .send()
+            .await?
+            .await?;
+        ERC20::new(token1, self.client.clone())
+            .transfer(pair.address(), amount1)
+            .send()
+            .await?
+            .await?;
+        pair.sync().send().await?.await?;
+
+        info!("Seeded pair {:?}: reserve0={} reserve1={}", pair.address(), amount0, amount1);
+        Ok(())
+    }
+
+    /// Sends `amount_in` of `token_in` to the pair and asks for exactly `amount_out` back.
+    ///
+    /// Before the real swap the pair is asked (via `eth_call`) for `amount_out + 1`, which must
+    /// revert with the K check — i.e. `amount_out` is the most the pool would actually pay.
+    /// Returns the signer's `token_out` balance delta.
+    pub async fn swap_exact_in(
+        &self,
+        pair: Address,
+        token_in: Address,
+        amount_in: U256,
+        amount_out: U256,
+    ) -> Result<U256> {
+        let pair = UniswapV2PairMock::new(pair, self.client.clone());
+        let token0 = pair.token_0().call().await?;
+        let token1 = pair.token_1().call().await?;
+        let (token_out, zero_for_one) = if token_in == token0 { (token1, true) } else { (token0, false) };
+        let outs = |amount: U256| if zero_for_one { (U256::zero(), amount) } else { (amount, U256::zero()) };
+
+        ERC20::new(token_in, self.client.clone())
+            .transfer(pair.address(), amount_in)
+            .send()
+            .await?
+            .await?;
+
+        let (over0, over1) = outs(amount_out + 1);
+        if pair.swap(over0, over1, self.signer(), Bytes::new()).call().await.is_ok() {
+            return Err(anyhow!("Pair would pay more than {} for {}", amount_out, amount_in));
+        }
+
+        let out = ERC20::new(token_out, self.client.clone());
+        let before = out.balance_of(self.signer()).call().await?;
+        let (out0, out1) = outs(amount_out);
+        pair.swap(out0, out1, self.signer(), Bytes::new()).send().await?.await?;
+        let after = out.balance_of(self.signer()).call().await?;
+
+        Ok(after - before)
+    }
+
+    /// Simulates selling `amount_in` of `token_in` on `pair` with `SimulateTradeLoopV2`, swaps
+    /// it on the pair for real and fails unless both agree to the wei.
+    pub async fn check_pair(&self, pair: Address, token_in: Address, amount_in: U256) -> Result<U256> {
+        let simulated = simulated_out(&self.rpc_url, pair, token_in, amount_in).await?;
+        let received = self.swap_exact_in(pair, token_in, amount_in, simulated).await?;
+        if received != simulated {
+            return Err(anyhow!("Pair {:?} paid {} for {}, simulator expected {}", pair, received, amount_in, simulated));
+        }
+        info!("Pair {:?} matches the simulator: {} in, {} out", pair, amount_in, received);
+        Ok(received)
+    }
+
+    /// Runs `check_pair` on every pair inside an `evm_snapshot`, reverted afterwards so the
+    /// seeded reserves are what the engine sees.
+    pub async fn check_pairs(&self, pairs: &[Address], token_in: Address) -> Result<()> {
+        let snapshot: U256 = self.client.provider().request("evm_snapshot", ()).await?;
+        let mut result = Ok(());
+        for pair in pairs {
+            let mock = UniswapV2PairMock::new(*pair, self.client.clone());
+            let (reserve0, reserve1, _) = mock.get_reserves().call().await?;
+            let reserve_in = if token_in == mock.token_0().call().await? { reserve0 } else { reserve1 };
+            // A 0.1% trade moves the price without draining the pair.
+            if let Err(e) = self.check_pair(*pair, token_in, U256::from(reserve_in / 1_000)).await {
+                result = Err(e);
+                break;
+            }
+        }
+        let reverted: bool = self.client.provider().request("evm_revert", [snapshot]).await?;
+        if !reverted {
+            warn!("evm_revert({}) failed; check swaps stayed on the devnet", snapshot);
+        }
+        result
+    }
+}
+
+/// What `SimulateTradeLoopV2` expects a `UniswapV2PairMock` to pay for `amount_in` of
+/// `token_in`, with the reserves, fee and token decimals read from the chain.
+pub async fn simulated_out(rpc_url: &str, pair: Address, token_in: Address, amount_in: U256) -> Result<U256> {
+    let client = rpc::provider(rpc_url)?;
+    let mock = UniswapV2PairMock::new(pair, client.clone());
+    let (reserve0, reserve1, _) = mock.get_reserves().call().await?;
+    let (token0, token1) = (mock.token_0().call().await?, mock.token_1().call().await?);
+    let (token_out, reserve_in, reserve_out) = if token_in == token0 {
+        (token1, reserve0, reserve1)
+    } else if token_in == token1 {
+        (token0, reserve1, reserve0)
+    } else {
+        return Err(anyhow!("Pair {:?} doesn't trade {:?}", pair, token_in));
+    };
+    let fee = mock.fee().call().await? as f64 / 1_000_000.0;
+    let decimals_in = ERC20::new(token_in, client.clone()).decimals().call().await?;
+    let decimals_out = ERC20::new(token_out, client).decimals().call().await?;
+
+    let (ref_in, ref_out) = (TokenRef::new(token_in, decimals_in), TokenRef::new(token_out, decimals_out));
+    let sim = SimulateTradeLoopV2::new(Some(fee));
+    let simulated = sim
+        .simulate_price_after_swap(
+            &TokenAmount::new(U256::from(reserve_in), ref_in),
+            &TokenAmount::new(U256::from(reserve_out), ref_out),
+            &TokenAmount::new(amount_in, ref_in),
+            false,
+            false,
+        )
+        .await?;
+    Ok(simulated.amount_out.raw)
+}
+
+/// Deploys (or attaches to) the configured tokens and pairs and returns a V2 direction over them.
+pub async fn setup(settings: &DevnetSettings) -> Result<ArbitrageDirection> {
+    if settings.pools.len() < 3 {
+        return Err(anyhow!("Devnet needs at least 3 pools, got {}", settings.pools.len()));
+    }
+    let devnet = Devnet::connect(settings).await?;
+    let supply = U256::exp10(30);
+
+    let (token_a, token_b) = match &settings.tokens {
+        Some([a, b]) => (a.parse::<Address>()?, b.parse::<Address>()?),
+        None => (
+            devnet.deploy_token("Mock WETH", "mWETH", supply).await?,
+            devnet.deploy_token("Mock USDC", "mUSDC", supply).await?,
+        ),
+    };
+
+    let mut path = Vec::with_capacity(settings.pools.len());
+    let mut pools = Vec::with_capacity(settings.pools.len());
+    for pool in &settings.pools {
+        let address = match &pool.address {
+            Some(address) => address.parse::<Address>()?,
+            None => {
+                let pair = devnet.deploy_pair(token_a, token_b, pool.fee).await?;
+                // reserve0/reserve1 are given in token_a/token_b order, 18 decimals each
+                let (amount_a, amount_b) = (to_raw(pool.reserve0), to_raw(pool.reserve1));
+                let (amount0, amount1) = if token_a < token_b { (amount_a, amount_b) } else { (amount_b, amount_a) };
+                devnet.seed_pair(pair, amount0, amount1).await?;
+                pair
+            }
+        };
+        path.push(format!("{:?}", address));
+        pools.push(PoolDescriptor { address, kind: AmmKind::V2, variant: PoolVariant::UniswapV2, dex: None, fee: pool.fee });
+    }
+
+    if settings.check_swaps {
+        let pairs: Vec<Address> = pools.iter().map(|p| p.address).collect();
+        devnet.check_pairs(&pairs, token_a).await?;
+    }
+
+    Ok(ArbitrageDirection {
+        pool_type: "V2".to_string(),
+        token0: format!("{:?}", token_a),
+        token1: format!("{:?}", token_b),
+        path,
+        roi: 0.0,
+        profit: 0.0,
+        priceDifference: 0.0,
+        pool_fee: Some(settings.pools[0].fee),
+        provider: devnet.rpc_url.clone(),
+        chain: Some("hardhat".to_string()),
+        pools,
+    })
+}
+
+fn to_raw(amount: f64) -> U256 {
+    U256::from((amount * 1e6).round() as u128) * U256::exp10(12)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> DevnetSettings {
+        DevnetSettings {
+            artifacts_dir: format!("{}/../artifacts", env!("CARGO_MANIFEST_DIR")),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs `npx hardhat node` and `npx hardhat compile`"]
+    async fn v2_simulator_matches_mock_pair_swap() {
+        let devnet = Devnet::connect(&settings()).await.unwrap();
+        let supply = U256::exp10(30);
+        let weth = devnet.deploy_token("Mock WETH", "mWETH", supply).await.unwrap();
+        let usdc = devnet.deploy_token("Mock USDC", "mUSDC", supply).await.unwrap();
+        let pair = devnet.deploy_pair(weth, usdc, 0.003).await.unwrap();
+
+        let (reserve_weth, reserve_usdc) = (to_raw(1_000.0), to_raw(2_500_000.0));
+        let (seed0, seed1) = if weth < usdc { (reserve_weth, reserve_usdc) } else { (reserve_usdc, reserve_weth) };
+        devnet.seed_pair(pair, seed0, seed1).await.unwrap();
+
+        let received = devnet.check_pair(pair, weth, to_raw(1.5)).await.unwrap();
+        assert!(received > to_raw(3_700.0) && received < to_raw(3_750.0), "received {received}");
+    }
+
+    #[tokio::test]
+    async fn simulated_out_reads_the_fee_and_decimals_from_the_chain() {
+        use crate::pools_abi::erc20_abi::DecimalsCall;
+        use crate::pools_abi::uniswap_v2_pair_mock::{FeeCall, GetReservesCall, GetReservesReturn, Token0Call, Token1Call};
+        use crate::rpc::fixture::eth_call_entry;
+        use crate::rpc::{FixtureClient, RpcClient};
+        use crate::simulator::amm::v2_amount_out;
+
+        const URL: &str = "fixture://devnet-pair";
+        let (pair, weth, usdc) = (Address::repeat_byte(0x20), Address::repeat_byte(0x2e), Address::repeat_byte(0x2f));
+        // 1000 WETH / 2.5M USDC (6 decimals) on a 0.25% pair.
+        let (reserve_weth, reserve_usdc) = (1_000 * 10u128.pow(18), 2_500_000 * 10u128.pow(6));
+        let reserves = GetReservesReturn { reserve_0: reserve_weth, reserve_1: reserve_usdc, block_timestamp_last: 0 };
+        let entries = vec![
+            eth_call_entry(pair, "latest", GetReservesCall, reserves),
+            eth_call_entry(pair, "latest", Token0Call, weth),
+            eth_call_entry(pair, "latest", Token1Call, usdc),
+            eth_call_entry(pair, "latest", FeeCall, 2_500u32),
+            eth_call_entry(weth, "latest", DecimalsCall, 18u8),
+            eth_call_entry(usdc, "latest", DecimalsCall, 6u8),
+        ];
+
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        let out = rpc::scoped(URL, client, simulated_out(URL, pair, weth, U256::exp10(18))).await.unwrap();
+        let expected = v2_amount_out(U256::from(reserve_weth), U256::from(reserve_usdc), U256::exp10(18), 0.0025).unwrap();
+        assert_eq!(out, expected);
+        assert_ne!(out, v2_amount_out(U256::from(reserve_weth), U256::from(reserve_usdc), U256::exp10(18), 0.003).unwrap());
+    }
+
+    #[tokio::test]
+    async fn setup_rejects_too_few_pools_before_connecting() {
+        let settings = DevnetSettings {
+            // Nothing listens here: reaching the node would fail with a different error.
+            rpc_url: "http://127.0.0.1:1".to_string(),
+            pools: settings().pools.into_iter().take(2).collect(),
+            ..settings()
+        };
+        let err = setup(&settings).await.unwrap_err();
+        assert!(err.to_string().contains("at least 3 pools"), "{err:#}");
+    }
+
+    #[tokio::test]
+    #[ignore = "needs `npx hardhat node` and `npx hardhat compile`"]
+    async fn setup_builds_a_direction_the_engine_can_simulate() {
+        let dir = setup(&settings()).await.unwrap();
+        let (_, _, sell, buy, _) = crate::simulator::simulate_direction(&dir).await.unwrap();
+        assert!(sell > 0.0 && buy > 0.0);
+    }
+}
@@ -0,0 +1,90 @@
+use ethers::types::Address;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AmmKind {
+    V2,
+    V3,
+}
+
+impl AmmKind {
+    /// Matches the `pool_type` strings used in `pools_to_arbitrage.json`.
+    pub fn from_pool_type(pool_type: &str) -> Option<Self> {
+        match pool_type {
+            "V2" => Some(AmmKind::V2),
+            "V3" => Some(AmmKind::V3),
+            _ => None,
+        }
+    }
+
+    pub fn pool_type(&self) -> &'static str {
+        match self {
+            AmmKind::V2 => "V2",
+            AmmKind::V3 => "V3",
+        }
+    }
+}
+
+/// ABI flavour of a pool; decides which adapter reads its state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PoolVariant {
+    UniswapV2,
+    /// Uniswap V3 and forks keeping its `slot0()` layout (Sushi V3).
+    UniswapV3,
+    /// `slot0()` with `uint32 feeProtocol`.
+    PancakeV3,
+    /// `globalState()` instead of `slot0()`, fee set dynamically by the pool (Camelot V3).
+    Algebra,
+}
+
+/// A DEX deployment: one factory on one chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DexInfo {
+    pub name: &'static str,
+    pub chain_id: u64,
+    pub kind: AmmKind,
+    pub variant: PoolVariant,
+    pub factory: &'static str,
+    /// Swap fee shared by every pool of a V2 factory; V3 and Algebra pools carry their own.
+    pub fee: Option<f64>,
+    /// UniswapV2Router02 for V2 factories, QuoterV2 for V3 ones; `None` where the DEX has
+    /// no quoter with the Uniswap ABI (Algebra).
+    pub quoter: Option<&'static str>,
+}
+
+impl DexInfo {
+    pub fn factory_address(&self) -> Address {
+        self.factory.parse().expect("valid factory address in DEXES")
+    }
+
+    pub fn quoter_address(&self) -> Option<Address> {
+        self.quoter.map(|q| q.parse().expect("valid quoter address in DEXES"))
+    }
+}
+
+pub static DEXES: &[DexInfo] = &[
+    // Ethereum
+    DexInfo { name: "uniswap_v2", chain_id: 1, kind: AmmKind::V2, variant: PoolVariant::UniswapV2, factory: "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f", fee: Some(0.003), quoter: Some("0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D") },
+    DexInfo { name: "sushiswap_v2", chain_id: 1, kind: AmmKind::V2, variant: PoolVariant::UniswapV2, factory: "0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac", fee: Some(0.003), quoter: Some("0xd9e1cE17f2641f24aE83637ab66a2cca9C378B9F") },
+    DexInfo { name: "pancakeswap_v2", chain_id: 1, kind: AmmKind::V2, variant: PoolVariant::UniswapV2, factory: "0x1097053Fd2ea711dad45caCcc45EfF7548fCB362", fee: Some(0.0025), quoter: Some("0xEfF92A263d31888d860bD50809A8D171709b7b1c") },
+    DexInfo { name: "uniswap_v3", chain_id: 1, kind: AmmKind::V3, variant: PoolVariant::UniswapV3, factory: "0x1F98431c8aD98523631AE4a59f267346ea31F984", fee: None, quoter: Some("0x61fFE014bA17989E743c5F6cB21bF9697530B21e") },
+    DexInfo { name: "sushiswap_v3", chain_id: 1, kind: AmmKind::V3, variant: PoolVariant::UniswapV3, factory: "0xbACEB8eC6b9355Dfc0269C18bac9d6E2Bdc29C4F", fee: None, quoter: Some("0x64e8802FE490fa7cc61d3463958199161Bb608A7") },
+    DexInfo { name: "pancakeswap_v3", chain_id: 1, kind: AmmKind::V3, variant: PoolVariant::PancakeV3, factory: "0x0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865", fee: None, quoter: Some("0xB048Bbc1Ee6b733FFfCFb9e9CeF7375518e25997") },
+    // Arbitrum
+    DexInfo { name: "uniswap_v2", chain_id: 42161, kind: AmmKind::V2, variant: PoolVariant::UniswapV2, factory: "0xf1D7CC64Fb4452F05c498126312eBE29f30Fbcf9", fee: Some(0.003), quoter: Some("0x4752ba5DBc23f44D87826276BF6Fd6b1C372aD24") },
+    DexInfo { name: "sushiswap_v2", chain_id: 42161, kind: AmmKind::V2, variant: PoolVariant::UniswapV2, factory: "0xc35DADB65012eC5796536bD9864eD8773aBc74C4", fee: Some(0.003), quoter: Some("0x1b02dA8Cb0d097eB8D57A175b88c7D8b47997506") },
+    DexInfo { name: "pancakeswap_v2", chain_id: 42161, kind: AmmKind::V2, variant: PoolVariant::UniswapV2, factory: "0x02a84c1b3BBD7401a5f7fa98a384EBC70bB5749E", fee: Some(0.0025), quoter: Some("0x8cFe327CEc66d1C090Dd72bd0FF11d690C33a2Eb") },
+    DexInfo { name: "uniswap_v3", chain_id: 42161, kind: AmmKind::V3, variant: PoolVariant::UniswapV3, factory: "0x1F98431c8aD98523631AE4a59f267346ea31F984", fee: None, quoter: Some("0x61fFE014bA17989E743c5F6cB21bF9697530B21e") },
+    DexInfo { name: "sushiswap_v3", chain_id: 42161, kind: AmmKind::V3, variant: PoolVariant::UniswapV3, factory: "0x1af415a1EbA07a4986a52B6f2e7dE7003D82231e", fee: None, quoter: Some("0x0524E833cCD057e4d7A296e3aaAb9f7675964Ce1") },
+    DexInfo { name: "pancakeswap_v3", chain_id: 42161, kind: AmmKind::V3, variant: PoolVariant::PancakeV3, factory: "0x0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865", fee: None, quoter: Some("0xB048Bbc1Ee6b733FFfCFb9e9CeF7375518e25997") },
+    DexInfo { name: "camelot_v3", chain_id: 42161, kind: AmmKind::V3, variant: PoolVariant::Algebra, factory: "0x1a3c9B1d2F0529D97f2afC5136Cc23e58f1FD35B", fee: None, quoter: None },
+];
+
+pub fn by_factory(chain_id: u64, factory: Address) -> Option<&'static DexInfo> {
+    DEXES
+        .iter()
+        .find(|d| d.chain_id == chain_id && d.factory_address() == factory)
+}
+
+pub fn on_chain(chain_id: u64) -> impl Iterator<Item = &'static DexInfo> {
+    DEXES.iter().filter(move |d| d.chain_id == chain_id)
+}
@@ -0,0 +1,286 @@
+use crate::chains;
+use crate::config::RpcSettings;
+use crate::dexes::AmmKind;
+use crate::discovery::{DiscoveredPool, DiscoveryUpdate};
+use crate::ArbitrageDirection;
+use anyhow::{anyhow, Result};
+use log::{debug, error, info, warn};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The parts of a pool direction generation needs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolRef {
+    pub address: String,
+    pub token0: String,
+    pub token1: String,
+    pub kind: AmmKind,
+}
+
+impl TryFrom<&DiscoveredPool> for PoolRef {
+    type Error = anyhow::Error;
+
+    fn try_from(pool: &DiscoveredPool) -> Result<Self> {
+        let kind = AmmKind::from_pool_type(&pool.pool_type)
+            .ok_or_else(|| anyhow!("Unknown pool_type {} for pool {:?}", pool.pool_type, pool.address))?;
+        Ok(PoolRef {
+            address: format!("{:?}", pool.address),
+            token0: format!("{:?}", pool.token0.address),
+            token1: format!("{:?}", pool.token1.address),
+            kind,
+        })
+    }
+}
+
+/// Pools trading the same pair. `kind` is `None` when the group mixes V2 and V3 pools.
+#[derive(Debug, Clone)]
+pub struct PairGroup {
+    pub pools: Vec<PoolRef>,
+    pub kind: Option<AmmKind>,
+}
+
+/// Order-independent key for a token pair.
+///
+/// Unlike the JS `getPairKey`, addresses are lowercased first so that checksummed and
+/// lowercase spellings of the same token land in one group.
+pub fn pair_key(token0: &str, token1: &str) -> String {
+    let mut tokens = [token0.to_lowercase(), token1.to_lowercase()];
+    tokens.sort();
+    tokens.join("-")
+}
+
+pub fn group_by_pair(pools: &[PoolRef]) -> BTreeMap<String, PairGroup> {
+    let mut groups: BTreeMap<String, PairGroup> = BTreeMap::new();
+    for pool in pools {
+        let group = groups
+            .entry(pair_key(&pool.token0, &pool.token1))
+            .or_insert_with(|| PairGroup { pools: Vec::new(), kind: Some(pool.kind) });
+        if group.kind != Some(pool.kind) {
+            group.kind = None;
+        }
+        group.pools.push(pool.clone());
+    }
+    groups
+}
+
+/// Every 3-combination of distinct pools per pair. Pairs with fewer than three pools are
+/// skipped, and so are mixed V2/V3 pairs when `skip_mixed` is set.
+pub fn triplets(groups: &BTreeMap<String, PairGroup>, skip_mixed: bool) -> Vec<[PoolRef; 3]> {
+    let mut triplets = Vec::new();
+
+    for (pair, group) in groups {
+        if skip_mixed && group.kind.is_none() {
+            debug!("Skipping pair {} (mixed AMM kinds)", pair);
+            continue;
+        }
+
+        let mut seen = HashSet::new();
+        let unique: Vec<&PoolRef> = group
+            .pools
+            .iter()
+            .filter(|p| seen.insert(p.address.to_lowercase()))
+            .collect();
+
+        if unique.len() < 3 {
+            debug!("Skipping pair {} (only {} unique pools)", pair, unique.len());
+            continue;
+        }
+
+        info!("Pair {} has {} unique pools. Generating triplets...", pair, unique.len());
+        for i in 0..unique.len() - 2 {
+            for j in i + 1..unique.len() - 1 {
+                for k in j + 1..unique.len() {
+                    triplets.push([unique[i].clone(), unique[j].clone(), unique[k].clone()]);
+                }
+            }
+        }
+    }
+
+    triplets
+}
+
+/// All orderings of a triplet, i.e. every choice of loan, sell and buy pool.
+pub fn permutations<T: Clone>([a, b, c]: [T; 3]) -> [[T; 3]; 6] {
+    [
+        [a.clone(), b.clone(), c.clone()],
+        [a.clone(), c.clone(), b.clone()],
+        [b.clone(), a.clone(), c.clone()],
+        [b.clone(), c.clone(), a.clone()],
+        [c.clone(), a.clone(), b.clone()],
+        [c, b, a],
+    ]
+}
+
+/// Builds `[loan, sell, buy]` directions for every permutation of every triplet.
+/// Fees and pool descriptors are left for `pools::describe_directions`.
+pub fn generate(pools: &[PoolRef], provider: &str, chain: Option<&str>) -> Vec<ArbitrageDirection> {
+    let groups = group_by_pair(pools);
+    info!("Grouped {} pools into {} token pairs", pools.len(), groups.len());
+
+    let directions: Vec<ArbitrageDirection> = triplets(&groups, true)
+        .into_iter()
+        .flat_map(permutations)
+        .map(|path| ArbitrageDirection {
+            pool_type: path[0].kind.pool_type().to_string(),
+            token0: path[0].token0.clone(),
+            token1: path[0].token1.clone(),
+            path: path.iter().map(|p| p.address.clone()).collect(),
+            roi: 0.0,
+            profit: 0.0,
+            priceDifference: 0.0,
+            pool_fee: None,
+            provider: provider.to_string(),
+            chain: chain.map(str::to_string),
+            pools: Vec::new(),
+        })
+        .collect();
+
+    info!("Generated {} directions", directions.len());
+    directions
+}
+
+/// Directions over the pools `discovery::start` found, one provider per chain.
+pub fn from_discovered(discovered: &HashMap<u64, Vec<DiscoveredPool>>, rpc: &RpcSettings) -> Vec<ArbitrageDirection> {
+    let mut directions = Vec::new();
+    for (chain_id, pools) in discovered {
+        let Some(chain) = chains::by_id(*chain_id) else {
+            error!("Skipping discovered pools on unknown chain {}", chain_id);
+            continue;
+        };
+        let Some(provider) = rpc.primary_url(chain.name) else {
+            error!("Skipping discovered pools on {}: no RPC endpoints configured", chain.name);
+            continue;
+        };
+        let pools: Vec<PoolRef> = pools
+            .iter()
+            .filter_map(|pool| match PoolRef::try_from(pool) {
+                Ok(pool) => Some(pool),
+                Err(e) => {
+                    warn!("Skipping discovered pool on {}: {e:#}", chain.name);
+                    None
+                }
+            })
+            .collect();
+        directions.extend(generate(&pools, &provider, Some(chain.name)));
+    }
+    directions
+}
+
+/// Directions a discovery refresh made possible: those over the chain's liquid pools that
+/// use at least one of the newly liquid ones.
+pub fn from_update(update: &DiscoveryUpdate, rpc: &RpcSettings) -> Vec<ArbitrageDirection> {
+    let added: HashSet<String> = update.added.iter().map(|a| format!("{:?}", a)).collect();
+    from_discovered(&HashMap::from([(update.chain_id, update.pools.clone())]), rpc)
+        .into_iter()
+        .filter(|dir| dir.path.iter().any(|p| added.contains(&p.to_lowercase())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WETH: &str = "0xC02aaa39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+    const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+
+    fn pool(address: &str, token0: &str, token1: &str, kind: AmmKind) -> PoolRef {
+        PoolRef { address: address.to_string(), token0: token0.to_string(), token1: token1.to_string(), kind }
+    }
+
+    #[test]
+    fn discovered_pools_need_a_known_pool_type() {
+        use crate::tokens::TokenMetadata;
+        use ethers::types::Address;
+
+        let token = |byte| TokenMetadata {
+            chain_id: 1,
+            address: Address::repeat_byte(byte),
+            decimals: 18,
+            symbol: String::new(),
+            name: String::new(),
+        };
+        let discovered = DiscoveredPool {
+            address: Address::repeat_byte(1),
+            dex: "uniswap_v3".to_string(),
+            pool_type: "V3".to_string(),
+            token0: token(0xa),
+            token1: token(0xb),
+            fee_tier: Some(500),
+            created_block: 100,
+            liquid: true,
+        };
+        assert_eq!(PoolRef::try_from(&discovered).unwrap().kind, AmmKind::V3);
+
+        let mislabeled = DiscoveredPool { pool_type: "v3-ish".to_string(), ..discovered };
+        assert!(PoolRef::try_from(&mislabeled).is_err());
+    }
+
+    #[test]
+    fn pair_key_ignores_order_and_case() {
+        assert_eq!(pair_key(WETH, USDC), pair_key(&USDC.to_lowercase(), WETH));
+    }
+
+    #[test]
+    fn groups_flag_mixed_kinds() {
+        let pools = [
+            pool("0x01", WETH, USDC, AmmKind::V2),
+            pool("0x02", USDC, WETH, AmmKind::V3),
+            pool("0x03", WETH, "0x6B175474E89094C44Da98b954EedeAC495271d0F", AmmKind::V3),
+        ];
+        let groups = group_by_pair(&pools);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[&pair_key(WETH, USDC)].kind, None);
+        assert_eq!(groups[&pair_key(WETH, USDC)].pools.len(), 2);
+    }
+
+    #[test]
+    fn four_pools_give_four_triplets_and_24_directions() {
+        let pools: Vec<PoolRef> = ["0x01", "0x02", "0x03", "0x04", "0x04"]
+            .iter()
+            .map(|a| pool(a, WETH, USDC, AmmKind::V2))
+            .collect();
+
+        assert_eq!(triplets(&group_by_pair(&pools), true).len(), 4);
+
+        let directions = generate(&pools, "http://localhost:8545", Some("hardhat"));
+        assert_eq!(directions.len(), 24);
+        assert!(directions.iter().all(|d| d.pool_type == "V2" && d.path.len() == 3));
+        let distinct: HashSet<_> = directions.iter().map(|d| d.path.clone()).collect();
+        assert_eq!(distinct.len(), 24);
+    }
+
+    #[test]
+    fn updates_only_yield_directions_through_new_pools() {
+        use crate::tokens::TokenMetadata;
+        use ethers::types::Address;
+
+        let token = |address: &str| TokenMetadata {
+            chain_id: 1,
+            address: address.parse().unwrap(),
+            decimals: 18,
+            symbol: String::new(),
+            name: String::new(),
+        };
+        let pools = (1..=4)
+            .map(|byte| DiscoveredPool {
+                address: Address::repeat_byte(byte),
+                dex: "uniswap_v2".to_string(),
+                pool_type: "V2".to_string(),
+                token0: token(WETH),
+                token1: token(USDC),
+                fee_tier: None,
+                created_block: 100,
+                liquid: true,
+            })
+            .collect();
+        let update = DiscoveryUpdate { chain_id: 1, pools, added: vec![Address::repeat_byte(4)] };
+        let rpc: RpcSettings = serde_json::from_value(serde_json::json!({
+            "chains": { "ethereum": { "endpoints": [{ "url": "http://localhost:8545" }] } }
+        }))
+        .unwrap();
+
+        let directions = from_update(&update, &rpc);
+        assert_eq!(directions.len(), 18);
+        let added = format!("{:?}", Address::repeat_byte(4));
+        assert!(directions.iter().all(|d| d.path.contains(&added)));
+    }
+}
@@ -0,0 +1,619 @@
+use crate::chains::{self, ChainInfo};
+use crate::config::{CacheSettings, ChainDiscoverySettings, DiscoverySettings, RpcSettings};
+use crate::dexes::{self, AmmKind, DexInfo, PoolVariant};
+use crate::pools_abi::algebra_factory::AlgebraFactory;
+use crate::pools_abi::erc20_abi::BalanceOfCall;
+use crate::pools_abi::multicall3::{Call3, Multicall3};
+use crate::pools_abi::uniswap_v2_factory::UniswapV2Factory;
+use crate::pools_abi::uniswap_v3_factory::UniswapV3Factory;
+use crate::rpc::{self, RpcProvider};
+use crate::tokens::{self, TokenMetadata};
+use crate::units::{TokenAmount, TokenRef};
+use anyhow::{anyhow, Context, Result};
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::providers::Middleware;
+use ethers::types::{Address, Bytes, U256};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// A pool found through its factory's creation event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredPool {
+    pub address: Address,
+    /// `DexInfo::name` of the factory that emitted the event.
+    pub dex: String,
+    pub pool_type: String, // "V2" | "V3"
+    pub token0: TokenMetadata,
+    pub token1: TokenMetadata,
+    /// Fee tier from `PoolCreated`, in hundredths of a bip; `None` for V2 pairs and Algebra pools.
+    pub fee_tier: Option<u32>,
+    pub created_block: u64,
+    /// Whether the pool held the configured minimum of both tokens at the last check.
+    pub liquid: bool,
+}
+
+/// Everything discovered on one chain, persisted as `<cache.dir>/pools_<chain>.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PoolStore {
+    pub chain_id: u64,
+    /// Last block whose creation events are included; the next scan starts after it.
+    pub last_block: Option<u64>,
+    pub pools: Vec<DiscoveredPool>,
+}
+
+impl PoolStore {
+    fn load(path: &PathBuf, chain_id: u64) -> Result<Self> {
+        if !path.exists() {
+            return Ok(PoolStore { chain_id, ..Default::default() });
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let store: PoolStore = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        if store.chain_id != chain_id {
+            return Err(anyhow!("{} belongs to chain {}, not {}", path.display(), store.chain_id, chain_id));
+        }
+        Ok(store)
+    }
+
+    fn save(&self, path: &PathBuf) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn liquid_pools(&self) -> impl Iterator<Item = &DiscoveredPool> {
+        self.pools.iter().filter(|p| p.liquid)
+    }
+}
+
+/// Token allowlist with a minimum pool balance per token, and the minimum applied to
+/// tokens the allowlist does not name.
+#[derive(Debug, Clone, Default)]
+pub struct TokenFilter {
+    min_balance: HashMap<Address, f64>,
+    default_min: f64,
+}
+
+impl TokenFilter {
+    pub fn new(tokens: &HashMap<String, f64>, default_min: f64) -> Result<Self> {
+        let mut min_balance = HashMap::new();
+        for (address, min) in tokens {
+            let address: Address = address
+                .parse()
+                .with_context(|| format!("Invalid allowlisted token {}", address))?;
+            min_balance.insert(address, *min);
+        }
+        Ok(TokenFilter { min_balance, default_min })
+    }
+
+    /// An empty filter accepts every pair.
+    pub fn allows(&self, token0: Address, token1: Address) -> bool {
+        self.min_balance.is_empty()
+            || (self.min_balance.contains_key(&token0) && self.min_balance.contains_key(&token1))
+    }
+
+    pub fn min_balance(&self, token: Address) -> f64 {
+        self.min_balance.get(&token).copied().unwrap_or(self.default_min)
+    }
+}
+
+/// A creation event, before token metadata is attached.
+struct CreatedPool {
+    address: Address,
+    token0: Address,
+    token1: Address,
+    fee_tier: Option<u32>,
+    block: u64,
+}
+
+/// Splits `[from, to]` into `eth_getLogs` ranges of at most `size` blocks. A range that
+/// fails is retried at half size, since providers cap `eth_getLogs` by block span or
+/// result count; the next range after a success is full size again.
+pub(crate) struct BlockChunks {
+    start: u64,
+    to: u64,
+    size: u64,
+    chunk: u64,
+}
+
+impl BlockChunks {
+    pub fn new(from: u64, to: u64, size: u64) -> Self {
+        let size = size.max(1);
+        BlockChunks { start: from, to, size, chunk: size }
+    }
+
+    /// The range to read next, `None` once `to` is passed.
+    pub fn range(&self) -> Option<(u64, u64)> {
+        (self.start <= self.to).then(|| (self.start, self.to.min(self.start + self.chunk - 1)))
+    }
+
+    /// Moves past the current range.
+    pub fn advance(&mut self) {
+        if let Some((_, end)) = self.range() {
+            self.start = end + 1;
+        }
+        self.chunk = self.size;
+    }
+
+    /// Halves the current range after it failed with `e`, returning the new chunk size;
+    /// `e` itself once the range is a single block.
+    pub fn shrink(&mut self, e: anyhow::Error) -> Result<u64> {
+        if self.chunk == 1 {
+            return Err(e.context(format!("eth_getLogs failed at block {}", self.start)));
+        }
+        self.chunk = (self.chunk / 2).max(1);
+        Ok(self.chunk)
+    }
+}
+
+/// Reads one factory's creation events in `[from, to]`.
+async fn scan_factory(client: Arc<RpcProvider>, dex: &DexInfo, from: u64, to: u64) -> Result<Vec<CreatedPool>> {
+    let factory = dex.factory_address();
+    let created = match (dex.kind, dex.variant) {
+        (AmmKind::V2, _) => UniswapV2Factory::new(factory, client)
+            .pair_created_filter()
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await?
+            .into_iter()
+            .map(|(e, meta)| CreatedPool {
+                address: e.pair,
+                token0: e.token_0,
+                token1: e.token_1,
+                fee_tier: None,
+                block: meta.block_number.as_u64(),
+            })
+            .collect(),
+        (AmmKind::V3, PoolVariant::Algebra) => AlgebraFactory::new(factory, client)
+            .pool_filter()
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await?
+            .into_iter()
+            .map(|(e, meta)| CreatedPool {
+                address: e.pool,
+                token0: e.token_0,
+                token1: e.token_1,
+                fee_tier: None,
+                block: meta.block_number.as_u64(),
+            })
+            .collect(),
+        (AmmKind::V3, _) => UniswapV3Factory::new(factory, client)
+            .pool_created_filter()
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await?
+            .into_iter()
+            .map(|(e, meta)| CreatedPool {
+                address: e.pool,
+                token0: e.token_0,
+                token1: e.token_1,
+                fee_tier: Some(e.fee),
+                block: meta.block_number.as_u64(),
+            })
+            .collect(),
+    };
+    Ok(created)
+}
+
+/// Keeps one chain's `PoolStore` in sync with its factories.
+pub struct ChainDiscovery {
+    chain: &'static ChainInfo,
+    rpc_url: String,
+    from_block: Option<u64>,
+    block_chunk: u64,
+    filter: TokenFilter,
+    path: PathBuf,
+    store: PoolStore,
+}
+
+impl ChainDiscovery {
+    pub fn new(
+        chain: &'static ChainInfo,
+        rpc_url: impl Into<String>,
+        settings: &ChainDiscoverySettings,
+        block_chunk: u64,
+        cache: &CacheSettings,
+    ) -> Result<Self> {
+        let path = PathBuf::from(&cache.dir).join(format!("pools_{}.json", chain.name));
+        let store = PoolStore::load(&path, chain.chain_id)?;
+        info!("Discovery {}: {} cached pools from {}", chain.name, store.pools.len(), path.display());
+
+        Ok(ChainDiscovery {
+            chain,
+            rpc_url: rpc_url.into(),
+            from_block: settings.from_block,
+            block_chunk: block_chunk.max(1),
+            filter: TokenFilter::new(&settings.tokens, settings.min_balance)?,
+            path,
+            store,
+        })
+    }
+
+    pub fn store(&self) -> &PoolStore {
+        &self.store
+    }
+
+    /// Scans every block since the last refresh, re-checks liquidity and saves the store.
+    /// A failed scan still saves the ranges read before it, so the next refresh resumes there.
+    /// Returns the liquid pools that were not liquid (or not known) before.
+    pub async fn refresh(&mut self) -> Result<Vec<Address>> {
+        let client = rpc::provider(&self.rpc_url)?;
+        let head = client.get_block_number().await?.as_u64();
+
+        let from = match (self.store.last_block, self.from_block) {
+            (Some(last), _) => last + 1,
+            (None, Some(from_block)) => from_block,
+            (None, None) => head,
+        };
+
+        let was_liquid: HashSet<Address> = self.store.liquid_pools().map(|p| p.address).collect();
+
+        if from <= head {
+            match self.scan(client.clone(), from, head).await {
+                Ok(added) => info!("Discovery {}: blocks {}..={} added {} pools", self.chain.name, from, head, added),
+                Err(e) => {
+                    self.store.save(&self.path)?;
+                    return Err(e);
+                }
+            }
+        }
+
+        if let Err(e) = self.check_liquidity(client).await {
+            warn!("Discovery {}: liquidity check failed, keeping previous flags: {e:#}", self.chain.name);
+        }
+        self.store.save(&self.path)?;
+
+        Ok(self
+            .store
+            .liquid_pools()
+            .map(|p| p.address)
+            .filter(|address| !was_liquid.contains(address))
+            .collect())
+    }
+
+    /// Walks `[from, to]` in `BlockChunks` of `block_chunk` blocks.
+    async fn scan(&mut self, client: Arc<RpcProvider>, from: u64, to: u64) -> Result<usize> {
+        let known: HashSet<Address> = self.store.pools.iter().map(|p| p.address).collect();
+        let mut added = 0;
+        let mut chunks = BlockChunks::new(from, to, self.block_chunk);
+
+        while let Some((start, end)) = chunks.range() {
+            let mut created = Vec::new();
+            let mut failed = None;
+            for dex in dexes::on_chain(self.chain.chain_id) {
+                match scan_factory(client.clone(), dex, start, end).await {
+                    Ok(pools) => created.extend(pools.into_iter().map(|p| (dex, p))),
+                    Err(e) => {
+                        failed = Some(e);
+                        break;
+                    }
+                }
+            }
+
+            if let Some(e) = failed {
+                let message = format!("{e:#}");
+                let chunk = chunks.shrink(e)?;
+                warn!("Discovery {}: {message}; retrying with {} blocks", self.chain.name, chunk);
+                continue;
+            }
+
+            for (dex, pool) in created {
+                if known.contains(&pool.address) || !self.filter.allows(pool.token0, pool.token1) {
+                    continue;
+                }
+                match self.describe(dex, pool).await {
+                    Ok(pool) => {
+                        info!("Discovered {} {}/{} pool {:?}", pool.dex, pool.token0.symbol, pool.token1.symbol, pool.address);
+                        self.store.pools.push(pool);
+                        added += 1;
+                    }
+                    Err(e) => error!("Skipping discovered pool: {e:#}"),
+                }
+            }
+
+            // Only advance past ranges that were fully read, so a crash resumes where it stopped.
+            self.store.last_block = Some(end);
+            chunks.advance();
+        }
+
+        Ok(added)
+    }
+
+    async fn describe(&self, dex: &DexInfo, pool: CreatedPool) -> Result<DiscoveredPool> {
+        let token0 = tokens::metadata(&self.rpc_url, pool.token0).await?;
+        let token1 = tokens::metadata(&self.rpc_url, pool.token1).await?;
+        Ok(DiscoveredPool {
+            address: pool.address,
+            dex: dex.name.to_string(),
+            pool_type: dex.kind.pool_type().to_string(),
+            token0,
+            token1,
+            fee_tier: pool.fee_tier,
+            created_block: pool.block,
+            liquid: false,
+        })
+    }
+
+    /// Marks each pool liquid if it holds at least the filter's minimum of both tokens.
+    /// `balanceOf(pool)` works the same for V2 pairs and V3 pools; the calls go out in
+    /// Multicall3 batches.
+    async fn check_liquidity(&mut self, client: Arc<RpcProvider>) -> Result<()> {
+        let multicall: Address = self
+            .chain
+            .multicall3
+            .parse()
+            .map_err(|_| anyhow!("No Multicall3 on {}", self.chain.name))?;
+        let multicall = Multicall3::new(multicall, client);
+
+        for batch in self.store.pools.chunks_mut(LIQUIDITY_BATCH) {
+            let calls = batch
+                .iter()
+                .flat_map(|pool| {
+                    [&pool.token0, &pool.token1].map(|token| Call3 {
+                        target: token.address,
+                        allow_failure: true,
+                        call_data: BalanceOfCall(pool.address).encode().into(),
+                    })
+                })
+                .collect();
+            let results = multicall
+                .aggregate_3(calls)
+                .call()
+                .await
+                .context("Multicall3 balanceOf batch failed")?;
+            for (pool, balances) in batch.iter_mut().zip(results.chunks(2)) {
+                pool.liquid = is_liquid(&self.filter, pool, balances);
+            }
+        }
+
+        let liquid = self.store.liquid_pools().count();
+        info!("Discovery {}: {}/{} pools above liquidity threshold", self.chain.name, liquid, self.store.pools.len());
+        Ok(())
+    }
+}
+
+/// Pools per Multicall3 batch, two `balanceOf` calls each.
+const LIQUIDITY_BATCH: usize = 250;
+
+/// Whether `pool` holds the filter's minimum of both tokens, given the `(success, returnData)`
+/// of `balanceOf(pool)` on token0 and token1. A failed call counts as an empty balance.
+fn is_liquid(filter: &TokenFilter, pool: &DiscoveredPool, balances: &[(bool, Bytes)]) -> bool {
+    let [(ok0, raw0), (ok1, raw1)] = balances else {
+        return false;
+    };
+    [(&pool.token0, *ok0, raw0), (&pool.token1, *ok1, raw1)]
+        .into_iter()
+        .all(|(token, ok, raw)| {
+            let balance = match U256::decode(raw) {
+                Ok(raw) if ok => TokenAmount::new(raw, TokenRef::from(token)).to_f64(),
+                _ => {
+                    warn!("balanceOf({:?}) failed for {}", pool.address, token.symbol);
+                    0.0
+                }
+            };
+            balance >= filter.min_balance(token.address)
+        })
+}
+
+/// A background refresh that turned up newly liquid pools.
+#[derive(Debug, Clone)]
+pub struct DiscoveryUpdate {
+    pub chain_id: u64,
+    /// Every liquid pool on the chain after the refresh.
+    pub pools: Vec<DiscoveredPool>,
+    /// The pools in `pools` that were not liquid before it.
+    pub added: Vec<Address>,
+}
+
+/// Runs an initial scan on every configured chain, then keeps each one current in the
+/// background every `poll_every`. Returns the pools known after the initial scan, and a
+/// receiver for the refreshes that add liquid pools after that.
+pub async fn start(
+    settings: &DiscoverySettings,
+    rpc: &RpcSettings,
+    cache: &CacheSettings,
+) -> Result<(HashMap<u64, Vec<DiscoveredPool>>, mpsc::UnboundedReceiver<DiscoveryUpdate>)> {
+    let mut discovered = HashMap::new();
+    let period = settings.poll_interval();
+    let (updates, receiver) = mpsc::unbounded_channel();
+
+    for (name, chain_settings) in &settings.chains {
+        let chain = chains::resolve(name)?;
+        let rpc_url = rpc
+            .primary_url(chain.name)
+            .ok_or_else(|| anyhow!("No RPC endpoints configured for {}", chain.name))?;
+
+        let mut discovery = ChainDiscovery::new(chain, rpc_url, chain_settings, settings.block_chunk, cache)?;
+        if let Err(e) = discovery.refresh().await {
+            error!("Discovery {}: initial scan failed: {e:#}", chain.name);
+        }
+        discovered.insert(chain.chain_id, discovery.store().liquid_pools().cloned().collect());
+
+        tokio::spawn(follow(discovery, period, updates.clone()));
+    }
+
+    Ok((discovered, receiver))
+}
+
+async fn follow(mut discovery: ChainDiscovery, period: Duration, updates: mpsc::UnboundedSender<DiscoveryUpdate>) {
+    let mut interval = tokio::time::interval(period);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let added = match discovery.refresh().await {
+            Ok(added) => added,
+            Err(e) => {
+                error!("Discovery {}: refresh failed: {e:#}", discovery.chain.name);
+                continue;
+            }
+        };
+        if added.is_empty() {
+            continue;
+        }
+        let update = DiscoveryUpdate {
+            chain_id: discovery.chain.chain_id,
+            pools: discovery.store().liquid_pools().cloned().collect(),
+            added,
+        };
+        if updates.send(update).is_err() {
+            // Nobody is consuming updates any more; the store file is still kept current.
+            warn!("Discovery {}: update receiver dropped", discovery.chain.name);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::fixture::FixtureEntry;
+    use crate::rpc::{FixtureClient, RpcClient};
+
+    #[test]
+    fn token_filter_requires_both_tokens() {
+        let weth = "0xC02aaa39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+        let usdc = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+        let other: Address = "0x6B175474E89094C44Da98b954EedeAC495271d0F".parse().unwrap();
+
+        let filter = TokenFilter::new(&HashMap::from([(weth.to_string(), 10.0), (usdc.to_string(), 25_000.0)]), 1.0).unwrap();
+        assert!(filter.allows(weth.parse().unwrap(), usdc.parse().unwrap()));
+        assert!(!filter.allows(weth.parse().unwrap(), other));
+        assert_eq!(filter.min_balance(usdc.parse().unwrap()), 25_000.0);
+
+        assert!(TokenFilter::default().allows(other, other));
+        assert_eq!(filter.min_balance(other), 1.0);
+    }
+
+    fn token(byte: u8, symbol: &str, decimals: u8) -> TokenMetadata {
+        TokenMetadata {
+            chain_id: 1,
+            address: Address::repeat_byte(byte),
+            decimals,
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+        }
+    }
+
+    fn pool(byte: u8) -> DiscoveredPool {
+        DiscoveredPool {
+            address: Address::repeat_byte(byte),
+            dex: "uniswap_v2".to_string(),
+            pool_type: "V2".to_string(),
+            token0: token(0xa, "WETH", 18),
+            token1: token(0xb, "USDC", 6),
+            fee_tier: None,
+            created_block: 100,
+            liquid: false,
+        }
+    }
+
+    fn balance(raw: u128) -> (bool, Bytes) {
+        (true, U256::from(raw).encode().into())
+    }
+
+    #[test]
+    fn liquidity_needs_both_minimums_from_successful_calls() {
+        let filter = TokenFilter::new(
+            &HashMap::from([
+                (format!("{:?}", Address::repeat_byte(0xa)), 10.0),
+                (format!("{:?}", Address::repeat_byte(0xb)), 25_000.0),
+            ]),
+            1.0,
+        )
+        .unwrap();
+        let pool = pool(1);
+        let weth = |n: u128| balance(n * 10u128.pow(18));
+        let usdc = |n: u128| balance(n * 10u128.pow(6));
+
+        assert!(is_liquid(&filter, &pool, &[weth(10), usdc(25_000)]));
+        assert!(!is_liquid(&filter, &pool, &[weth(9), usdc(1_000_000)]));
+        assert!(!is_liquid(&filter, &pool, &[weth(100), usdc(24_999)]));
+        assert!(!is_liquid(&filter, &pool, &[weth(100), (false, Bytes::new())]));
+        assert!(!is_liquid(&filter, &pool, &[weth(100)]));
+    }
+
+    #[test]
+    fn empty_allowlist_still_applies_the_default_minimum() {
+        let filter = TokenFilter::new(&HashMap::new(), 1.0).unwrap();
+        let pool = pool(1);
+        let weth = |n: u128| balance(n * 10u128.pow(18));
+        let usdc = |n: u128| balance(n * 10u128.pow(6));
+
+        assert!(filter.allows(pool.token0.address, pool.token1.address));
+        assert!(is_liquid(&filter, &pool, &[weth(1), usdc(1)]));
+        assert!(!is_liquid(&filter, &pool, &[weth(1), balance(999_999)]));
+        assert!(!is_liquid(&filter, &pool, &[balance(0), usdc(1_000)]));
+    }
+
+    /// The `eth_getLogs` exchange each factory on `chain_id` makes for a one-block range with no events.
+    fn empty_logs(chain_id: u64, block: u64) -> Vec<FixtureEntry> {
+        let client = Arc::new(ethers::providers::Provider::new(RpcClient::Fixture(FixtureClient::replay_entries(Vec::new()))));
+        dexes::on_chain(chain_id)
+            .map(|dex| {
+                let factory = dex.factory_address();
+                let filter = match (dex.kind, dex.variant) {
+                    (AmmKind::V2, _) => UniswapV2Factory::new(factory, client.clone()).pair_created_filter().filter,
+                    (AmmKind::V3, PoolVariant::Algebra) => AlgebraFactory::new(factory, client.clone()).pool_filter().filter,
+                    (AmmKind::V3, _) => UniswapV3Factory::new(factory, client.clone()).pool_created_filter().filter,
+                };
+                FixtureEntry::new(
+                    "eth_getLogs",
+                    serde_json::json!([filter.from_block(block).to_block(block)]),
+                    serde_json::json!([]),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn failed_scan_saves_the_blocks_read_before_it() {
+        let url = "http://discovery-partial.test";
+        let dir = std::env::temp_dir().join(format!("discovery_partial_{}", std::process::id()));
+        let cache = CacheSettings { dir: dir.to_string_lossy().into_owned(), ..Default::default() };
+        let settings = ChainDiscoverySettings { from_block: Some(10), ..Default::default() };
+        let chain = chains::resolve("ethereum").unwrap();
+        let mut discovery = ChainDiscovery::new(chain, url, &settings, 1, &cache).unwrap();
+
+        // Block 10 reads fine, block 11 has no recorded answer and fails at the minimum chunk.
+        let mut entries = empty_logs(chain.chain_id, 10);
+        entries.push(FixtureEntry::new("eth_blockNumber", serde_json::Value::Null, serde_json::json!("0xb")));
+        let replay = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        assert!(rpc::scoped(url, replay, discovery.refresh()).await.is_err());
+
+        let saved = PoolStore::load(&discovery.path, chain.chain_id).unwrap();
+        assert_eq!(saved.last_block, Some(10));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn store_round_trips_and_belongs_to_one_chain() {
+        let path = std::env::temp_dir().join(format!("pools_test_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let empty = PoolStore::load(&path, 1).unwrap();
+        assert_eq!((empty.chain_id, empty.last_block, empty.pools.len()), (1, None, 0));
+
+        let store = PoolStore { chain_id: 1, last_block: Some(200), pools: vec![pool(1), DiscoveredPool { liquid: true, ..pool(2) }] };
+        store.save(&path).unwrap();
+
+        let loaded = PoolStore::load(&path, 1).unwrap();
+        assert_eq!(loaded.last_block, Some(200));
+        assert_eq!(loaded.liquid_pools().map(|p| p.address).collect::<Vec<_>>(), vec![Address::repeat_byte(2)]);
+        assert!(PoolStore::load(&path, 42161).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
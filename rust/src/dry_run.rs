@@ -0,0 +1,538 @@
+use crate::chains;
+use crate::config::{DeployedContracts, DryRunSettings};
+use crate::contracts_abi::flash_swap_arbitrage_v2::{FlashSwapArbitrageV2, InitiateFlashSwapCall};
+use crate::contracts_abi::flash_swap_arbitrage_v3::FlashSwapCall;
+use crate::contracts_abi;
+use crate::dexes::AmmKind;
+use crate::execution::{flash_fee, flash_fee_rate, Opportunity};
+use crate::pools_abi::erc20_abi::ERC20;
+use crate::pools_abi::multicall3::{Call3, Multicall3};
+use crate::rpc::{self, RpcProvider};
+use crate::simulator::route;
+use crate::units::{TokenAmount, TokenRef};
+use crate::ArbitrageDirection;
+use anyhow::{anyhow, Context, Result};
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::providers::call_raw::{spoof, RawCall};
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes, TransactionRequest, H160, U256};
+use ethers::utils::hex;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Where the contract's code is injected on chains without a deployed one.
+pub const PLACEHOLDER_ADDRESS: Address = H160([0xf1; 20]);
+
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Arguments of `FlashSwapArbitrageV2.initiateFlashSwap` / `FlashSwapArbitrageV3.flashSwap`:
+/// borrow `amount_in` from `pool0`, sell it on `pool1`, buy it back on `pool2`. The contract
+/// reverts unless the profit reaches `min_profit`; zero accepts any profit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlashSwap {
+    pub kind: AmmKind,
+    pub pool0: Address,
+    pub pool1: Address,
+    pub pool2: Address,
+    pub amount_in: TokenAmount,
+    pub token_out: Address,
+    pub min_profit: TokenAmount,
+}
+
+impl FlashSwap {
+    pub fn token_in(&self) -> Address {
+        self.amount_in.token.address
+    }
+
+    /// Contract that runs this swap.
+    pub fn contract_name(&self) -> &'static str {
+        match self.kind {
+            AmmKind::V2 => "FlashSwapArbitrageV2",
+            AmmKind::V3 => "FlashSwapArbitrageV3",
+        }
+    }
+
+    /// The contract's creation bytecode, from its checked-in artifact.
+    pub fn creation_code(&self) -> Result<Bytes> {
+        contracts_abi::creation_code(self.contract_name())
+    }
+
+    pub fn calldata(&self) -> Bytes {
+        let (pool_0, pool_1, pool_2, token_in, token_out, amount_in, min_profit) = (
+            self.pool0,
+            self.pool1,
+            self.pool2,
+            self.token_in(),
+            self.token_out,
+            self.amount_in.raw,
+            self.min_profit.raw,
+        );
+        let calldata = match self.kind {
+            AmmKind::V2 => InitiateFlashSwapCall { pool_0, pool_1, pool_2, token_in, token_out, amount_in, min_profit }.encode(),
+            AmmKind::V3 => FlashSwapCall { pool_0, pool_1, pool_2, token_in, token_out, amount_in, min_profit }.encode(),
+        };
+        calldata.into()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DryRunOutcome {
+    /// The swap went through; `profit` is what the initiator received in the borrowed token.
+    Succeeded { block: u64, profit: TokenAmount },
+    Reverted { block: u64, reason: String },
+}
+
+impl fmt::Display for DryRunOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DryRunOutcome::Succeeded { block, profit } => write!(f, "succeeds at block {}, profit {}", block, profit),
+            DryRunOutcome::Reverted { block, reason } => write!(f, "reverts at block {}: {}", block, reason),
+        }
+    }
+}
+
+/// `Error(string)` and `Panic(uint256)` as Solidity raises them; anything else as hex.
+pub fn decode_revert(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "reverted without a reason".to_string();
+    }
+    if data.len() >= 4 && data[..4] == ERROR_SELECTOR {
+        if let Ok(reason) = String::decode(&data[4..]) {
+            return reason;
+        }
+    }
+    if data.len() >= 4 && data[..4] == PANIC_SELECTOR {
+        if let Ok(code) = U256::decode(&data[4..]) {
+            return format!("panic 0x{:x}", code);
+        }
+    }
+    format!("custom error 0x{}", hex::encode(data))
+}
+
+/// The FlashSwapArbitrage contract of `kind` deployed on `chain`, if configured.
+pub fn contract_address(contracts: &HashMap<String, DeployedContracts>, chain: &str, kind: AmmKind) -> Result<Option<Address>> {
+    let deployed = contracts.get(chain).and_then(|d| match kind {
+        AmmKind::V2 => d.v2.as_ref(),
+        AmmKind::V3 => d.v3.as_ref(),
+    });
+    deployed
+        .map(|address| {
+            address
+                .parse::<Address>()
+                .with_context(|| format!("Invalid {:?} flash-swap contract address {} on {}", kind, address, chain))
+        })
+        .transpose()
+}
+
+/// Runtime code of a contract whose constructor runs with `deployer` as `msg.sender`,
+/// immutables included: what a creation transaction would leave on chain.
+pub async fn runtime_code(client: Arc<RpcProvider>, creation: Bytes, deployer: Address, block: u64) -> Result<Bytes> {
+    let tx: TypedTransaction = TransactionRequest::new().from(deployer).data(creation).into();
+    client
+        .call(&tx, Some(block.into()))
+        .await
+        .context("Constructor eth_call failed")
+}
+
+/// Runs `swap` through `eth_call` at `block` (the head when `None`) without sending anything.
+///
+/// A contract configured for the chain runs its deployed code. The V2 one only accepts
+/// calls from its owner, so for the call the owner's account runs Multicall3's code and is
+/// the initiator; the V3 one is called by Multicall3 itself. Without a deployment the code
+/// is injected at `PLACEHOLDER_ADDRESS` through a state override, built with Multicall3 as
+/// the deployer and so as the V2 owner. Either way the initiator reads its `tokenIn`
+/// balance, calls the contract and reads the balance again in one `aggregate3`, so the
+/// profit is the difference of the two reads.
+pub async fn dry_run(
+    rpc_url: &str,
+    swap: &FlashSwap,
+    contracts: &HashMap<String, DeployedContracts>,
+    block: Option<u64>,
+) -> Result<DryRunOutcome> {
+    dry_run_with(rpc_url, swap, contracts, block, FlashSwap::creation_code).await
+}
+
+/// `dry_run` with the injected contract built from `creation_code(swap)`.
+async fn dry_run_with(
+    rpc_url: &str,
+    swap: &FlashSwap,
+    contracts: &HashMap<String, DeployedContracts>,
+    block: Option<u64>,
+    creation_code: fn(&FlashSwap) -> Result<Bytes>,
+) -> Result<DryRunOutcome> {
+    let client = rpc::provider(rpc_url)?;
+    let chain_id = chains::fetch_chain_id(rpc_url).await?;
+    let chain = chains::by_id(chain_id).ok_or_else(|| anyhow!("Unknown chain id {}", chain_id))?;
+    let multicall: Address = chain
+        .multicall3
+        .parse()
+        .map_err(|_| anyhow!("No Multicall3 on {}", chain.name))?;
+    let block = match block {
+        Some(block) => block,
+        None => client.get_block_number().await?.as_u64(),
+    };
+
+    let mut state = spoof::state();
+    let (contract, initiator) = match contract_address(contracts, chain.name, swap.kind)? {
+        Some(contract) if swap.kind == AmmKind::V2 => {
+            let owner = FlashSwapArbitrageV2::new(contract, client.clone())
+                .owner()
+                .block(block)
+                .call()
+                .await
+                .context("owner() failed on the deployed contract")?;
+            state.account(owner).code(client.get_code(multicall, Some(block.into())).await?);
+            (contract, owner)
+        }
+        Some(contract) => (contract, multicall),
+        None => {
+            let code = runtime_code(client.clone(), creation_code(swap)?, multicall, block).await?;
+            state.account(PLACEHOLDER_ADDRESS).code(code);
+            (PLACEHOLDER_ADDRESS, multicall)
+        }
+    };
+
+    let balance = ERC20::new(swap.token_in(), client.clone())
+        .balance_of(initiator)
+        .calldata()
+        .ok_or_else(|| anyhow!("Failed to encode balanceOf"))?;
+    let calls = vec![
+        Call3 { target: swap.token_in(), allow_failure: false, call_data: balance.clone() },
+        Call3 { target: contract, allow_failure: true, call_data: swap.calldata() },
+        Call3 { target: swap.token_in(), allow_failure: false, call_data: balance },
+    ];
+    let tx = Multicall3::new(initiator, client.clone()).aggregate_3(calls).tx;
+    let raw = client
+        .call_raw(&tx)
+        .block(block.into())
+        .state(&state)
+        .await
+        .context("Multicall3 eth_call failed")?;
+
+    let results = Vec::<(bool, Bytes)>::decode(raw)?;
+    let [(_, before), (success, returned), (_, after)] = results.as_slice() else {
+        return Err(anyhow!("Multicall3 returned {} results, expected 3", results.len()));
+    };
+    if !success {
+        return Ok(DryRunOutcome::Reverted { block, reason: decode_revert(returned) });
+    }
+
+    let token = swap.amount_in.token;
+    let before = TokenAmount::new(U256::decode(before)?, token);
+    let after = TokenAmount::new(U256::decode(after)?, token);
+    Ok(DryRunOutcome::Succeeded { block, profit: after.checked_sub(&before)? })
+}
+
+/// Sizes the direction's cycle at the head block, up to `settings.amount` and within
+/// `settings.max_price_impact`, and dry-runs the flash swap for that input at the same
+/// block, borrowing from `path[0]`. `None` when no size is profitable net of the flash fee.
+pub async fn dry_run_direction(
+    dir: &ArbitrageDirection,
+    settings: &DryRunSettings,
+    contracts: &HashMap<String, DeployedContracts>,
+) -> Result<Option<(Opportunity, DryRunOutcome)>> {
+    let flash_pool = dir
+        .pools
+        .first()
+        .ok_or_else(|| anyhow!("Pools not described for direction {:?}", dir.path))?;
+    let loan_cost = 1.0 + flash_fee_rate(flash_pool.kind);
+    let Some(sized) = route::size_direction(dir, settings.amount, settings.max_price_impact, loan_cost).await? else {
+        return Ok(None);
+    };
+    let token_in = TokenRef::resolve(&dir.provider, sized.route.start_token()).await?;
+    let loan = TokenAmount::new(sized.result.amount_in(), token_in);
+    if sized.result.amount_out() <= loan.checked_add(&flash_fee(flash_pool.kind, &loan)?)?.raw {
+        return Ok(None);
+    }
+    let opportunity = Opportunity::from_route(&dir.provider, &sized.route, &sized.result, flash_pool, token_in)?;
+    let outcome = dry_run(&dir.provider, &opportunity.swap, contracts, Some(sized.block)).await?;
+    Ok(Some((opportunity, outcome)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::PoolVariant;
+    use crate::pools::PoolDescriptor;
+    use crate::pools_abi::erc20_abi::DecimalsCall;
+    use crate::pools_abi::multicall3::Aggregate3Call;
+    use crate::pools_abi::uniswap_v2_pair::{GetReservesCall, GetReservesReturn, Token0Call, Token1Call};
+    use crate::contracts_abi::flash_swap_arbitrage_v2::OwnerCall;
+    use crate::rpc::fixture::{eth_call_entry, FixtureEntry};
+    use crate::rpc::{FixtureClient, RpcClient};
+    use ethers::abi::{encode, Token};
+    use serde_json::{json, Value};
+
+    fn with_selector(selector: [u8; 4], token: Token) -> Vec<u8> {
+        [selector.to_vec(), encode(&[token])].concat()
+    }
+
+    #[test]
+    fn aggregate3_takes_an_array_of_call_structs() {
+        use ethers::utils::id;
+
+        let call = Aggregate3Call {
+            calls: vec![Call3 { target: Address::repeat_byte(1), allow_failure: true, call_data: Bytes::from(vec![0xaa]) }],
+        };
+        assert_eq!(call.encode()[..4], id("aggregate3((address,bool,bytes)[])"));
+    }
+
+    #[test]
+    fn calldata_carries_the_min_profit_guard() {
+        let token = TokenRef::new(Address::repeat_byte(0xa), 18);
+        let mut swap = FlashSwap {
+            kind: AmmKind::V2,
+            pool0: Address::repeat_byte(1),
+            pool1: Address::repeat_byte(2),
+            pool2: Address::repeat_byte(3),
+            amount_in: TokenAmount::new(U256::exp10(18), token),
+            token_out: Address::repeat_byte(0xb),
+            min_profit: TokenAmount::new(U256::exp10(15), token),
+        };
+        let call = InitiateFlashSwapCall::decode(swap.calldata()).unwrap();
+        assert_eq!((call.pool_0, call.token_in, call.amount_in), (swap.pool0, token.address, U256::exp10(18)));
+        assert_eq!(call.min_profit, U256::exp10(15));
+
+        swap.kind = AmmKind::V3;
+        let call = FlashSwapCall::decode(swap.calldata()).unwrap();
+        assert_eq!((call.pool_2, call.token_out, call.min_profit), (swap.pool2, swap.token_out, U256::exp10(15)));
+    }
+
+    #[test]
+    fn revert_data_decodes_to_a_reason() {
+        let unprofitable = with_selector(ERROR_SELECTOR, Token::String("Unprofitable".to_string()));
+        assert_eq!(decode_revert(&unprofitable), "Unprofitable");
+
+        // 0x11: arithmetic overflow
+        let overflow = with_selector(PANIC_SELECTOR, Token::Uint(U256::from(0x11)));
+        assert_eq!(decode_revert(&overflow), "panic 0x11");
+
+        assert_eq!(decode_revert(&[]), "reverted without a reason");
+        assert_eq!(decode_revert(&[0xde, 0xad, 0xbe, 0xef]), "custom error 0xdeadbeef");
+    }
+
+    #[tokio::test]
+    async fn deployed_v2_contracts_are_called_by_their_owner() {
+        const URL: &str = "fixture://dry-run";
+        let (a, b) = (Address::repeat_byte(0x1a), Address::repeat_byte(0x1b));
+        let (contract, owner) = (Address::repeat_byte(0xc0), Address::repeat_byte(0x0e));
+        let multicall: Address = chains::MULTICALL3.parse().unwrap();
+        let pools: Vec<PoolDescriptor> = (1..=3)
+            .map(|n| PoolDescriptor {
+                address: Address::repeat_byte(n),
+                kind: AmmKind::V2,
+                variant: PoolVariant::UniswapV2,
+                dex: None,
+                fee: 0.003,
+            })
+            .collect();
+        let dir = ArbitrageDirection {
+            pool_type: "V2".to_string(),
+            token0: format!("{:?}", a),
+            token1: format!("{:?}", b),
+            path: pools.iter().map(|p| format!("{:?}", p.address)).collect(),
+            roi: 0.0,
+            profit: 0.0,
+            priceDifference: 0.0,
+            pool_fee: None,
+            provider: URL.to_string(),
+            chain: None,
+            pools,
+        };
+        let contracts = HashMap::from([(
+            "arbitrum".to_string(),
+            DeployedContracts { v2: Some(format!("{:?}", contract)), v3: None },
+        )]);
+        let settings = DryRunSettings { amount: 1_000.0, ..Default::default() };
+
+        // Pool 2 sells `a` for ~2.1 `b`, pool 3 sells ~1.9 `b` back for an `a`.
+        let mut entries = vec![
+            FixtureEntry::new("eth_chainId", json!(null), json!("0xa4b1")),
+            FixtureEntry::new("eth_blockNumber", json!(null), json!("0x10")),
+            eth_call_entry(a, "latest", DecimalsCall, 6u8),
+        ];
+        for (n, reserve1) in [(2u8, 2_100_000_000_000u128), (3, 1_900_000_000_000)] {
+            let pair = Address::repeat_byte(n);
+            let reserves = GetReservesReturn { reserve_0: 1_000_000_000_000, reserve_1: reserve1, block_timestamp_last: 0 };
+            entries.push(eth_call_entry(pair, "0x10", GetReservesCall, reserves));
+            entries.push(eth_call_entry(pair, "0x10", Token0Call, a));
+            entries.push(eth_call_entry(pair, "0x10", Token1Call, b));
+        }
+
+        // Size first so the recorded aggregate3 carries the calldata the dry run will send.
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries.clone()));
+        let sized = rpc::scoped(URL, client, route::size_direction(&dir, settings.amount, settings.max_price_impact, 1.0 + flash_fee_rate(AmmKind::V2)))
+            .await
+            .unwrap()
+            .unwrap();
+        let token = TokenRef::new(a, 6);
+        let expected = Opportunity::from_route(URL, &sized.route, &sized.result, &dir.pools[0], token).unwrap();
+
+        // The owner's account runs Multicall3's code; nothing is injected at the contract.
+        let multicall_code = Bytes::from(vec![0x60, 0x80, 0x60, 0x40]);
+        let mut state = spoof::state();
+        state.account(owner).code(multicall_code.clone());
+        let balance = ERC20::new(a, rpc::provider(URL).unwrap()).balance_of(owner).calldata().unwrap();
+        let aggregate = Aggregate3Call {
+            calls: vec![
+                Call3 { target: a, allow_failure: false, call_data: balance.clone() },
+                Call3 { target: contract, allow_failure: true, call_data: expected.swap.calldata() },
+                Call3 { target: a, allow_failure: false, call_data: balance },
+            ],
+        };
+        let profit = expected.expected_profit.raw;
+        let returned: Vec<(bool, Bytes)> = vec![
+            (true, U256::exp10(6).encode().into()),
+            (true, Bytes::new()),
+            (true, (U256::exp10(6) + profit).encode().into()),
+        ];
+        entries.push(eth_call_entry(contract, "0x10", OwnerCall, owner));
+        entries.push(FixtureEntry::new(
+            "eth_getCode",
+            json!([format!("{:?}", multicall), "0x10"]),
+            json!(format!("0x{}", hex::encode(&multicall_code))),
+        ));
+        let mut aggregate_call = eth_call_entry(owner, "0x10", aggregate, returned);
+        if let Value::Array(params) = &mut aggregate_call.params {
+            params.push(serde_json::to_value(&state).unwrap());
+        }
+        entries.push(aggregate_call);
+
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        let (opportunity, outcome) = rpc::scoped(URL, client, dry_run_direction(&dir, &settings, &contracts))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(opportunity.swap, expected.swap);
+        assert_eq!((opportunity.swap.pool0, opportunity.swap.pool1), (dir.pools[0].address, dir.pools[1].address));
+        assert!(profit > U256::zero());
+        assert_eq!(outcome, DryRunOutcome::Succeeded { block: 16, profit: TokenAmount::new(profit, token) });
+    }
+
+    #[tokio::test]
+    async fn spreads_the_flash_fee_eats_are_not_dry_run() {
+        const URL: &str = "fixture://dry-run-thin";
+        let (a, b) = (Address::repeat_byte(0x2a), Address::repeat_byte(0x2b));
+        let pools: Vec<PoolDescriptor> = (1..=3)
+            .map(|n| PoolDescriptor {
+                address: Address::repeat_byte(n),
+                kind: AmmKind::V2,
+                variant: PoolVariant::UniswapV2,
+                dex: None,
+                fee: 0.003,
+            })
+            .collect();
+        let dir = ArbitrageDirection {
+            pool_type: "V2".to_string(),
+            token0: format!("{:?}", a),
+            token1: format!("{:?}", b),
+            path: pools.iter().map(|p| format!("{:?}", p.address)).collect(),
+            roi: 0.0,
+            profit: 0.0,
+            priceDifference: 0.0,
+            pool_fee: None,
+            provider: URL.to_string(),
+            chain: None,
+            pools,
+        };
+        let settings = DryRunSettings { amount: 1_000.0, ..Default::default() };
+
+        // A 0.75% gap covers both 0.3% swap fees, not the ~0.3% flash fee on top.
+        let mut entries = vec![
+            FixtureEntry::new("eth_chainId", json!(null), json!("0xa4b1")),
+            FixtureEntry::new("eth_blockNumber", json!(null), json!("0x10")),
+            eth_call_entry(a, "latest", DecimalsCall, 6u8),
+        ];
+        for (n, reserve1) in [(2u8, 2_015_000_000_000u128), (3, 2_000_000_000_000)] {
+            let pair = Address::repeat_byte(n);
+            let reserves = GetReservesReturn { reserve_0: 1_000_000_000_000, reserve_1: reserve1, block_timestamp_last: 0 };
+            entries.push(eth_call_entry(pair, "0x10", GetReservesCall, reserves));
+            entries.push(eth_call_entry(pair, "0x10", Token0Call, a));
+            entries.push(eth_call_entry(pair, "0x10", Token1Call, b));
+        }
+
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries.clone()));
+        let free = rpc::scoped(URL, client, route::size_direction(&dir, settings.amount, settings.max_price_impact, 1.0)).await.unwrap();
+        assert!(free.is_some_and(|sized| sized.result.profit().is_some_and(|p| !p.is_zero())));
+
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        let dry_run = rpc::scoped(URL, client, dry_run_direction(&dir, &settings, &HashMap::new())).await.unwrap();
+        assert!(dry_run.is_none());
+    }
+
+    /// Dry-runs a V2 swap on a chain without deployed contracts, with the code to inject
+    /// built by `creation_code`, against a node that deploys it and reports a 1234 profit.
+    async fn dry_run_injected(url: &str, creation_code: fn(&FlashSwap) -> Result<Bytes>) -> DryRunOutcome {
+        let token = TokenRef::new(Address::repeat_byte(0x1a), 6);
+        let multicall: Address = chains::MULTICALL3.parse().unwrap();
+        let swap = FlashSwap {
+            kind: AmmKind::V2,
+            pool0: Address::repeat_byte(1),
+            pool1: Address::repeat_byte(2),
+            pool2: Address::repeat_byte(3),
+            amount_in: TokenAmount::new(U256::exp10(9), token),
+            token_out: Address::repeat_byte(0x1b),
+            min_profit: TokenAmount::new(U256::zero(), token),
+        };
+        let creation = creation_code(&swap).unwrap();
+        let runtime = Bytes::from(vec![0x60, 0x80, 0x60, 0x40]);
+
+        // The constructor runs with Multicall3 as the deployer, so it owns the V2 contract.
+        let constructor = FixtureEntry::new(
+            "eth_call",
+            json!([{ "from": format!("{:?}", multicall), "data": format!("0x{}", hex::encode(&creation)) }, "0x10"]),
+            json!(format!("0x{}", hex::encode(&runtime))),
+        );
+
+        // Multicall3 is the initiator; the runtime code sits at the placeholder.
+        let mut state = spoof::state();
+        state.account(PLACEHOLDER_ADDRESS).code(runtime);
+        let balance = ERC20::new(token.address, rpc::provider(url).unwrap()).balance_of(multicall).calldata().unwrap();
+        let aggregate = Aggregate3Call {
+            calls: vec![
+                Call3 { target: token.address, allow_failure: false, call_data: balance.clone() },
+                Call3 { target: PLACEHOLDER_ADDRESS, allow_failure: true, call_data: swap.calldata() },
+                Call3 { target: token.address, allow_failure: false, call_data: balance },
+            ],
+        };
+        let returned: Vec<(bool, Bytes)> = vec![
+            (true, U256::exp10(6).encode().into()),
+            (true, Bytes::new()),
+            (true, (U256::exp10(6) + 1_234).encode().into()),
+        ];
+        let mut aggregate_call = eth_call_entry(multicall, "0x10", aggregate, returned);
+        if let Value::Array(params) = &mut aggregate_call.params {
+            params.push(serde_json::to_value(&state).unwrap());
+        }
+
+        let entries = vec![
+            FixtureEntry::new("eth_chainId", json!(null), json!("0xa4b1")),
+            constructor,
+            aggregate_call,
+        ];
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        rpc::scoped(url, client, dry_run_with(url, &swap, &HashMap::new(), Some(16), creation_code))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn undeployed_contracts_are_injected_at_the_placeholder() {
+        const CREATION: &[u8] = &[0x60, 0x80, 0x60, 0x40, 0x52];
+        let outcome = dry_run_injected("fixture://dry-run-injected", |_| Ok(Bytes::from_static(CREATION))).await;
+        let token = TokenRef::new(Address::repeat_byte(0x1a), 6);
+        assert_eq!(outcome, DryRunOutcome::Succeeded { block: 16, profit: TokenAmount::new(U256::from(1_234), token) });
+    }
+
+    #[tokio::test]
+    #[ignore = "needs compiled artifacts: run `npm run export-abi` in the repository root"]
+    async fn injected_code_is_built_from_the_checked_in_artifact() {
+        let outcome = dry_run_injected("fixture://dry-run-artifact", FlashSwap::creation_code).await;
+        let token = TokenRef::new(Address::repeat_byte(0x1a), 6);
+        assert_eq!(outcome, DryRunOutcome::Succeeded { block: 16, profit: TokenAmount::new(U256::from(1_234), token) });
+    }
+}
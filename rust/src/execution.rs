@@ -0,0 +1,259 @@
+use crate::chains;
+use crate::config::{DeployedContracts, ExecutionSettings};
+use crate::dexes::AmmKind;
+use crate::dry_run::{contract_address, decode_revert, FlashSwap};
+use crate::pools::PoolDescriptor;
+use crate::rpc;
+use crate::simulator::route::{Route, RouteResult};
+use crate::units::{TokenAmount, TokenRef};
+use anyhow::{anyhow, Context, Result};
+use ethers::providers::{Middleware, ProviderError, RpcError};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{TransactionRequest, H256, U256};
+use ethers::utils::format_ether;
+use log::info;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A flash swap the engine expects to profit from, with the profit it expects in the
+/// borrowed token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Opportunity {
+    pub rpc_url: String,
+    pub swap: FlashSwap,
+    pub expected_profit: TokenAmount,
+}
+
+/// What `FlashSwapArbitrageV2` repays `pool0` on top of the loan: `loan·3/997 + 1`.
+/// The V3 contract swaps on `pool0` instead and repays the loan as is.
+pub fn flash_fee(kind: AmmKind, loan: &TokenAmount) -> Result<TokenAmount> {
+    match kind {
+        AmmKind::V2 => {
+            let fee = loan.mul_div(U256::from(3u64), U256::from(997u64))?;
+            fee.checked_add(&TokenAmount::new(U256::one(), loan.token))
+        }
+        AmmKind::V3 => Ok(TokenAmount::zero(loan.token)),
+    }
+}
+
+/// `flash_fee` as a fraction of the loan, for sizing: each unit borrowed costs `1 + rate`
+/// to repay.
+pub fn flash_fee_rate(kind: AmmKind) -> f64 {
+    match kind {
+        AmmKind::V2 => 3.0 / 997.0,
+        AmmKind::V3 => 0.0,
+    }
+}
+
+impl Opportunity {
+    pub fn new(rpc_url: impl Into<String>, swap: FlashSwap, expected_profit: TokenAmount) -> Self {
+        Opportunity { rpc_url: rpc_url.into(), swap, expected_profit }
+    }
+
+    /// A sized two-hop cycle (sell on the first pool, buy back on the second) funded by a
+    /// flash swap on `flash_pool`. The expected profit is net of the contract's flash fee.
+    pub fn from_route(
+        rpc_url: impl Into<String>,
+        route: &Route,
+        result: &RouteResult,
+        flash_pool: &PoolDescriptor,
+        token_in: TokenRef,
+    ) -> Result<Self> {
+        let [sell, buy] = route.hops.as_slice() else {
+            return Err(anyhow!("Flash-swap contracts run two-hop cycles, got {} hops", route.hops.len()));
+        };
+        if !route.is_cycle() || sell.token_in != token_in.address {
+            return Err(anyhow!("Route {:?} isn't a cycle from {:?}", route.tokens(), token_in.address));
+        }
+        let kind = flash_pool.kind;
+        if sell.pool.kind != kind || buy.pool.kind != kind {
+            return Err(anyhow!("Flash-swap contracts don't mix V2 and V3 pools"));
+        }
+
+        let amount_in = TokenAmount::new(result.amount_in(), token_in);
+        let owed = amount_in.checked_add(&flash_fee(kind, &amount_in)?)?;
+        let expected_profit = TokenAmount::new(result.amount_out(), token_in)
+            .checked_sub(&owed)
+            .context("Route doesn't cover the flash fee")?;
+
+        let swap = FlashSwap {
+            kind,
+            pool0: flash_pool.address,
+            pool1: sell.pool.address,
+            pool2: buy.pool.address,
+            amount_in,
+            token_out: sell.token_out,
+            min_profit: TokenAmount::zero(token_in),
+        };
+        Ok(Opportunity::new(rpc_url, swap, expected_profit))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionOutcome {
+    /// Not worth sending; nothing was estimated.
+    Skipped { reason: String },
+    /// `eth_estimateGas` reverted.
+    Reverted { reason: String },
+    /// Built and estimated, not signed: `execution.send` is off.
+    DryRun { tx: Box<TypedTransaction>, gas: U256 },
+    Sent { tx_hash: H256, succeeded: Option<bool>, gas_used: Option<U256> },
+}
+
+impl fmt::Display for ExecutionOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionOutcome::Skipped { reason } => write!(f, "skipped: {}", reason),
+            ExecutionOutcome::Reverted { reason } => write!(f, "gas estimate reverts: {}", reason),
+            ExecutionOutcome::DryRun { tx, gas } => {
+                write!(f, "dry run: would send to {:?} with gas limit {} (estimate {})", tx.to(), tx.gas().copied().unwrap_or_default(), gas)
+            }
+            ExecutionOutcome::Sent { tx_hash, succeeded, gas_used } => {
+                write!(f, "sent {:?}: succeeded {:?}, gas used {:?}", tx_hash, succeeded, gas_used)
+            }
+        }
+    }
+}
+
+/// Why `expected` isn't worth sending, or `None` if it clears `min`.
+pub fn below_min_profit(expected: &TokenAmount, min: &TokenAmount) -> Option<String> {
+    if expected.is_zero() || expected.raw < min.raw {
+        return Some(format!("expected profit {} below minimum {}", expected, min));
+    }
+    None
+}
+
+fn revert_reason(e: &ProviderError) -> String {
+    e.as_error_response()
+        .and_then(|response| response.as_revert_data())
+        .map(|data| decode_revert(&data))
+        .unwrap_or_else(|| e.to_string())
+}
+
+fn signer(settings: &ExecutionSettings, chain_id: u64) -> Result<Option<LocalWallet>> {
+    let Ok(key) = std::env::var(&settings.private_key_env) else {
+        return Ok(None);
+    };
+    let wallet = key
+        .parse::<LocalWallet>()
+        .with_context(|| format!("${} is not a valid private key", settings.private_key_env))?;
+    Ok(Some(wallet.with_chain_id(chain_id)))
+}
+
+/// Turns `opportunity` into an `initiateFlashSwap` (V2) or `flashSwap` (V3) transaction
+/// from the signer to the deployed contract, with `min_profit` as the contract's own
+/// profit floor so a worse fill reverts on-chain. The gas limit is the estimate plus
+/// `gas_margin`. Signing and sending only happen with `execution.send` on.
+pub async fn execute(
+    opportunity: &Opportunity,
+    settings: &ExecutionSettings,
+    contracts: &HashMap<String, DeployedContracts>,
+) -> Result<ExecutionOutcome> {
+    let min_profit = TokenAmount::from_f64(settings.min_profit, opportunity.expected_profit.token)?;
+    if let Some(reason) = below_min_profit(&opportunity.expected_profit, &min_profit) {
+        return Ok(ExecutionOutcome::Skipped { reason });
+    }
+
+    let swap = &FlashSwap { min_profit, ..opportunity.swap.clone() };
+    let client = rpc::provider(&opportunity.rpc_url)?;
+    let chain_id = chains::fetch_chain_id(&opportunity.rpc_url).await?;
+    let chain = chains::by_id(chain_id).ok_or_else(|| anyhow!("Unknown chain id {}", chain_id))?;
+    let contract = contract_address(contracts, chain.name, swap.kind)?
+        .ok_or_else(|| anyhow!("No {} deployed on {}", swap.contract_name(), chain.name))?;
+
+    // The V2 contract only accepts calls from its owner, so even the estimate needs the signer.
+    let Some(wallet) = signer(settings, chain_id)? else {
+        if settings.send {
+            return Err(anyhow!("execution.send is on but ${} is not set", settings.private_key_env));
+        }
+        return Ok(ExecutionOutcome::Skipped {
+            reason: format!("${} not set, nothing to estimate from", settings.private_key_env),
+        });
+    };
+
+    let mut tx: TypedTransaction = TransactionRequest::new()
+        .from(wallet.address())
+        .to(contract)
+        .data(swap.calldata())
+        .chain_id(chain_id)
+        .into();
+
+    let gas = match client.estimate_gas(&tx, None).await {
+        Ok(gas) => gas,
+        Err(e) => return Ok(ExecutionOutcome::Reverted { reason: revert_reason(&e) }),
+    };
+    let margin = U256::from((settings.gas_margin.max(0.0) * 1_000.0).round() as u64);
+    tx.set_gas(gas + gas * margin / U256::from(1_000u64));
+
+    if !settings.send {
+        return Ok(ExecutionOutcome::DryRun { tx: Box::new(tx), gas });
+    }
+
+    client.fill_transaction(&mut tx, None).await?;
+    let gas_price = tx.gas_price().unwrap_or_default();
+    info!(
+        "Sending {} on {}: expected profit {}, max gas cost {} {}",
+        swap.contract_name(),
+        chain.name,
+        opportunity.expected_profit,
+        format_ether(gas_price * tx.gas().copied().unwrap_or_default()),
+        chain.native_token
+    );
+
+    let signature = wallet.sign_transaction(&tx).await?;
+    let pending = client.send_raw_transaction(tx.rlp_signed(&signature)).await?;
+    let tx_hash = pending.tx_hash();
+    let receipt = pending.await?;
+    Ok(ExecutionOutcome::Sent {
+        tx_hash,
+        succeeded: receipt.as_ref().and_then(|r| r.status).map(|status| status.as_u64() == 1),
+        gas_used: receipt.and_then(|r| r.gas_used),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::PoolVariant;
+    use crate::simulator::route::Hop;
+    use ethers::types::Address;
+
+    fn pool(byte: u8) -> PoolDescriptor {
+        PoolDescriptor { address: Address::repeat_byte(byte), kind: AmmKind::V2, variant: PoolVariant::UniswapV2, dex: None, fee: 0.003 }
+    }
+
+    #[test]
+    fn two_hop_cycles_become_flash_swaps_net_of_the_fee() {
+        let weth = TokenRef::new(Address::repeat_byte(0xa), 18);
+        let usdc = Address::repeat_byte(0xb);
+        let route = Route::new(vec![
+            Hop { pool: pool(2), token_in: weth.address, token_out: usdc },
+            Hop { pool: pool(3), token_in: usdc, token_out: weth.address },
+        ])
+        .unwrap();
+        let loan = U256::exp10(18);
+        let result = RouteResult { amounts: vec![loan, U256::from(2_500_000_000u64), loan * 101 / 100] };
+
+        let opportunity = Opportunity::from_route("http://localhost:8545", &route, &result, &pool(1), weth).unwrap();
+        assert_eq!((opportunity.swap.pool0, opportunity.swap.pool1, opportunity.swap.pool2), (pool(1).address, pool(2).address, pool(3).address));
+        assert_eq!(opportunity.swap.token_out, usdc);
+        assert!(opportunity.swap.min_profit.is_zero());
+        // 1% gross minus the 0.3009..% the V2 contract repays on top of the loan
+        let fee = loan * 3 / 997 + 1;
+        assert_eq!(opportunity.expected_profit.raw, loan / 100 - fee);
+
+        let thin = RouteResult { amounts: vec![loan, U256::one(), loan + 1] };
+        assert!(Opportunity::from_route("http://localhost:8545", &route, &thin, &pool(1), weth).is_err());
+    }
+
+    #[test]
+    fn min_profit_guard_rejects_small_and_zero_profits() {
+        let weth = TokenRef::new(Address::repeat_byte(0xa), 18);
+        let profit = TokenAmount::from_f64(0.01, weth).unwrap();
+        let min = |amount: f64| TokenAmount::from_f64(amount, weth).unwrap();
+        assert!(below_min_profit(&profit, &min(0.005)).is_none());
+        assert!(below_min_profit(&profit, &min(0.02)).is_some());
+        assert!(below_min_profit(&TokenAmount::zero(weth), &min(0.0)).is_some());
+    }
+}
@@ -1,19 +1,28 @@
 mod pools_abi;
+mod contracts_abi;
 mod threads;
 //mod context;
+mod chains;
 mod config;
+mod devnet;
+mod dexes;
+mod directions;
+mod discovery;
+mod dry_run;
+mod execution;
+mod pool_adapters;
+mod pool_verification;
+mod pools;
+mod rpc;
 mod simulator;
+mod ticks;
+mod tokens;
+mod units;
 
 use anyhow::Result;
-use bigdecimal::BigDecimal;
-use env_logger::Builder;
-use ethers::types::U256;
-use futures::{stream, StreamExt};
+use futures::StreamExt;
 use log::{error, info};
 use serde::Deserialize;
-use serde_json;
-use std::path::Path;
-use tokio::task;
 
 use serde_json::from_reader;
 use std::fs::File;
@@ -26,6 +35,7 @@ use simulator::simulate_direction;
 //use anyhow::Context as AnyhowContext;
 
 #[derive(Debug, Deserialize)]
+#[allow(non_snake_case)]
 pub struct ArbitrageDirection {
     pub pool_type: String, // "V2" | "V3"
     pub token0: String,
@@ -34,8 +44,21 @@ pub struct ArbitrageDirection {
     pub roi: f64,
     pub profit: f64,
     pub priceDifference: f64,
-    pub pool_fee: f64,
+    pub pool_fee: Option<f64>,
     pub provider: String,
+    /// Chain name or id from `chains::CHAINS`, checked against the provider's `eth_chainId`.
+    #[serde(default)]
+    pub chain: Option<String>,
+    /// One descriptor per `path` entry, resolved on-chain by `pools::describe_directions`.
+    #[serde(skip)]
+    pub pools: Vec<pools::PoolDescriptor>,
+}
+
+impl ArbitrageDirection {
+    /// Resolved fee of `path[index]`; `None` until the pool has been described.
+    pub fn fee_at(&self, index: usize) -> Option<f64> {
+        self.pools.get(index).map(|p| p.fee)
+    }
 }
 
 pub async fn load_directions<P: AsRef<std::path::Path>>(
@@ -54,17 +77,124 @@ async fn main() -> Result<()> {
         .filter(None, log::LevelFilter::Info)
         .init();
 
-    let directions = load_directions("rust/pools_to_arbitrage.json").await?;
+    let cfg = config::read_config_or_default();
+    rpc::init(&cfg.rpc)?;
+    tokens::init(&cfg.cache)?;
+    if cfg.ticks.enabled {
+        ticks::start(&cfg.ticks, &cfg.rpc);
+    }
+
+    let mut updates = None;
+    let directions = if cfg.devnet.enabled {
+        vec![devnet::setup(&cfg.devnet).await?]
+    } else if cfg.discovery.enabled {
+        let (discovered, receiver) = discovery::start(&cfg.discovery, &cfg.rpc, &cfg.cache).await?;
+        for (chain_id, pools) in &discovered {
+            info!("Chain {}: {} liquid pools discovered", chain_id, pools.len());
+        }
+        updates = Some(receiver);
+        directions::from_discovered(&discovered, &cfg.rpc)
+    } else {
+        load_directions("rust/pools_to_arbitrage.json").await?
+    };
     info!("Loaded {} directions", directions.len());
-    let concurrency = directions.len();
+    run(directions, &cfg).await?;
+
+    // Discovery keeps refreshing in the background; each batch of newly liquid pools
+    // goes through the same pipeline as the initial set.
+    if let Some(mut updates) = updates {
+        while let Some(update) = updates.recv().await {
+            let directions = directions::from_update(&update, &cfg.rpc);
+            info!("Chain {}: {} new liquid pools, {} new directions", update.chain_id, update.added.len(), directions.len());
+            run(directions, &cfg).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Takes one batch of directions through chain and pool verification and fee resolution,
+/// then simulates each, dry-running and executing the profitable ones.
+async fn run(directions: Vec<ArbitrageDirection>, cfg: &config::Config) -> Result<()> {
+    let directions = chains::verify_directions(directions).await;
+    info!("{} directions passed chain verification", directions.len());
+
+    // Devnet mock pairs have no factory to check against.
+    let directions = if cfg.devnet.enabled {
+        directions
+    } else {
+        pool_verification::verify_directions(directions, &cfg.pool_verification).await
+    };
+    info!("{} directions passed pool verification", directions.len());
+
+    let directions = pools::describe_directions(directions).await;
+    info!("{} directions with resolved pool fees", directions.len());
+
+    if cfg.ticks.enabled {
+        ticks::prefetch(&directions, &cfg.ticks).await;
+    }
+
+    if cfg.route.enabled {
+        match cfg.route.search {
+            config::RouteSearch::Exhaustive => {
+                let routes = simulator::route::search_directions(&directions, &cfg.route).await;
+                info!("{} profitable multi-hop routes", routes.len());
+            }
+            config::RouteSearch::Graph => simulator::graph::watch(&directions, &cfg.route),
+        }
+    }
 
+    if cfg.quote.enabled {
+        let quotes = simulator::quote::quote_directions(&directions, &cfg.quote).await;
+        info!("Quoted {} pairs", quotes.len());
+    }
+
+    if cfg.verify.enabled {
+        simulator::verify::verify_directions(&directions, &cfg.verify).await;
+        tokens::registry().save_if_dirty()?;
+        rpc::flush()?;
+        return Ok(());
+    }
+    let concurrency = directions.len().max(1);
+
+    let dry_run_settings = &cfg.dry_run;
+    let execution_settings = &cfg.execution;
+    let contracts = &cfg.contracts;
     let results: Vec<_> = futures::stream::iter(directions)
         .map(|dir| {
-            async move { simulate_direction(&dir).await }
+            async move {
+                let result = simulate_direction(&dir).await;
+                if let Some((pool_sell, pool_buy, _, _, spread)) = &result {
+                    if dry_run_settings.enabled && *spread > 0.0 {
+                        match dry_run::dry_run_direction(&dir, dry_run_settings, contracts).await {
+                            Ok(None) => info!("Dry run {} -> {}: no profitable size", pool_sell, pool_buy),
+                            Ok(Some((sized, outcome))) => {
+                                info!("Dry run {} -> {}: expected profit {}, {}", pool_sell, pool_buy, sized.expected_profit, outcome);
+                                if let dry_run::DryRunOutcome::Succeeded { profit, .. } = outcome {
+                                    if execution_settings.enabled {
+                                        // The dry-run balance delta is what the contract actually paid out at that block.
+                                        let opportunity = execution::Opportunity::new(&dir.provider, sized.swap, profit);
+                                        match execution::execute(&opportunity, execution_settings, contracts).await {
+                                            Ok(outcome) => info!("Execute {} -> {}: {}", pool_sell, pool_buy, outcome),
+                                            Err(e) => error!("Execute {} -> {} failed: {e:#}", pool_sell, pool_buy),
+                                        }
+                                    }
+                                }
+                            }
+                            Err(e) => error!("Dry run {} -> {} failed: {e:#}", pool_sell, pool_buy),
+                        }
+                    }
+                }
+                result
+            }
         })
         .buffer_unordered(concurrency)
         .collect()
         .await;
+    info!("Simulated {} directions", results.iter().flatten().count());
+
+    tokens::registry().save_if_dirty()?;
+    rpc::flush()?;
 
     //let flat: Vec<_> = results.into_iter().flatten().collect();
     //let json = serde_json::to_string_pretty(&flat)?;
@@ -73,99 +203,3 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
-
-
-// ============================
-// Gradient-based loan optimizer
-// ============================
-// This module finds the optimal `loan_amount` that maximizes the arbitrage profit:
-// 
-//     profit(loan) = (sell_price(loan) - buy_price(loan)) * loan - fees(loan)
-//
-// Where:
-// - `sell_price(loan)` and `buy_price(loan)` are derived from on-chain pool simulation
-// - `fees(loan)` can be estimated as loan * (fee_bps + gas_costs), etc.
-//
-// To find the loan that gives the highest profit, we use **gradient ascent**:
-// 
-//     x ← x + α · ∇f(x)
-//
-// with the gradient approximated numerically:
-//
-//     ∇f(x) ≈ (f(x + ε) - f(x - ε)) / (2·ε)
-//
-// ----------------------------
-// Example usage:
-//
-// let (optimal_loan, max_profit) = gradient_ascent(
-//     |l| profit_for_loan(l),
-//     start = 1.0,
-//     learning_rate = 0.5,
-//     eps = 1e-4,
-//     max_iters = 50,
-// ).unwrap();
-//
-// Note:
-// - Ensure `profit_for_loan()` is smooth (no jumps in swap sim)
-// - This is a local optimizer — might not find global max if function has multiple peaks
-
-fn numerical_gradient<F>(f: &F, x: f64, eps: f64) -> f64
-where
-    F: Fn(f64) -> f64,
-{
-    (f(x + eps) - f(x - eps)) / (2.0 * eps)
-}
-
-fn gradient_ascent<F>(
-    f: F,
-    start: f64,
-    learning_rate: f64,
-    eps: f64,
-    max_iters: usize,
-) -> Option<(f64, f64)>
-where
-    F: Fn(f64) -> f64,
-{
-    let mut x = start;
-    let mut best_profit = f(x);
-    let mut best_x = x;
-
-    for _ in 0..max_iters {
-        let grad = numerical_gradient(&f, x, eps);
-        x += learning_rate * grad;
-
-        if x < 0.0 {
-            break; // loan can’t be negative
-        }
-
-        let p = f(x);
-        if p > best_profit {
-            best_profit = p;
-            best_x = x;
-        }
-
-        // Stop if gradient small
-        if grad.abs() < 1e-6 {
-            break;
-        }
-    }
-
-    if best_profit > 0.0 {
-        Some((best_x, best_profit))
-    } else {
-        None
-    }
-}
-
-/*fn profit_for_loan(loan: f64) -> f64 {
-    // call simulate_price_after_swap for sell and buy
-    // profit = (sell_price - buy_price) * loan - loan * fee
-
-    let (optimal_loan, max_profit) = gradient_ascent(
-    |l| profit_for_loan(l),
-    start = 1.0,
-    learning_rate = 0.5,
-    eps = 1e-4,
-    max_iters = 50,
-).unwrap();
-}*/
\ No newline at end of file
@@ -0,0 +1,196 @@
+use crate::dexes::PoolVariant;
+use crate::pools_abi::algebra_pool::AlgebraPool;
+use crate::pools_abi::pancake_v3_slot0::PancakeV3Slot0;
+use crate::pools_abi::uniswap_v3_liquidity::UniswapV3Liquidity;
+use crate::pools_abi::uniswap_v3_slot0::UniswapV3Slot0;
+use crate::rpc::RpcProvider;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::types::{Address, BlockId, BlockNumber, U256};
+use std::sync::Arc;
+
+/// Concentrated-liquidity pool state, independent of which ABI it was read through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolState {
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+    /// Current fee in hundredths of a bip per direction when the state getter reports it
+    /// (Algebra); `None` for pools with a fixed `fee()` tier.
+    pub fee: Option<DirectionalFee>,
+}
+
+/// Algebra pools charge token0→token1 and token1→token0 swaps separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirectionalFee {
+    pub zero_for_one: u32,
+    pub one_for_zero: u32,
+}
+
+impl PoolState {
+    /// Live fee for a swap in the given direction, if the pool reports one.
+    pub fn fee_for(&self, zero_for_one: bool) -> Option<u32> {
+        self.fee.map(|fee| if zero_for_one { fee.zero_for_one } else { fee.one_for_zero })
+    }
+}
+
+/// Reads a pool's current state through the ABI its DEX actually exposes.
+#[async_trait]
+pub trait PoolAdapter: Send + Sync {
+    async fn fetch_state(&self, client: Arc<RpcProvider>, pool: Address, block: BlockId) -> Result<PoolState>;
+}
+
+pub struct UniswapV3Adapter;
+pub struct PancakeV3Adapter;
+pub struct AlgebraAdapter;
+
+#[async_trait]
+impl PoolAdapter for UniswapV3Adapter {
+    async fn fetch_state(&self, client: Arc<RpcProvider>, pool: Address, block: BlockId) -> Result<PoolState> {
+        let (sqrt_price_x96, tick, _, _, _, _, _) = UniswapV3Slot0::new(pool, client.clone()).slot_0().block(block).call().await?;
+        let liquidity = UniswapV3Liquidity::new(pool, client).liquidity().block(block).call().await?;
+        Ok(PoolState { sqrt_price_x96, tick, liquidity, fee: None })
+    }
+}
+
+#[async_trait]
+impl PoolAdapter for PancakeV3Adapter {
+    async fn fetch_state(&self, client: Arc<RpcProvider>, pool: Address, block: BlockId) -> Result<PoolState> {
+        let (sqrt_price_x96, tick, _, _, _, _, _) = PancakeV3Slot0::new(pool, client.clone()).slot_0().block(block).call().await?;
+        let liquidity = UniswapV3Liquidity::new(pool, client).liquidity().block(block).call().await?;
+        Ok(PoolState { sqrt_price_x96, tick, liquidity, fee: None })
+    }
+}
+
+#[async_trait]
+impl PoolAdapter for AlgebraAdapter {
+    /// Camelot charges different fees per direction; both are kept and each leg picks its own.
+    async fn fetch_state(&self, client: Arc<RpcProvider>, pool: Address, block: BlockId) -> Result<PoolState> {
+        let pool = AlgebraPool::new(pool, client);
+        let (price, tick, fee_zto, fee_otz, _, _, _, _) = pool.global_state().block(block).call().await?;
+        let liquidity = pool.liquidity().block(block).call().await?;
+        Ok(PoolState {
+            sqrt_price_x96: price,
+            tick,
+            liquidity,
+            fee: Some(DirectionalFee { zero_for_one: fee_zto as u32, one_for_zero: fee_otz as u32 }),
+        })
+    }
+}
+
+pub fn adapter_for(variant: PoolVariant) -> Result<&'static dyn PoolAdapter> {
+    match variant {
+        PoolVariant::UniswapV3 => Ok(&UniswapV3Adapter),
+        PoolVariant::PancakeV3 => Ok(&PancakeV3Adapter),
+        PoolVariant::Algebra => Ok(&AlgebraAdapter),
+        PoolVariant::UniswapV2 => Err(anyhow!("V2 pairs have no concentrated-liquidity state")),
+    }
+}
+
+pub async fn fetch_pool_state(client: Arc<RpcProvider>, variant: PoolVariant, pool: Address) -> Result<PoolState> {
+    fetch_pool_state_at(client, variant, pool, BlockNumber::Latest.into()).await
+}
+
+/// State as of `block`, so it can be compared with other calls pinned to the same block.
+pub async fn fetch_pool_state_at(client: Arc<RpcProvider>, variant: PoolVariant, pool: Address, block: BlockId) -> Result<PoolState> {
+    adapter_for(variant)?.fetch_state(client, pool, block).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools_abi::{algebra_pool, pancake_v3_slot0, uniswap_v3_liquidity, uniswap_v3_slot0};
+    use crate::rpc::fixture::{eth_call_entry, FixtureEntry};
+    use crate::rpc::{self, FixtureClient, RpcClient};
+
+    const URL: &str = "fixture://pool_adapters";
+
+    fn pool() -> Address {
+        Address::repeat_byte(1)
+    }
+
+    fn sqrt_price() -> U256 {
+        U256::from_dec_str("3961408125713216879677197").unwrap()
+    }
+
+    async fn fetch(variant: PoolVariant, entries: Vec<FixtureEntry>) -> PoolState {
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        rpc::scoped(URL, client, async { fetch_pool_state(rpc::provider(URL)?, variant, pool()).await })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn uniswap_v3_state_comes_from_slot0_and_liquidity() {
+        let slot0 = uniswap_v3_slot0::Slot0Return {
+            sqrt_price_x96: sqrt_price(),
+            tick: -198080,
+            observation_index: 7,
+            observation_cardinality: 100,
+            observation_cardinality_next: 100,
+            fee_protocol: 0,
+            unlocked: true,
+        };
+        let state = fetch(
+            PoolVariant::UniswapV3,
+            vec![
+                eth_call_entry(pool(), "latest", uniswap_v3_slot0::Slot0Call, slot0),
+                eth_call_entry(pool(), "latest", uniswap_v3_liquidity::LiquidityCall, 5u128 << 64),
+            ],
+        )
+        .await;
+        assert_eq!(state, PoolState { sqrt_price_x96: sqrt_price(), tick: -198080, liquidity: 5 << 64, fee: None });
+    }
+
+    #[tokio::test]
+    async fn pancake_v3_slot0_has_a_wide_fee_protocol() {
+        let slot0 = pancake_v3_slot0::Slot0Return {
+            sqrt_price_x96: sqrt_price(),
+            tick: 887,
+            observation_index: 0,
+            observation_cardinality: 1,
+            observation_cardinality_next: 1,
+            fee_protocol: 32_003_200,
+            unlocked: true,
+        };
+        let state = fetch(
+            PoolVariant::PancakeV3,
+            vec![
+                eth_call_entry(pool(), "latest", pancake_v3_slot0::Slot0Call, slot0),
+                eth_call_entry(pool(), "latest", uniswap_v3_liquidity::LiquidityCall, 42u128),
+            ],
+        )
+        .await;
+        assert_eq!((state.tick, state.liquidity, state.fee), (887, 42, None));
+    }
+
+    #[tokio::test]
+    async fn algebra_keeps_the_fee_of_each_direction() {
+        let global_state = algebra_pool::GlobalStateReturn {
+            price: sqrt_price(),
+            tick: -198080,
+            fee_zto: 100,
+            fee_otz: 3000,
+            timepoint_index: 3,
+            community_fee_token_0: 0,
+            community_fee_token_1: 0,
+            unlocked: true,
+        };
+        let state = fetch(
+            PoolVariant::Algebra,
+            vec![
+                eth_call_entry(pool(), "latest", algebra_pool::GlobalStateCall, global_state),
+                eth_call_entry(pool(), "latest", algebra_pool::LiquidityCall, 7u128),
+            ],
+        )
+        .await;
+        assert_eq!(state.fee, Some(DirectionalFee { zero_for_one: 100, one_for_zero: 3000 }));
+        assert_eq!((state.fee_for(true), state.fee_for(false)), (Some(100), Some(3000)));
+        assert_eq!(state.liquidity, 7);
+    }
+
+    #[test]
+    fn v2_pairs_have_no_adapter() {
+        assert!(adapter_for(PoolVariant::UniswapV2).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use crate::chains;
+use crate::config::{PoolVerificationSettings, VerificationMode};
+use crate::dexes::{self, AmmKind, DexInfo, PoolVariant};
+use crate::pools_abi::algebra_factory::AlgebraFactory;
+use crate::pools_abi::uniswap_v2_factory::UniswapV2Factory;
+use crate::pools_abi::uniswap_v2_pair::UniswapV2Pair;
+use crate::pools_abi::uniswap_v3_factory::UniswapV3Factory;
+use crate::pools_abi::uniswap_v3_immutables::UniswapV3Immutables;
+use crate::rpc::{self, RpcProvider};
+use crate::ArbitrageDirection;
+use anyhow::{anyhow, Result};
+use ethers::types::Address;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Outcome of checking a pool against the factories we know.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoolVerdict {
+    /// The factory the pool reports is known and maps the pool's tokens (and fee) back to it.
+    Verified { dex: &'static DexInfo },
+    /// The pool reports a factory that isn't in `dexes::DEXES` for this chain.
+    UnknownFactory { factory: Address },
+    /// The pool claims a known factory, but that factory's canonical pool is a different address.
+    Spoofed { dex: &'static DexInfo, canonical: Address },
+    /// The pool is genuine but trades a different pair than the direction declares.
+    TokenMismatch { pool_tokens: (Address, Address) },
+}
+
+impl PoolVerdict {
+    pub fn is_verified(&self) -> bool {
+        matches!(self, PoolVerdict::Verified { .. })
+    }
+}
+
+impl fmt::Display for PoolVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolVerdict::Verified { dex } => write!(f, "verified ({})", dex.name),
+            PoolVerdict::UnknownFactory { factory } => write!(f, "unknown factory {:?}", factory),
+            PoolVerdict::Spoofed { dex, canonical } => {
+                write!(f, "spoofed: {} factory maps these tokens to {:?}", dex.name, canonical)
+            }
+            PoolVerdict::TokenMismatch { pool_tokens } => {
+                write!(f, "pool trades {:?}/{:?}", pool_tokens.0, pool_tokens.1)
+            }
+        }
+    }
+}
+
+fn same_pair(a: (Address, Address), b: (Address, Address)) -> bool {
+    a == b || a == (b.1, b.0)
+}
+
+/// Checks a V2 pair: `factory()` is known, `getPair(token0, token1)` returns the pair, and
+/// the pair's tokens are the ones the direction trades.
+pub async fn verify_v2_pool(
+    client: Arc<RpcProvider>,
+    chain_id: u64,
+    pool: Address,
+    tokens: (Address, Address),
+) -> Result<PoolVerdict> {
+    let pair = UniswapV2Pair::new(pool, client.clone());
+    let factory = pair.factory().call().await?;
+    let pool_tokens = (pair.token_0().call().await?, pair.token_1().call().await?);
+
+    let Some(dex) = dexes::by_factory(chain_id, factory).filter(|d| d.kind == AmmKind::V2) else {
+        return Ok(PoolVerdict::UnknownFactory { factory });
+    };
+
+    let canonical = UniswapV2Factory::new(factory, client)
+        .get_pair(pool_tokens.0, pool_tokens.1)
+        .call()
+        .await?;
+    if canonical != pool {
+        return Ok(PoolVerdict::Spoofed { dex, canonical });
+    }
+
+    if !same_pair(pool_tokens, tokens) {
+        return Ok(PoolVerdict::TokenMismatch { pool_tokens });
+    }
+
+    Ok(PoolVerdict::Verified { dex })
+}
+
+/// Checks a V3 pool: `factory()` is known, the factory maps the pool's tokens (and fee tier)
+/// back to it, and the pool's tokens are the ones the direction trades. Algebra factories
+/// have one pool per pair and are asked via `poolByPair` instead of `getPool`.
+pub async fn verify_v3_pool(
+    client: Arc<RpcProvider>,
+    chain_id: u64,
+    pool: Address,
+    tokens: (Address, Address),
+) -> Result<PoolVerdict> {
+    let immutables = UniswapV3Immutables::new(pool, client.clone());
+    let factory = immutables.factory().call().await?;
+    let pool_tokens = (immutables.token_0().call().await?, immutables.token_1().call().await?);
+
+    let Some(dex) = dexes::by_factory(chain_id, factory).filter(|d| d.kind == AmmKind::V3) else {
+        return Ok(PoolVerdict::UnknownFactory { factory });
+    };
+
+    let canonical = match dex.variant {
+        PoolVariant::Algebra => {
+            AlgebraFactory::new(factory, client)
+                .pool_by_pair(pool_tokens.0, pool_tokens.1)
+                .call()
+                .await?
+        }
+        _ => {
+            let fee = immutables.fee().call().await?;
+            UniswapV3Factory::new(factory, client)
+                .get_pool(pool_tokens.0, pool_tokens.1, fee)
+                .call()
+                .await?
+        }
+    };
+    if canonical != pool {
+        return Ok(PoolVerdict::Spoofed { dex, canonical });
+    }
+
+    if !same_pair(pool_tokens, tokens) {
+        return Ok(PoolVerdict::TokenMismatch { pool_tokens });
+    }
+
+    Ok(PoolVerdict::Verified { dex })
+}
+
+pub async fn verify_pool(
+    rpc_url: &str,
+    chain_id: u64,
+    kind: AmmKind,
+    pool: Address,
+    tokens: (Address, Address),
+) -> Result<PoolVerdict> {
+    let client = rpc::provider(rpc_url)?;
+    match kind {
+        AmmKind::V2 => verify_v2_pool(client, chain_id, pool, tokens).await,
+        AmmKind::V3 => verify_v3_pool(client, chain_id, pool, tokens).await,
+    }
+}
+
+/// Verifies every pool in a direction's path; returns the first pool that fails, if any.
+async fn check_direction(dir: &ArbitrageDirection, chain_id: u64) -> Result<Option<(String, PoolVerdict)>> {
+    let kind = AmmKind::from_pool_type(&dir.pool_type)
+        .ok_or_else(|| anyhow!("Unknown pool_type: {}", dir.pool_type))?;
+    let tokens: (Address, Address) = (dir.token0.parse()?, dir.token1.parse()?);
+
+    for pool in &dir.path {
+        let verdict = verify_pool(&dir.provider, chain_id, kind, pool.parse()?, tokens).await?;
+        if !verdict.is_verified() {
+            return Ok(Some((pool.clone(), verdict)));
+        }
+        info!("Pool {} {}", pool, verdict);
+    }
+    Ok(None)
+}
+
+/// Runs factory verification over all directions according to `settings.mode`:
+/// `warn` logs unverified pools and keeps the direction, `reject` drops it.
+pub async fn verify_directions(
+    directions: Vec<ArbitrageDirection>,
+    settings: &PoolVerificationSettings,
+) -> Vec<ArbitrageDirection> {
+    if settings.mode == VerificationMode::Off {
+        return directions;
+    }
+
+    let mut chain_ids: HashMap<String, u64> = HashMap::new();
+    let mut kept = Vec::with_capacity(directions.len());
+
+    for dir in directions {
+        let chain_id = match chain_ids.get(&dir.provider) {
+            Some(id) => *id,
+            None => match chains::fetch_chain_id(&dir.provider).await {
+                Ok(id) => {
+                    chain_ids.insert(dir.provider.clone(), id);
+                    id
+                }
+                Err(e) => {
+                    error!("Skipping direction {:?}: {e:#}", dir.path);
+                    continue;
+                }
+            },
+        };
+
+        match check_direction(&dir, chain_id).await {
+            Ok(None) => kept.push(dir),
+            Ok(Some((pool, verdict))) if settings.mode == VerificationMode::Warn => {
+                warn!("Pool {} not verified: {}", pool, verdict);
+                kept.push(dir);
+            }
+            Ok(Some((pool, verdict))) => {
+                error!("Rejecting direction {:?}: pool {} {}", dir.path, pool, verdict);
+            }
+            Err(e) => {
+                error!("Pool verification failed for {:?}: {e:#}", dir.path);
+                if settings.mode == VerificationMode::Warn {
+                    kept.push(dir);
+                }
+            }
+        }
+    }
+
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools_abi::uniswap_v2_factory::GetPairCall;
+    use crate::pools_abi::uniswap_v3_factory::GetPoolCall;
+    use crate::pools_abi::uniswap_v3_immutables::{FactoryCall, FeeCall, Token0Call, Token1Call};
+    use crate::rpc::fixture::{eth_call_entry, FixtureEntry};
+    use crate::rpc::{FixtureClient, RpcClient};
+    use ethers::types::U256;
+
+    const URL: &str = "fixture://pool_verification";
+    const UNISWAP_V2: &str = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f";
+    const UNISWAP_V3: &str = "0x1F98431c8aD98523631AE4a59f267346ea31F984";
+
+    fn addr(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    /// A pool at `addr(1)` trading `addr(0xa)`/`addr(0xb)` that reports `factory`.
+    fn pool(factory: Address) -> Vec<FixtureEntry> {
+        vec![
+            eth_call_entry(addr(1), "latest", FactoryCall, factory),
+            eth_call_entry(addr(1), "latest", Token0Call, addr(0xa)),
+            eth_call_entry(addr(1), "latest", Token1Call, addr(0xb)),
+        ]
+    }
+
+    async fn verify(kind: AmmKind, tokens: (Address, Address), entries: Vec<FixtureEntry>) -> PoolVerdict {
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        rpc::scoped(URL, client, verify_pool(URL, 1, kind, addr(1), tokens)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn v2_pairs_must_come_from_a_known_factory_for_the_declared_tokens() {
+        let factory: Address = UNISWAP_V2.parse().unwrap();
+        let canonical = |pair: Address| {
+            let mut entries = pool(factory);
+            entries.push(eth_call_entry(factory, "latest", GetPairCall { token_a: addr(0xa), token_b: addr(0xb) }, pair));
+            entries
+        };
+
+        let verdict = verify(AmmKind::V2, (addr(0xb), addr(0xa)), canonical(addr(1))).await;
+        assert!(matches!(verdict, PoolVerdict::Verified { dex } if dex.name == "uniswap_v2"));
+
+        let verdict = verify(AmmKind::V2, (addr(0xa), addr(0xb)), pool(addr(0xf))).await;
+        assert_eq!(verdict, PoolVerdict::UnknownFactory { factory: addr(0xf) });
+
+        let verdict = verify(AmmKind::V2, (addr(0xa), addr(0xb)), canonical(addr(2))).await;
+        assert!(matches!(verdict, PoolVerdict::Spoofed { canonical, .. } if canonical == addr(2)));
+
+        let verdict = verify(AmmKind::V2, (addr(0xa), addr(0xc)), canonical(addr(1))).await;
+        assert_eq!(verdict, PoolVerdict::TokenMismatch { pool_tokens: (addr(0xa), addr(0xb)) });
+    }
+
+    #[tokio::test]
+    async fn v3_pools_must_match_the_factory_for_their_fee_tier() {
+        let factory: Address = UNISWAP_V3.parse().unwrap();
+        // The factory has addr(1) under the 0.05% tier and a different pool under 0.3%.
+        let entries = |fee: u32| {
+            let mut entries = pool(factory);
+            entries.push(eth_call_entry(addr(1), "latest", FeeCall, U256::from(fee)));
+            for (tier, pool) in [(500, addr(1)), (3000, addr(3))] {
+                entries.push(eth_call_entry(factory, "latest", GetPoolCall { token_a: addr(0xa), token_b: addr(0xb), fee: tier }, pool));
+            }
+            entries
+        };
+
+        let verdict = verify(AmmKind::V3, (addr(0xa), addr(0xb)), entries(500)).await;
+        assert!(matches!(verdict, PoolVerdict::Verified { dex } if dex.name == "uniswap_v3"));
+
+        let verdict = verify(AmmKind::V3, (addr(0xa), addr(0xb)), entries(3000)).await;
+        assert!(matches!(verdict, PoolVerdict::Spoofed { canonical, .. } if canonical == addr(3)));
+
+        // A V2 factory doesn't vouch for a V3 pool.
+        let verdict = verify(AmmKind::V3, (addr(0xa), addr(0xb)), pool(UNISWAP_V2.parse().unwrap())).await;
+        assert!(matches!(verdict, PoolVerdict::UnknownFactory { .. }));
+    }
+}
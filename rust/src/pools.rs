@@ -0,0 +1,130 @@
+use crate::chains;
+use crate::dexes::{self, AmmKind, DexInfo, PoolVariant};
+use crate::pool_adapters;
+use crate::pools_abi::uniswap_v2_pair::UniswapV2Pair;
+use crate::pools_abi::uniswap_v3_immutables::UniswapV3Immutables;
+use crate::rpc;
+use crate::ArbitrageDirection;
+use anyhow::{anyhow, Result};
+use ethers::types::Address;
+use log::{error, info};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// What the engine knows about a pool beyond its address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolDescriptor {
+    pub address: Address,
+    pub kind: AmmKind,
+    pub variant: PoolVariant,
+    /// `None` for pools that don't come from a known factory (e.g. devnet mocks).
+    pub dex: Option<&'static DexInfo>,
+    /// Swap fee as a fraction, e.g. 0.0005 for a 5 bps V3 pool.
+    pub fee: f64,
+}
+
+/// V3 `fee()` and Algebra fees are in hundredths of a basis point: 500 = 0.05%.
+pub fn v3_fee_to_fraction(fee: u32) -> f64 {
+    fee as f64 / 1_000_000.0
+}
+
+static DESCRIPTORS: OnceLock<Mutex<HashMap<(u64, Address), PoolDescriptor>>> = OnceLock::new();
+
+fn descriptors() -> &'static Mutex<HashMap<(u64, Address), PoolDescriptor>> {
+    DESCRIPTORS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Resolves a pool's DEX and fee.
+///
+/// V3 pools report their own tier via `fee()`, Algebra pools via `globalState()`; pools from
+/// an unknown factory are read as plain Uniswap V3. V2 pairs have no fee getter, so the fee
+/// comes from the per-factory table in `dexes::DEXES`; a pair from an unknown factory is an
+/// error rather than a guessed 0.3%.
+pub async fn describe(rpc_url: &str, chain_id: u64, kind: AmmKind, address: Address) -> Result<PoolDescriptor> {
+    if let Some(d) = descriptors().lock().unwrap().get(&(chain_id, address)) {
+        return Ok(d.clone());
+    }
+
+    let client = rpc::provider(rpc_url)?;
+    let descriptor = match kind {
+        AmmKind::V2 => {
+            let factory = UniswapV2Pair::new(address, client).factory().call().await?;
+            let dex = dexes::by_factory(chain_id, factory)
+                .ok_or_else(|| anyhow!("Pair {:?} comes from unknown factory {:?}", address, factory))?;
+            let fee = dex
+                .fee
+                .ok_or_else(|| anyhow!("No fee known for {} pairs", dex.name))?;
+            PoolDescriptor { address, kind, variant: dex.variant, dex: Some(dex), fee }
+        }
+        AmmKind::V3 => {
+            let pool = UniswapV3Immutables::new(address, client.clone());
+            let factory = pool.factory().call().await?;
+            let dex = dexes::by_factory(chain_id, factory);
+            let variant = dex.map_or(PoolVariant::UniswapV3, |d| d.variant);
+
+            let fee = match variant {
+                // Algebra has no fee() getter. The descriptor keeps the token0→token1 fee;
+                // snapshots and simulations read the live fee for each direction.
+                PoolVariant::Algebra => {
+                    let state = pool_adapters::fetch_pool_state(client, variant, address).await?;
+                    state.fee_for(true).ok_or_else(|| anyhow!("Algebra pool {:?} reported no fee", address))?
+                }
+                _ => pool.fee().call().await?,
+            };
+            PoolDescriptor { address, kind, variant, dex, fee: v3_fee_to_fraction(fee) }
+        }
+    };
+
+    info!(
+        "Pool {:?}: {} fee={}",
+        address,
+        descriptor.dex.map_or("unknown dex", |d| d.name),
+        descriptor.fee
+    );
+    descriptors().lock().unwrap().insert((chain_id, address), descriptor.clone());
+    Ok(descriptor)
+}
+
+/// Fills `dir.pools` for every direction that doesn't have descriptors yet.
+/// Directions whose pools can't all be described are dropped.
+pub async fn describe_directions(directions: Vec<ArbitrageDirection>) -> Vec<ArbitrageDirection> {
+    let mut chain_ids: HashMap<String, u64> = HashMap::new();
+    let mut kept = Vec::with_capacity(directions.len());
+
+    for mut dir in directions {
+        if dir.pools.len() == dir.path.len() {
+            kept.push(dir);
+            continue;
+        }
+
+        let result: Result<Vec<PoolDescriptor>> = async {
+            let kind = AmmKind::from_pool_type(&dir.pool_type)
+                .ok_or_else(|| anyhow!("Unknown pool_type: {}", dir.pool_type))?;
+            let chain_id = match chain_ids.get(&dir.provider) {
+                Some(id) => *id,
+                None => {
+                    let id = chains::fetch_chain_id(&dir.provider).await?;
+                    chain_ids.insert(dir.provider.clone(), id);
+                    id
+                }
+            };
+
+            let mut pools = Vec::with_capacity(dir.path.len());
+            for pool in &dir.path {
+                pools.push(describe(&dir.provider, chain_id, kind, pool.parse()?).await?);
+            }
+            Ok(pools)
+        }
+        .await;
+
+        match result {
+            Ok(pools) => {
+                dir.pools = pools;
+                kept.push(dir);
+            }
+            Err(e) => error!("Skipping direction {:?}: {e:#}", dir.path),
+        }
+    }
+
+    kept
+}
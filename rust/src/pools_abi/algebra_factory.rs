@@ -0,0 +1,10 @@
+use ethers::contract::abigen;
+
+abigen!(
+    AlgebraFactory,
+    r#"[
+        function poolByPair(address tokenA, address tokenB) external view returns (address pool)
+        event Pool(address indexed token0, address indexed token1, address pool)
+    ]"#
+);
+pub use AlgebraFactory;
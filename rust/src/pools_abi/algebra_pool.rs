@@ -0,0 +1,12 @@
+use ethers::contract::abigen;
+
+// Algebra with directional fees (Camelot V3): no slot0(), fee lives in globalState().
+abigen!(
+    AlgebraPool,
+    r#"[
+        function globalState() view returns (uint160 price, int24 tick, uint16 feeZto, uint16 feeOtz, uint16 timepointIndex, uint8 communityFeeToken0, uint8 communityFeeToken1, bool unlocked)
+        function liquidity() view returns (uint128)
+    ]"#
+);
+
+pub use AlgebraPool;
@@ -6,7 +6,9 @@ abigen!(
         [
             function decimals() external view returns (uint8)
             function symbol() external view returns (string)
+            function name() external view returns (string)
             function balanceOf(address) external view returns (uint256)
+            function transfer(address to, uint256 amount) external returns (bool)
         ]
     "#
 );
@@ -1,7 +1,19 @@
 pub mod uniswap_v3_slot0;
 pub mod uniswap_v3_liquidity;
+pub mod uniswap_v3_immutables;
+pub mod uniswap_v3_factory;
+pub mod uniswap_v3_ticks;
+pub mod tick_lens;
+pub mod quoter_v2;
+pub mod pancake_v3_slot0;
+pub mod algebra_pool;
+pub mod algebra_factory;
 
-pub mod uniswap_v2_reservers;
 
 pub mod erc20_abi;
-pub mod uniswap_v2_pair;
\ No newline at end of file
+pub mod uniswap_v2_pair;
+pub mod uniswap_v2_pair_mock;
+pub mod uniswap_v2_factory;
+pub mod uniswap_v2_router;
+
+pub mod multicall3;
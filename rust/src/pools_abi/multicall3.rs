@@ -0,0 +1,11 @@
+use ethers::contract::abigen;
+
+abigen!(
+    Multicall3,
+    r#"[
+        struct Call3 { address target; bool allowFailure; bytes callData; }
+        struct Call3Result { bool success; bytes returnData; }
+        function aggregate3(Call3[] calls) external payable returns (Call3Result[] returnData)
+    ]"#
+);
+pub use Multicall3;
@@ -0,0 +1,9 @@
+use ethers::contract::abigen;
+
+// PancakeSwap V3 widened feeProtocol to uint32.
+abigen!(
+    PancakeV3Slot0,
+    r#"[ "function slot0() view returns (uint160 sqrtPriceX96, int24 tick, uint16 observationIndex, uint16 observationCardinality, uint16 observationCardinalityNext, uint32 feeProtocol, bool unlocked)" ]"#
+);
+
+pub use PancakeV3Slot0;
@@ -0,0 +1,10 @@
+use ethers::contract::abigen;
+
+abigen!(
+    TickLens,
+    r#"[
+        struct PopulatedTick { int24 tick; int128 liquidityNet; uint128 liquidityGross; }
+        function getPopulatedTicksInWord(address pool, int16 tickBitmapIndex) external view returns (PopulatedTick[] populatedTicks)
+    ]"#
+);
+pub use TickLens;
@@ -0,0 +1,10 @@
+use ethers::contract::abigen;
+
+abigen!(
+    UniswapV2Factory,
+    r#"[
+        function getPair(address tokenA, address tokenB) external view returns (address pair)
+        event PairCreated(address indexed token0, address indexed token1, address pair, uint256 allPairsLength)
+    ]"#
+);
+pub use UniswapV2Factory;
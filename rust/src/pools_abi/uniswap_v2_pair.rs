@@ -3,6 +3,7 @@ use ethers::contract::abigen;
 abigen!(
     UniswapV2Pair,
     r#"[
+        function factory() external view returns (address)
         function token0() external view returns (address)
         function token1() external view returns (address)
         function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast)
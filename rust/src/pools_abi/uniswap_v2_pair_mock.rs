@@ -0,0 +1,14 @@
+use ethers::contract::abigen;
+
+abigen!(
+    UniswapV2PairMock,
+    r#"[
+        function token0() external view returns (address)
+        function token1() external view returns (address)
+        function fee() external view returns (uint32)
+        function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast)
+        function sync() external
+        function swap(uint256 amount0Out, uint256 amount1Out, address to, bytes data) external
+    ]"#
+);
+pub use UniswapV2PairMock;
@@ -0,0 +1,10 @@
+use ethers::contract::abigen;
+
+abigen!(
+    UniswapV2Router02,
+    r#"[
+        function factory() external pure returns (address)
+        function getAmountsOut(uint256 amountIn, address[] path) external view returns (uint256[] amounts)
+    ]"#
+);
+pub use UniswapV2Router02;
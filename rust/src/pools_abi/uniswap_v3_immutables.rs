@@ -0,0 +1,13 @@
+use ethers::contract::abigen;
+
+abigen!(
+    UniswapV3Immutables,
+    r#"[
+        function factory() external view returns (address)
+        function token0() external view returns (address)
+        function token1() external view returns (address)
+        function fee() external view returns (uint24)
+        function tickSpacing() external view returns (int24)
+    ]"#
+);
+pub use UniswapV3Immutables;
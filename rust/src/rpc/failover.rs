@@ -0,0 +1,602 @@
+use crate::config::{ChainRpcSettings, RpcEndpointSettings};
+use crate::rpc::rate_limit::TokenBucket;
+use async_trait::async_trait;
+use ethers::providers::{HttpClientError, JsonRpcClient, JsonRpcError, ProviderError, RpcError};
+use log::{debug, warn};
+use reqwest::{StatusCode, Url};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+const MIN_HEALTH: f64 = 0.05;
+
+#[derive(Debug, Error)]
+pub enum FailoverClientError {
+    #[error("no RPC endpoints configured for chain {0}")]
+    NoEndpoints(String),
+    #[error("all retries exhausted after {attempts} attempts: {last}")]
+    Exhausted {
+        attempts: u32,
+        last: HttpClientError,
+    },
+    #[error(transparent)]
+    Http(#[from] HttpClientError),
+}
+
+impl RpcError for FailoverClientError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            FailoverClientError::Http(e) | FailoverClientError::Exhausted { last: e, .. } => {
+                e.as_error_response()
+            }
+            FailoverClientError::NoEndpoints(_) => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            FailoverClientError::Http(e) | FailoverClientError::Exhausted { last: e, .. } => {
+                e.as_serde_error()
+            }
+            FailoverClientError::NoEndpoints(_) => None,
+        }
+    }
+}
+
+impl From<FailoverClientError> for ProviderError {
+    fn from(src: FailoverClientError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(src))
+    }
+}
+
+/// How a failed request should be handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    /// 429 or provider-specific "rate limit" error: back off and cool the endpoint down.
+    RateLimited,
+    /// 5xx, timeouts, connection errors, garbage bodies: try another endpoint.
+    Transient,
+    /// 401/403: this endpoint refuses our key or plan. Other endpoints may not, so fail
+    /// over and demote it as far as it goes.
+    Rejected,
+    /// The node answered with a proper JSON-RPC error (revert, bad params): retrying won't help.
+    Fatal,
+}
+
+/// HTTP status of a failed request; `None` when there was no response at all.
+fn classify_status(status: Option<u16>) -> Failure {
+    match status {
+        Some(429) => Failure::RateLimited,
+        Some(401 | 403) => Failure::Rejected,
+        Some(code) if code >= 500 => Failure::Transient,
+        Some(_) => Failure::Fatal,
+        None => Failure::Transient,
+    }
+}
+
+fn classify(err: &HttpClientError) -> Failure {
+    match err {
+        HttpClientError::ReqwestError(e) => classify_status(e.status().map(|s| s.as_u16())),
+        HttpClientError::JsonRpcError(e) => {
+            let msg = e.message.to_lowercase();
+            if e.code == 429 || e.code == -32005 || msg.contains("rate limit") || msg.contains("too many requests") {
+                Failure::RateLimited
+            } else if e.code == -32603 && msg.contains("timeout") {
+                Failure::Transient
+            } else {
+                Failure::Fatal
+            }
+        }
+        HttpClientError::SerdeJson { text, .. } => {
+            let text = text.to_lowercase();
+            if text.contains("rate limit") || text.contains("429") || text.contains("too many requests") {
+                Failure::RateLimited
+            } else {
+                Failure::Transient
+            }
+        }
+    }
+}
+
+/// JSON-RPC over HTTP. ethers' `Http` never looks at the status code, so a 401 or 503
+/// whose body is a JSON-RPC error would read as a node-side error; here those statuses
+/// and 429 surface as a reqwest error carrying the status for `classify`.
+#[derive(Debug)]
+struct HttpTransport {
+    client: reqwest::Client,
+    url: Url,
+    next_id: AtomicU64,
+}
+
+impl HttpTransport {
+    fn new(url: &str, timeout: Duration) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self { client, url: url.parse()?, next_id: AtomicU64::new(1) })
+    }
+
+    async fn request<T: Serialize, R: DeserializeOwned>(&self, method: &str, params: T) -> Result<R, HttpClientError> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let payload = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let res = self.client.post(self.url.clone()).json(&payload).send().await?;
+
+        let status = res.status();
+        if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS)
+            || status.is_server_error()
+        {
+            res.error_for_status_ref()?;
+        }
+
+        let body = res.bytes().await?;
+        let garbage = |err| HttpClientError::SerdeJson { err, text: String::from_utf8_lossy(&body).to_string() };
+        let mut response: Value = serde_json::from_slice(&body).map_err(garbage)?;
+        if let Some(error) = response.get_mut("error").map(Value::take) {
+            return Err(HttpClientError::JsonRpcError(serde_json::from_value(error).map_err(garbage)?));
+        }
+        let result = response
+            .get_mut("result")
+            .map(Value::take)
+            .ok_or_else(|| garbage(serde::de::Error::missing_field("result")))?;
+        serde_json::from_value(result).map_err(garbage)
+    }
+}
+
+#[derive(Debug)]
+struct Health {
+    /// 1.0 = perfectly healthy, decays towards MIN_HEALTH on failures.
+    score: f64,
+    cooldown_until: Option<Instant>,
+    /// Smooth weighted round-robin accumulator.
+    current_weight: f64,
+}
+
+impl Health {
+    fn available(&self, now: Instant) -> bool {
+        self.cooldown_until.is_none_or(|until| until <= now)
+    }
+}
+
+#[derive(Debug)]
+pub struct Endpoint {
+    pub url: String,
+    pub weight: u32,
+    http: HttpTransport,
+    bucket: TokenBucket,
+    health: Mutex<Health>,
+}
+
+impl Endpoint {
+    pub fn new(settings: &RpcEndpointSettings) -> anyhow::Result<Self> {
+        Ok(Self {
+            url: settings.url.clone(),
+            weight: settings.weight.max(1),
+            http: HttpTransport::new(&settings.url, Duration::from_millis(settings.timeout_ms))?,
+            bucket: TokenBucket::new(settings.requests_per_second, settings.burst),
+            health: Mutex::new(Health {
+                score: 1.0,
+                cooldown_until: None,
+                current_weight: 0.0,
+            }),
+        })
+    }
+
+    pub fn health_score(&self) -> f64 {
+        self.health.lock().unwrap().score
+    }
+
+    fn record_success(&self) {
+        let mut health = self.health.lock().unwrap();
+        health.score = (health.score + 0.1).min(1.0);
+        health.cooldown_until = None;
+    }
+
+    fn record_failure(&self, failure: Failure, cooldown: Duration) {
+        let mut health = self.health.lock().unwrap();
+        match failure {
+            Failure::RateLimited => {
+                health.score = (health.score * 0.75).max(MIN_HEALTH);
+                health.cooldown_until = Some(Instant::now() + cooldown);
+            }
+            Failure::Transient => {
+                health.score = (health.score * 0.5).max(MIN_HEALTH);
+            }
+            Failure::Rejected => {
+                health.score = MIN_HEALTH;
+            }
+            Failure::Fatal => {}
+        }
+    }
+}
+
+/// `JsonRpcClient` spreading requests over several endpoints of one chain.
+///
+/// Endpoints are picked by smooth weighted round-robin where the configured weight is
+/// scaled by a health score; endpoints cooling down after a 429 are skipped while any
+/// other endpoint is available. Each endpoint has its own token-bucket rate limit.
+#[derive(Debug)]
+pub struct FailoverClient {
+    pub chain: String,
+    endpoints: Vec<Endpoint>,
+    max_retries: u32,
+    base_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl FailoverClient {
+    pub fn new(chain: impl Into<String>, settings: &ChainRpcSettings) -> anyhow::Result<Self> {
+        let chain = chain.into();
+        let endpoints = settings
+            .endpoints
+            .iter()
+            .map(Endpoint::new)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if endpoints.is_empty() {
+            return Err(FailoverClientError::NoEndpoints(chain).into());
+        }
+
+        Ok(Self {
+            chain,
+            endpoints,
+            max_retries: settings.max_retries,
+            base_backoff: Duration::from_millis(settings.base_backoff_ms),
+            max_backoff: Duration::from_millis(settings.max_backoff_ms),
+        })
+    }
+
+    /// Single endpoint with default limits, used for URLs that aren't listed in config.
+    pub fn single(url: &str) -> anyhow::Result<Self> {
+        let settings = ChainRpcSettings {
+            endpoints: vec![RpcEndpointSettings {
+                url: url.to_string(),
+                weight: 1,
+                requests_per_second: 10.0,
+                burst: 20,
+                timeout_ms: 10_000,
+            }],
+            ..Default::default()
+        };
+        let host = url.split("://").nth(1).unwrap_or(url).split('/').next().unwrap_or(url);
+        Self::new(host, &settings)
+    }
+
+    pub fn endpoints(&self) -> &[Endpoint] {
+        &self.endpoints
+    }
+
+    pub fn serves(&self, url: &str) -> bool {
+        self.endpoints.iter().any(|e| e.url == url)
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.base_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    fn pick_endpoint(&self) -> &Endpoint {
+        let now = Instant::now();
+        let mut healths: Vec<_> = self.endpoints.iter().map(|e| e.health.lock().unwrap()).collect();
+        let any_available = healths.iter().any(|h| h.available(now));
+
+        let mut total = 0.0;
+        let mut best: Option<(usize, f64)> = None;
+        for (i, (endpoint, health)) in self.endpoints.iter().zip(healths.iter_mut()).enumerate() {
+            if any_available && !health.available(now) {
+                continue;
+            }
+            let effective = endpoint.weight as f64 * health.score;
+            health.current_weight += effective;
+            total += effective;
+            if best.is_none_or(|(_, w)| health.current_weight > w) {
+                best = Some((i, health.current_weight));
+            }
+        }
+
+        let (idx, _) = best.expect("FailoverClient always has at least one endpoint");
+        healths[idx].current_weight -= total;
+        &self.endpoints[idx]
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for FailoverClient {
+    type Error = FailoverClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let mut attempt = 0;
+        loop {
+            let endpoint = self.pick_endpoint();
+            endpoint.bucket.acquire().await;
+
+            let err = match endpoint.http.request(method, &params).await {
+                Ok(res) => {
+                    endpoint.record_success();
+                    return Ok(res);
+                }
+                Err(e) => e,
+            };
+
+            let failure = classify(&err);
+            let backoff = self.backoff(attempt);
+            endpoint.record_failure(failure, backoff);
+
+            if failure == Failure::Fatal {
+                return Err(FailoverClientError::Http(err));
+            }
+
+            attempt += 1;
+            if attempt > self.max_retries {
+                return Err(FailoverClientError::Exhausted { attempts: attempt, last: err });
+            }
+
+            warn!(
+                "[{}] {} failed on {} ({:?}, health {:.2}), retry {}/{} in {:?}: {}",
+                self.chain,
+                method,
+                endpoint.url,
+                failure,
+                endpoint.health_score(),
+                attempt,
+                self.max_retries,
+                backoff,
+                err
+            );
+
+            // Rate limits always wait; transient errors fail over immediately when
+            // another endpoint can take the request.
+            if failure == Failure::RateLimited || self.endpoints.len() == 1 {
+                tokio::time::sleep(backoff).await;
+            } else {
+                debug!("[{}] failing over from {}", self.chain, endpoint.url);
+            }
+        }
+    }
+}
+
+/// Serves every request on a local port with `status` and a JSON-RPC `body`.
+#[cfg(test)]
+pub async fn stub(status: &'static str, body: &'static str) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Headers, then as many body bytes as Content-Length announces.
+            while let Ok(n @ 1..) = socket.read(&mut buf).await {
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_lowercase();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .and_then(|len| len.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length {
+                        break;
+                    }
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpc_error(code: i64, message: &str) -> HttpClientError {
+        HttpClientError::JsonRpcError(JsonRpcError { code, message: message.to_string(), data: None })
+    }
+
+    fn garbage(text: &str) -> HttpClientError {
+        HttpClientError::SerdeJson { err: serde_json::from_str::<u8>("x").unwrap_err(), text: text.to_string() }
+    }
+
+    fn client(weights: &[u32]) -> FailoverClient {
+        let settings = ChainRpcSettings {
+            endpoints: weights
+                .iter()
+                .enumerate()
+                .map(|(i, weight)| RpcEndpointSettings {
+                    url: format!("http://node{}.local:8545", i),
+                    weight: *weight,
+                    requests_per_second: 10.0,
+                    burst: 20,
+                    timeout_ms: 10_000,
+                })
+                .collect(),
+            base_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+            ..Default::default()
+        };
+        FailoverClient::new("test", &settings).unwrap()
+    }
+
+    fn picks(client: &FailoverClient, n: usize) -> Vec<usize> {
+        let mut counts = vec![0; client.endpoints().len()];
+        for _ in 0..n {
+            let picked = client.pick_endpoint();
+            counts[client.endpoints().iter().position(|e| std::ptr::eq(e, picked)).unwrap()] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn errors_are_classified_by_code_and_message() {
+        assert_eq!(classify(&rpc_error(429, "Too Many Requests")), Failure::RateLimited);
+        assert_eq!(classify(&rpc_error(-32005, "limit exceeded")), Failure::RateLimited);
+        assert_eq!(classify(&rpc_error(-32000, "Your app has exceeded its compute units per second capacity, rate limit")), Failure::RateLimited);
+        assert_eq!(classify(&rpc_error(-32603, "request timeout")), Failure::Transient);
+        assert_eq!(classify(&rpc_error(-32603, "internal error")), Failure::Fatal);
+        assert_eq!(classify(&rpc_error(3, "execution reverted")), Failure::Fatal);
+        assert_eq!(classify(&garbage("error code: 429")), Failure::RateLimited);
+        assert_eq!(classify(&garbage("<html>502 Bad Gateway</html>")), Failure::Transient);
+    }
+
+    /// Accepts connections on a local port and never answers them.
+    async fn silent() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+        url
+    }
+
+    fn endpoints(urls: &[String]) -> FailoverClient {
+        let settings = ChainRpcSettings {
+            endpoints: urls
+                .iter()
+                .map(|url| RpcEndpointSettings {
+                    url: url.clone(),
+                    weight: 1,
+                    requests_per_second: 100.0,
+                    burst: 100,
+                    timeout_ms: 200,
+                })
+                .collect(),
+            max_retries: 2,
+            base_backoff_ms: 1,
+            max_backoff_ms: 1,
+        };
+        FailoverClient::new("test", &settings).unwrap()
+    }
+
+    #[test]
+    fn statuses_are_classified() {
+        assert_eq!(classify_status(Some(401)), Failure::Rejected);
+        assert_eq!(classify_status(Some(403)), Failure::Rejected);
+        assert_eq!(classify_status(Some(429)), Failure::RateLimited);
+        assert_eq!(classify_status(Some(400)), Failure::Fatal);
+        assert_eq!(classify_status(Some(503)), Failure::Transient);
+        assert_eq!(classify_status(None), Failure::Transient);
+    }
+
+    #[tokio::test]
+    async fn auth_failures_fail_over_instead_of_aborting() {
+        const UNAUTHORIZED: &str = r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"invalid API key"}}"#;
+        let refused = stub("401 Unauthorized", UNAUTHORIZED).await;
+        let healthy = stub("200 OK", r#"{"jsonrpc":"2.0","id":1,"result":"0xa4b1"}"#).await;
+
+        let client = endpoints(&[refused, healthy]);
+        let chain_id: String = client.request("eth_chainId", ()).await.unwrap();
+        assert_eq!(chain_id, "0xa4b1");
+        assert_eq!(client.endpoints()[0].health_score(), MIN_HEALTH);
+        assert_eq!(client.endpoints()[1].health_score(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_elsewhere() {
+        const UNAVAILABLE: &str = r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32603,"message":"upstream unavailable"}}"#;
+        let down = stub("503 Service Unavailable", UNAVAILABLE).await;
+        let healthy = stub("200 OK", r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#).await;
+
+        let client = endpoints(&[down.clone(), healthy]);
+        let block: String = client.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block, "0x10");
+        assert_eq!(client.endpoints()[0].health_score(), 0.5);
+
+        // Alone, the endpoint keeps failing transiently until the retries run out.
+        let err = endpoints(&[down]).request::<_, String>("eth_blockNumber", ()).await.unwrap_err();
+        assert!(matches!(err, FailoverClientError::Exhausted { attempts: 3, .. }), "{err}");
+    }
+
+    #[tokio::test]
+    async fn hung_endpoints_time_out_and_fail_over() {
+        let hung = silent().await;
+        let healthy = stub("200 OK", r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#).await;
+
+        let client = endpoints(&[hung, healthy]);
+        let started = Instant::now();
+        let block: String = client.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block, "0x10");
+        assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+        assert_eq!(client.endpoints()[0].health_score(), 0.5);
+    }
+
+    #[tokio::test]
+    async fn json_rpc_errors_with_a_success_status_are_fatal() {
+        const REVERTED: &str = r#"{"jsonrpc":"2.0","id":1,"error":{"code":3,"message":"execution reverted","data":"0x"}}"#;
+        let node = stub("200 OK", REVERTED).await;
+        let client = endpoints(&[node]);
+        let err = client.request::<_, String>("eth_call", ()).await.unwrap_err();
+        assert!(matches!(err, FailoverClientError::Http(HttpClientError::JsonRpcError(ref e)) if e.code == 3), "{err}");
+        assert_eq!(client.endpoints()[0].health_score(), 1.0);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let client = client(&[1]);
+        let backoffs: Vec<u64> = (0..5).map(|a| client.backoff(a).as_millis() as u64).collect();
+        assert_eq!(backoffs, vec![100, 200, 400, 800, 1_000]);
+        assert_eq!(client.backoff(64), Duration::from_millis(1_000));
+    }
+
+    #[test]
+    fn picks_follow_the_configured_weights() {
+        assert_eq!(picks(&client(&[3, 1]), 8), vec![6, 2]);
+    }
+
+    #[test]
+    fn failing_endpoints_are_demoted_and_recover() {
+        let client = client(&[1, 1]);
+        let flaky = &client.endpoints()[0];
+
+        for _ in 0..3 {
+            flaky.record_failure(Failure::Transient, Duration::ZERO);
+        }
+        assert_eq!(flaky.health_score(), 0.125);
+        assert_eq!(picks(&client, 9), vec![1, 8]);
+
+        for _ in 0..20 {
+            flaky.record_failure(Failure::Transient, Duration::ZERO);
+        }
+        assert_eq!(flaky.health_score(), MIN_HEALTH);
+
+        flaky.record_failure(Failure::Fatal, Duration::ZERO);
+        assert_eq!(flaky.health_score(), MIN_HEALTH);
+
+        for _ in 0..10 {
+            flaky.record_success();
+        }
+        assert_eq!(flaky.health_score(), 1.0);
+    }
+
+    #[test]
+    fn rate_limited_endpoints_cool_down() {
+        let client = client(&[1, 1]);
+        let limited = &client.endpoints()[0];
+
+        limited.record_failure(Failure::RateLimited, Duration::from_secs(60));
+        assert_eq!(limited.health_score(), 0.75);
+        assert_eq!(picks(&client, 4), vec![0, 4]);
+
+        // With every endpoint cooling down one of them still gets the request.
+        client.endpoints()[1].record_failure(Failure::RateLimited, Duration::from_secs(60));
+        assert_eq!(picks(&client, 2).iter().sum::<usize>(), 2);
+
+        limited.record_success();
+        assert!(picks(&client, 4)[0] > 0);
+    }
+}
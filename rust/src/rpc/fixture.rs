@@ -0,0 +1,354 @@
+use crate::rpc::failover::{FailoverClient, FailoverClientError};
+use anyhow::{Context, Result};
+use ethers::providers::{HttpClientError, JsonRpcClient, JsonRpcError, ProviderError, RpcError};
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// One recorded JSON-RPC exchange: a `result`, or the node's `error` when it answered with
+/// one (a revert, bad params...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureEntry {
+    pub method: String,
+    pub params: Value,
+    #[serde(default)]
+    pub result: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+impl FixtureEntry {
+    pub fn new(method: &str, params: Value, result: Value) -> Self {
+        FixtureEntry { method: method.to_string(), params, result, error: None }
+    }
+
+    pub fn error(method: &str, params: Value, error: &JsonRpcError) -> Self {
+        let error = json!({ "code": error.code, "message": error.message, "data": error.data });
+        FixtureEntry { method: method.to_string(), params, result: Value::Null, error: Some(error) }
+    }
+
+    /// The recorded answer, with a node error surfaced the way the live transport does.
+    fn outcome(&self) -> Result<Value, ProviderError> {
+        match &self.error {
+            None => Ok(self.result.clone()),
+            Some(error) => {
+                let error: JsonRpcError = serde_json::from_value(error.clone())?;
+                Err(FailoverClientError::Http(HttpClientError::JsonRpcError(error)).into())
+            }
+        }
+    }
+}
+
+/// An `eth_call` of `data` on `to` at `block` ("latest" or a hex number) returning `result`.
+#[cfg(test)]
+pub fn eth_call_entry(
+    to: ethers::types::Address,
+    block: &str,
+    data: impl ethers::abi::AbiEncode,
+    result: impl ethers::abi::AbiEncode,
+) -> FixtureEntry {
+    use ethers::utils::hex;
+
+    FixtureEntry::new(
+        "eth_call",
+        json!([{ "to": format!("{:?}", to), "data": format!("0x{}", hex::encode(data.encode())) }, block]),
+        json!(format!("0x{}", hex::encode(result.encode()))),
+    )
+}
+
+/// Recorded exchanges written since the last flush before record mode writes the file
+/// again on its own.
+const FLUSH_EVERY: usize = 64;
+
+/// Request/response pairs keyed by a normalized form of `(method, params)`.
+#[derive(Debug, Default)]
+struct FixtureBook {
+    entries: Vec<FixtureEntry>,
+    index: HashMap<String, usize>,
+    /// Entries inserted since the book was last loaded or saved.
+    unsaved: usize,
+}
+
+impl FixtureBook {
+    fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read fixture file {}", path.display()))?;
+        let entries: Vec<FixtureEntry> = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse fixture file {}", path.display()))?;
+
+        let mut book = FixtureBook::default();
+        for entry in entries {
+            book.insert(entry);
+        }
+        book.unsaved = 0;
+        Ok(book)
+    }
+
+    fn insert(&mut self, entry: FixtureEntry) {
+        self.unsaved += 1;
+        let key = fixture_key(&entry.method, &entry.params);
+        match self.index.get(&key) {
+            Some(&i) => self.entries[i] = entry,
+            None => {
+                self.index.insert(key, self.entries.len());
+                self.entries.push(entry);
+            }
+        }
+    }
+
+    fn get(&self, method: &str, params: &Value) -> Option<&FixtureEntry> {
+        self.index
+            .get(&fixture_key(method, params))
+            .map(|&i| &self.entries[i])
+    }
+
+    fn save(&mut self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&self.entries)?)?;
+        self.unsaved = 0;
+        Ok(())
+    }
+}
+
+/// Builds the lookup key for a request.
+///
+/// `eth_call` is keyed only by sender, target, calldata, block and state overrides so that
+/// fixtures don't depend on how ethers happens to serialize the rest of the transaction
+/// (type, gas, chain id...). The sender and overrides change what the call returns, e.g.
+/// a dry run with injected code versus the deployed contract. Everything else is keyed by
+/// its lowercased params.
+pub fn fixture_key(method: &str, params: &Value) -> String {
+    if method == "eth_call" {
+        let tx = params.get(0).cloned().unwrap_or(Value::Null);
+        let field = |name: &str| {
+            tx.get(name)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_lowercase()
+        };
+        let data = if tx.get("input").is_some() { field("input") } else { field("data") };
+        let block = params
+            .get(1)
+            .map(|b| b.to_string().to_lowercase())
+            .unwrap_or_else(|| "\"latest\"".to_string());
+        let overrides = params
+            .get(2)
+            .filter(|o| !o.is_null())
+            .map(|o| o.to_string().to_lowercase())
+            .unwrap_or_default();
+        return format!("eth_call:{}:{}:{}:{}:{}", field("from"), field("to"), data, block, overrides);
+    }
+
+    format!("{}:{}", method, params.to_string().to_lowercase())
+}
+
+#[derive(Debug)]
+enum Mode {
+    Record(Box<FailoverClient>),
+    Replay,
+}
+
+/// `JsonRpcClient` that either records live traffic to a fixture file or serves it back.
+///
+/// Replay never touches the network: an unknown request is an error, which keeps tests
+/// deterministic and makes missing fixtures obvious. Record mode buffers exchanges in
+/// memory and writes the file every `FLUSH_EVERY` new entries, on `flush` and on drop.
+#[derive(Debug)]
+pub struct FixtureClient {
+    mode: Mode,
+    path: PathBuf,
+    book: Mutex<FixtureBook>,
+}
+
+impl FixtureClient {
+    /// Forwards every request to `inner` and appends the exchange to `path`.
+    /// Existing entries in the file are kept.
+    pub fn record(inner: FailoverClient, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let book = if path.exists() { FixtureBook::load(&path)? } else { FixtureBook::default() };
+        info!("Recording RPC fixtures for {} to {}", inner.chain, path.display());
+        Ok(Self {
+            mode: Mode::Record(Box::new(inner)),
+            path,
+            book: Mutex::new(book),
+        })
+    }
+
+    /// Serves responses from `path` only.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let book = FixtureBook::load(&path)?;
+        info!("Replaying {} RPC fixtures from {}", book.entries.len(), path.display());
+        Ok(Self {
+            mode: Mode::Replay,
+            path,
+            book: Mutex::new(book),
+        })
+    }
+
+    /// Serves `entries` only, without a backing file.
+    #[cfg(test)]
+    pub fn replay_entries(entries: Vec<FixtureEntry>) -> Self {
+        let mut book = FixtureBook::default();
+        for entry in entries {
+            book.insert(entry);
+        }
+        Self {
+            mode: Mode::Replay,
+            path: PathBuf::from("<memory>"),
+            book: Mutex::new(book),
+        }
+    }
+
+    pub fn serves(&self, url: &str) -> bool {
+        match &self.mode {
+            Mode::Record(inner) => inner.serves(url),
+            Mode::Replay => false,
+        }
+    }
+
+    /// Writes recorded exchanges not yet on disk. A no-op in replay mode.
+    pub fn flush(&self) -> Result<()> {
+        let mut book = self.book.lock().unwrap();
+        if !matches!(self.mode, Mode::Record(_)) || book.unsaved == 0 {
+            return Ok(());
+        }
+        let written = book.unsaved;
+        book.save(&self.path)
+            .with_context(|| format!("Failed to write fixture file {}", self.path.display()))?;
+        info!("Wrote {} new RPC fixtures to {}", written, self.path.display());
+        Ok(())
+    }
+
+    pub async fn request<T, R>(&self, method: &str, params: T) -> Result<R, ProviderError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(&params)?;
+
+        let result: Value = match &self.mode {
+            Mode::Replay => {
+                let book = self.book.lock().unwrap();
+                match book.get(method, &params) {
+                    Some(entry) => entry.outcome()?,
+                    None => {
+                        return Err(ProviderError::CustomError(format!(
+                            "no fixture for {} {} in {}",
+                            method,
+                            params,
+                            self.path.display()
+                        )))
+                    }
+                }
+            }
+            Mode::Record(inner) => {
+                // Node errors are answers worth replaying; transport failures are not.
+                let (entry, outcome) = match inner.request::<_, Value>(method, &params).await {
+                    Ok(result) => (FixtureEntry::new(method, params, result.clone()), Ok(result)),
+                    Err(e) => match e.as_error_response() {
+                        Some(error) => (FixtureEntry::error(method, params, error), Err(e)),
+                        None => return Err(e.into()),
+                    },
+                };
+                let mut book = self.book.lock().unwrap();
+                book.insert(entry);
+                if book.unsaved >= FLUSH_EVERY {
+                    if let Err(e) = book.save(&self.path) {
+                        log::error!("Failed to write fixture file {}: {e:#}", self.path.display());
+                    }
+                }
+                outcome?
+            }
+        };
+
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+impl Drop for FixtureClient {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            log::error!("{e:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(from: Option<&str>, overrides: Option<Value>) -> Value {
+        let mut tx = json!({ "to": "0xAB", "data": "0x01", "gas": "0x5208" });
+        if let Some(from) = from {
+            tx["from"] = json!(from);
+        }
+        match overrides {
+            Some(o) => json!([tx, "latest", o]),
+            None => json!([tx, "latest"]),
+        }
+    }
+
+    #[test]
+    fn eth_call_keys_include_sender_and_state_overrides() {
+        let plain = fixture_key("eth_call", &call(None, None));
+        assert_eq!(plain, "eth_call::0xab:0x01:\"latest\":");
+        assert_eq!(fixture_key("eth_call", &call(None, Some(Value::Null))), plain);
+
+        let owner = fixture_key("eth_call", &call(Some("0xCD"), None));
+        assert_ne!(owner, plain);
+        assert_eq!(owner, fixture_key("eth_call", &call(Some("0xcd"), None)));
+
+        let injected = fixture_key("eth_call", &call(Some("0xcd"), Some(json!({ "0xab": { "code": "0x60" } }))));
+        assert_ne!(injected, owner);
+    }
+
+    #[test]
+    fn recorded_entries_stay_in_memory_until_saved() {
+        let path = std::env::temp_dir().join(format!("fixture-book-{}.json", std::process::id()));
+        let mut book = FixtureBook::default();
+        for n in 0..3 {
+            book.insert(FixtureEntry::new("eth_blockNumber", json!([n]), json!(n)));
+        }
+        assert_eq!(book.unsaved, 3);
+        assert!(!path.exists());
+
+        book.save(&path).unwrap();
+        assert_eq!(book.unsaved, 0);
+        let loaded = FixtureBook::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!((loaded.entries.len(), loaded.unsaved), (3, 0));
+        assert_eq!(loaded.get("eth_blockNumber", &json!([2])).unwrap().result, json!(2));
+    }
+
+    #[tokio::test]
+    async fn node_errors_are_recorded_and_replayed_as_errors() {
+        const REVERTED: &str =
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":3,"message":"execution reverted","data":"0x08c379a0"}}"#;
+        let url = crate::rpc::failover::stub("200 OK", REVERTED).await;
+        let path = std::env::temp_dir().join(format!("fixture-errors-{}.json", std::process::id()));
+        let params = json!([{ "to": "0xab", "data": "0x01" }, "latest"]);
+
+        let revert_data = |err: ProviderError| {
+            let error = err.as_error_response().cloned().expect("a JSON-RPC error");
+            (error.code, error.data)
+        };
+        let recorder = FixtureClient::record(FailoverClient::single(&url).unwrap(), &path).unwrap();
+        let recorded = recorder.request::<_, Value>("eth_call", &params).await.unwrap_err();
+        recorder.flush().unwrap();
+        assert_eq!(revert_data(recorded), (3, Some(json!("0x08c379a0"))));
+
+        let replay = FixtureClient::replay(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let replayed = replay.request::<_, Value>("eth_call", &params).await.unwrap_err();
+        assert_eq!(revert_data(replayed), (3, Some(json!("0x08c379a0"))));
+    }
+}
@@ -0,0 +1,178 @@
+pub mod failover;
+pub mod fixture;
+pub mod rate_limit;
+
+use crate::config::{FixtureMode, RpcSettings};
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::providers::{JsonRpcClient, Provider, ProviderError};
+use log::info;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+pub use failover::FailoverClient;
+pub use fixture::FixtureClient;
+
+/// The transport behind every provider the engine hands out.
+#[derive(Debug)]
+pub enum RpcClient {
+    Failover(FailoverClient),
+    Fixture(FixtureClient),
+}
+
+impl RpcClient {
+    pub fn serves(&self, url: &str) -> bool {
+        match self {
+            RpcClient::Failover(c) => c.serves(url),
+            RpcClient::Fixture(c) => c.serves(url),
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for RpcClient {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        match self {
+            RpcClient::Failover(c) => Ok(c.request(method, params).await?),
+            RpcClient::Fixture(c) => c.request(method, params).await,
+        }
+    }
+}
+
+pub type RpcProvider = Provider<RpcClient>;
+
+/// Providers shared by every simulator, so rate limits and health scores are per endpoint
+/// rather than per `SimulateTradeLoop` instance.
+#[derive(Default)]
+struct Registry {
+    chains: Vec<Arc<RpcProvider>>,
+    by_url: HashMap<String, Arc<RpcProvider>>,
+}
+
+static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+
+fn registry() -> &'static Mutex<Registry> {
+    REGISTRY.get_or_init(|| Mutex::new(Registry::default()))
+}
+
+/// Builds one provider per configured chain. Call once at startup.
+pub fn init(settings: &RpcSettings) -> Result<()> {
+    let mut registry = registry().lock().unwrap();
+    let fixtures_dir = Path::new(&settings.fixtures.dir);
+
+    for (chain, chain_settings) in &settings.chains {
+        let failover = FailoverClient::new(chain.clone(), chain_settings)?;
+        info!("RPC chain {}: {} endpoint(s)", chain, failover.endpoints().len());
+
+        let fixture_path = fixtures_dir.join(format!("{}.json", chain));
+        let client = match settings.fixtures.mode {
+            FixtureMode::Off => RpcClient::Failover(failover),
+            FixtureMode::Record => RpcClient::Fixture(FixtureClient::record(failover, fixture_path)?),
+            FixtureMode::Replay => {
+                let replay = Arc::new(Provider::new(RpcClient::Fixture(FixtureClient::replay(fixture_path)?)));
+                for endpoint in failover.endpoints() {
+                    registry.by_url.insert(endpoint.url.clone(), replay.clone());
+                }
+                continue;
+            }
+        };
+        registry.chains.push(Arc::new(Provider::new(client)));
+    }
+    Ok(())
+}
+
+/// Writes every recording client's buffered exchanges to its fixture file. Call before
+/// exiting; providers live in a static registry and are never dropped.
+pub fn flush() -> Result<()> {
+    let registry = registry().lock().unwrap();
+    for provider in &registry.chains {
+        if let RpcClient::Fixture(client) = provider.as_ref().as_ref() {
+            client.flush()?;
+        }
+    }
+    Ok(())
+}
+
+tokio::task_local! {
+    static SCOPED: HashMap<String, Arc<RpcProvider>>;
+}
+
+/// Runs `fut` with `client` serving `url` for every `provider(url)` made inside it, without
+/// touching the shared registry. Used by tests to point a direction's `provider` at a
+/// replay fixture; tasks spawned from `fut` don't see the override.
+#[cfg(test)]
+pub async fn scoped<F: std::future::Future>(url: &str, client: RpcClient, fut: F) -> F::Output {
+    let mut providers = SCOPED.try_with(|p| p.clone()).unwrap_or_default();
+    providers.insert(url.to_string(), Arc::new(Provider::new(client)));
+    SCOPED.scope(providers, fut).await
+}
+
+/// Returns the provider serving `rpc_url`.
+///
+/// If the URL is one of a configured chain's endpoints the whole chain's failover set is
+/// used; otherwise a single-endpoint client with default limits is created and reused.
+pub fn provider(rpc_url: &str) -> Result<Arc<RpcProvider>> {
+    if let Ok(Some(p)) = SCOPED.try_with(|p| p.get(rpc_url).cloned()) {
+        return Ok(p);
+    }
+
+    let mut registry = registry().lock().unwrap();
+
+    if let Some(p) = registry.by_url.get(rpc_url) {
+        return Ok(p.clone());
+    }
+
+    let serves = |p: &&Arc<RpcProvider>| {
+        let client: &RpcClient = (***p).as_ref();
+        client.serves(rpc_url)
+    };
+
+    let provider = match registry.chains.iter().find(serves) {
+        Some(p) => p.clone(),
+        None => Arc::new(Provider::new(RpcClient::Failover(FailoverClient::single(rpc_url)?))),
+    };
+
+    registry.by_url.insert(rpc_url.to_string(), provider.clone());
+    Ok(provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::providers::Middleware;
+
+    fn replay(name: &str) -> RpcClient {
+        let path = format!("{}/tests/fixtures/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+        RpcClient::Fixture(FixtureClient::replay(path).unwrap())
+    }
+
+    fn is_fixture(provider: &RpcProvider) -> bool {
+        matches!(provider.as_ref(), RpcClient::Fixture(_))
+    }
+
+    #[tokio::test]
+    async fn scoped_clients_stay_inside_their_future() {
+        let url = "fixture://scoped";
+        let chain_id = scoped(url, replay("v3_arbitrum"), async {
+            let nested = scoped("fixture://nested", replay("v2_ethereum"), async {
+                provider("fixture://nested").unwrap().get_chainid().await.unwrap()
+            })
+            .await;
+            assert_eq!(nested.as_u64(), 1);
+            provider(url).unwrap().get_chainid().await.unwrap()
+        })
+        .await;
+        assert_eq!(chain_id.as_u64(), 42161);
+
+        assert!(!is_fixture(&provider(url).unwrap()));
+    }
+}
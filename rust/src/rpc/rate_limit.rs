@@ -0,0 +1,92 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Classic token bucket: `burst` tokens, refilled at `rate` tokens per second.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        let capacity = burst.max(1) as f64;
+        Self {
+            rate: requests_per_second.max(0.001),
+            capacity,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token if one is available, otherwise returns how long to wait for the next one.
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.rate).min(self.capacity);
+        state.last_refill = now;
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - state.tokens) / self.rate))
+        }
+    }
+
+    /// Waits until a token is available and takes it.
+    pub async fn acquire(&self) {
+        while let Err(wait) = self.try_acquire() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_is_spent_then_waits_for_refill() {
+        let bucket = TokenBucket::new(10.0, 3);
+        for _ in 0..3 {
+            assert!(bucket.try_acquire().is_ok());
+        }
+        let wait = bucket.try_acquire().unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_millis(100), "{:?}", wait);
+    }
+
+    #[test]
+    fn refills_at_the_rate_up_to_capacity() {
+        let bucket = TokenBucket::new(100.0, 2);
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_err());
+
+        // 50ms at 100/s would be 5 tokens, but the bucket only holds 2.
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_err());
+    }
+
+    #[tokio::test]
+    async fn acquire_waits_instead_of_failing() {
+        let bucket = TokenBucket::new(200.0, 1);
+        bucket.acquire().await;
+        let start = Instant::now();
+        bucket.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(3));
+    }
+}
@@ -0,0 +1,546 @@
+use crate::chains;
+use crate::dexes::AmmKind;
+use crate::pool_adapters::{self, PoolState};
+use crate::pools::{v3_fee_to_fraction, PoolDescriptor};
+use crate::pools_abi::uniswap_v2_pair::UniswapV2Pair;
+use crate::pools_abi::uniswap_v3_immutables::UniswapV3Immutables;
+use crate::rpc;
+use crate::simulator::v3_math::liquidity_math::add_delta;
+use crate::simulator::v3_math::swap_math::compute_swap_step;
+use crate::simulator::v3_math::tick_math::{
+    get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK,
+};
+use crate::ticks::{self, PoolTicks};
+use anyhow::{anyhow, Result};
+use ethers::types::{Address, BlockId, BlockNumber, I256, U256};
+
+pub use crate::simulator::v3_math::full_math::mul_div;
+
+/// 2^96, the fixed-point scale of `sqrtPriceX96`.
+pub fn q96() -> U256 {
+    U256::one() << 96
+}
+
+/// Fee fraction in millionths, the unit V3 pools use (0.003 -> 3000).
+pub fn fee_pips(fee: f64) -> U256 {
+    U256::from((fee * 1_000_000.0).round() as u64)
+}
+
+/// `getAmountOut` of a constant-product pair.
+pub fn v2_amount_out(reserve_in: U256, reserve_out: U256, amount_in: U256, fee: f64) -> Result<U256> {
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return Err(anyhow!("Pair has no liquidity"));
+    }
+    let amount_in_with_fee = amount_in * (U256::from(1_000_000u64) - fee_pips(fee));
+    let numerator = amount_in_with_fee * reserve_out;
+    let denominator = reserve_in * U256::from(1_000_000u64) + amount_in_with_fee;
+    Ok(numerator / denominator)
+}
+
+/// The widest price limit a swap can pass, as routers set `sqrtPriceLimitX96`.
+fn price_limit(zero_for_one: bool) -> U256 {
+    if zero_for_one {
+        MIN_SQRT_RATIO + 1
+    } else {
+        MAX_SQRT_RATIO - 1
+    }
+}
+
+fn to_i256(amount: U256) -> Result<I256> {
+    I256::try_from(amount).map_err(|_| anyhow!("Amount {} overflows int256", amount))
+}
+
+/// Outcome of a V3 swap: the gross input, fee included, the output and the pool state
+/// after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct V3Swap {
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub state: PoolState,
+}
+
+/// `UniswapV3Pool.swap` for a non-negative exact input or a negative exact output. With
+/// `ticks` it steps from one initialized tick to the next, applying each tick's
+/// `liquidityNet`, and fails if the swap runs past the fetched words. Without them it is a
+/// single `computeSwapStep` on the in-range liquidity, exact as long as no initialized
+/// tick is crossed.
+pub fn v3_swap(
+    state: &PoolState,
+    ticks: Option<&PoolTicks>,
+    amount_specified: I256,
+    fee: f64,
+    zero_for_one: bool,
+) -> Result<V3Swap> {
+    if state.sqrt_price_x96.is_zero() || (ticks.is_none() && state.liquidity == 0) {
+        return Err(anyhow!("Pool has no liquidity"));
+    }
+    let exact_in = !amount_specified.is_negative();
+    let limit = price_limit(zero_for_one);
+    let fee_pips = fee_pips(fee).as_u32();
+
+    let mut remaining = amount_specified.unsigned_abs();
+    let mut next_state = state.clone();
+    let (mut amount_in, mut amount_out) = (U256::zero(), U256::zero());
+
+    while !remaining.is_zero() && next_state.sqrt_price_x96 != limit {
+        let next = match ticks {
+            Some(ticks) => Some(ticks.next_initialized(next_state.tick, zero_for_one).ok_or_else(|| {
+                anyhow!("Swap runs past the ticks fetched in words {:?}", ticks.words)
+            })?),
+            None => None,
+        };
+        let target = match next {
+            Some(next) => {
+                let sqrt_next = get_sqrt_ratio_at_tick(next.tick.clamp(MIN_TICK, MAX_TICK))?;
+                if zero_for_one { sqrt_next.max(limit) } else { sqrt_next.min(limit) }
+            }
+            None => limit,
+        };
+
+        let amount_remaining = if exact_in { to_i256(remaining)? } else { -to_i256(remaining)? };
+        let step = compute_swap_step(next_state.sqrt_price_x96, target, next_state.liquidity, amount_remaining, fee_pips)?;
+        let gross = step.amount_in + step.fee_amount;
+        remaining = remaining.saturating_sub(if exact_in { gross } else { step.amount_out });
+        amount_in += gross;
+        amount_out += step.amount_out;
+        next_state.sqrt_price_x96 = step.sqrt_ratio_next_x96;
+
+        match next {
+            Some(next) if step.sqrt_ratio_next_x96 == target => {
+                // Crossing leftwards takes the tick's liquidity out instead of adding it.
+                let net = if zero_for_one { -next.liquidity_net } else { next.liquidity_net };
+                next_state.liquidity = add_delta(next_state.liquidity, net)?;
+                next_state.tick = if zero_for_one { next.tick - 1 } else { next.tick };
+            }
+            _ => {
+                next_state.tick = get_tick_at_sqrt_ratio(next_state.sqrt_price_x96)?;
+                if next.is_none() {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(V3Swap { amount_in, amount_out, state: next_state })
+}
+
+/// Exact-input V3 swap. Returns the output amount and the square-root price after the swap.
+pub fn v3_amount_out(
+    state: &PoolState,
+    ticks: Option<&PoolTicks>,
+    amount_in: U256,
+    fee: f64,
+    zero_for_one: bool,
+) -> Result<(U256, U256)> {
+    let swap = v3_swap(state, ticks, to_i256(amount_in)?, fee, zero_for_one)?;
+    Ok((swap.amount_out, swap.state.sqrt_price_x96))
+}
+
+/// `getAmountIn` of a constant-product pair: the smallest input that yields `amount_out`.
+pub fn v2_amount_in(reserve_in: U256, reserve_out: U256, amount_out: U256, fee: f64) -> Result<U256> {
+    if amount_out >= reserve_out {
+        return Err(anyhow!("Requested output exceeds the pair's reserve"));
+    }
+    let numerator = reserve_in * amount_out * U256::from(1_000_000u64);
+    let denominator = (reserve_out - amount_out) * (U256::from(1_000_000u64) - fee_pips(fee));
+    Ok(numerator / denominator + 1)
+}
+
+/// Exact-output V3 swap. Returns the gross input, fee included, and the square-root price
+/// after the swap.
+pub fn v3_amount_in(
+    state: &PoolState,
+    ticks: Option<&PoolTicks>,
+    amount_out: U256,
+    fee: f64,
+    zero_for_one: bool,
+) -> Result<(U256, U256)> {
+    let swap = v3_swap(state, ticks, -to_i256(amount_out)?, fee, zero_for_one)?;
+    if swap.amount_out < amount_out {
+        return Err(anyhow!("Requested output exceeds the pool's liquidity"));
+    }
+    Ok((swap.amount_in, swap.state.sqrt_price_x96))
+}
+
+/// token1 per token0 in raw units for a `sqrtPriceX96`.
+pub fn sqrt_price_to_price(sqrt_price_x96: U256) -> f64 {
+    let sqrt = sqrt_price_x96.to_string().parse::<f64>().unwrap_or(0.0) / 2f64.powi(96);
+    sqrt * sqrt
+}
+
+/// Lossy conversion of a raw amount for the float solvers.
+pub(crate) fn to_f64(v: U256) -> f64 {
+    v.to_string().parse::<f64>().unwrap_or(0.0)
+}
+
+/// A float solver's amount back in raw units, rounded down and clamped at zero.
+pub(crate) fn from_f64(v: f64) -> U256 {
+    U256::from(v.max(0.0).floor() as u128)
+}
+
+/// Pool reserves or in-range liquidity at one point in time, enough to quote swaps offline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoolSnapshot {
+    V2 {
+        address: Address,
+        token0: Address,
+        token1: Address,
+        reserve0: U256,
+        reserve1: U256,
+        fee: f64,
+    },
+    V3 {
+        address: Address,
+        token0: Address,
+        token1: Address,
+        state: PoolState,
+        fee: f64,
+        /// Initialized ticks around `state.tick`; swaps cross them when present.
+        ticks: Option<PoolTicks>,
+    },
+}
+
+impl PoolSnapshot {
+    /// Reads the pool's tokens and current state. Algebra pools report a live fee per
+    /// direction, which takes precedence over the descriptor's (see `fee`). V3 pools pick
+    /// up their initialized ticks from `ticks::cache` when they have been fetched.
+    pub async fn fetch(rpc_url: &str, pool: &PoolDescriptor) -> Result<Self> {
+        Self::fetch_at(rpc_url, pool, BlockNumber::Latest.into()).await
+    }
+
+    /// Like `fetch`, as of `block`, so several pools can be read at the same point.
+    pub async fn fetch_at(rpc_url: &str, pool: &PoolDescriptor, block: BlockId) -> Result<Self> {
+        let client = rpc::provider(rpc_url)?;
+        let address = pool.address;
+        match pool.kind {
+            AmmKind::V2 => {
+                let pair = UniswapV2Pair::new(address, client);
+                let (reserve0, reserve1, _) = pair.get_reserves().block(block).call().await?;
+                Ok(PoolSnapshot::V2 {
+                    address,
+                    token0: pair.token_0().block(block).call().await?,
+                    token1: pair.token_1().block(block).call().await?,
+                    reserve0: U256::from(reserve0),
+                    reserve1: U256::from(reserve1),
+                    fee: pool.fee,
+                })
+            }
+            AmmKind::V3 => {
+                let immutables = UniswapV3Immutables::new(address, client.clone());
+                let state = pool_adapters::fetch_pool_state_at(client, pool.variant, address, block).await?;
+                let chain_id = chains::fetch_chain_id(rpc_url).await?;
+                let ticks = ticks::cache().get(chain_id, address).filter(|ticks| ticks.covers(state.tick));
+                Ok(PoolSnapshot::V3 {
+                    address,
+                    token0: immutables.token_0().block(block).call().await?,
+                    token1: immutables.token_1().block(block).call().await?,
+                    state,
+                    fee: pool.fee,
+                    ticks,
+                })
+            }
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        match self {
+            PoolSnapshot::V2 { address, .. } | PoolSnapshot::V3 { address, .. } => *address,
+        }
+    }
+
+    pub fn tokens(&self) -> (Address, Address) {
+        match self {
+            PoolSnapshot::V2 { token0, token1, .. } | PoolSnapshot::V3 { token0, token1, .. } => (*token0, *token1),
+        }
+    }
+
+    /// Fee fraction for a swap in the given direction: the live per-direction fee when the
+    /// state carries one (Algebra), the pool's fee tier otherwise.
+    pub fn fee(&self, zero_for_one: bool) -> f64 {
+        match self {
+            PoolSnapshot::V2 { fee, .. } => *fee,
+            PoolSnapshot::V3 { state, fee, .. } => state.fee_for(zero_for_one).map_or(*fee, v3_fee_to_fraction),
+        }
+    }
+
+    /// The token on the other side of `token`, if the pool trades it.
+    pub fn other(&self, token: Address) -> Option<Address> {
+        let (token0, token1) = self.tokens();
+        if token == token0 {
+            Some(token1)
+        } else if token == token1 {
+            Some(token0)
+        } else {
+            None
+        }
+    }
+
+    /// True when selling token0 for token1.
+    pub fn zero_for_one(&self, token_in: Address) -> Result<bool> {
+        let (token0, token1) = self.tokens();
+        if token_in == token0 {
+            Ok(true)
+        } else if token_in == token1 {
+            Ok(false)
+        } else {
+            Err(anyhow!("Pool {:?} doesn't trade {:?}", self.address(), token_in))
+        }
+    }
+
+    pub fn amount_out(&self, token_in: Address, amount_in: U256) -> Result<U256> {
+        let zero_for_one = self.zero_for_one(token_in)?;
+        match self {
+            PoolSnapshot::V2 { reserve0, reserve1, fee, .. } => {
+                let (reserve_in, reserve_out) = if zero_for_one { (*reserve0, *reserve1) } else { (*reserve1, *reserve0) };
+                v2_amount_out(reserve_in, reserve_out, amount_in, *fee)
+            }
+            PoolSnapshot::V3 { state, ticks, .. } => {
+                Ok(v3_amount_out(state, ticks.as_ref(), amount_in, self.fee(zero_for_one), zero_for_one)?.0)
+            }
+        }
+    }
+
+    /// Input of `token_in` needed to receive exactly `amount_out` of the other token.
+    pub fn amount_in(&self, token_in: Address, amount_out: U256) -> Result<U256> {
+        let zero_for_one = self.zero_for_one(token_in)?;
+        match self {
+            PoolSnapshot::V2 { reserve0, reserve1, fee, .. } => {
+                let (reserve_in, reserve_out) = if zero_for_one { (*reserve0, *reserve1) } else { (*reserve1, *reserve0) };
+                v2_amount_in(reserve_in, reserve_out, amount_out, *fee)
+            }
+            PoolSnapshot::V3 { state, ticks, .. } => {
+                Ok(v3_amount_in(state, ticks.as_ref(), amount_out, self.fee(zero_for_one), zero_for_one)?.0)
+            }
+        }
+    }
+
+    /// Marginal rate for an infinitesimal trade, output per input in raw units, net of fee.
+    pub fn spot_price(&self, token_in: Address) -> Result<f64> {
+        let zero_for_one = self.zero_for_one(token_in)?;
+        let gross = match self {
+            PoolSnapshot::V2 { reserve0, reserve1, .. } => {
+                if zero_for_one {
+                    to_f64(*reserve1) / to_f64(*reserve0)
+                } else {
+                    to_f64(*reserve0) / to_f64(*reserve1)
+                }
+            }
+            PoolSnapshot::V3 { state, .. } => {
+                let price = sqrt_price_to_price(state.sqrt_price_x96);
+                if zero_for_one { price } else { 1.0 / price }
+            }
+        };
+        Ok(gross * (1.0 - self.fee(zero_for_one)))
+    }
+
+    /// Gross input of `token_in` that moves the marginal rate (as in `spot_price`) down to
+    /// `rate`; zero if the pool is already at or below it. Closed form for V2 and for V3
+    /// within the current tick.
+    pub fn input_to_rate(&self, token_in: Address, rate: f64) -> Result<f64> {
+        let zero_for_one = self.zero_for_one(token_in)?;
+        if rate <= 0.0 {
+            return Err(anyhow!("Target rate must be positive"));
+        }
+        let gamma = 1.0 - self.fee(zero_for_one);
+        let net = match self {
+            // γ·R_in·R_out / (R_in + γx)² = rate
+            PoolSnapshot::V2 { reserve0, reserve1, .. } => {
+                let (r_in, r_out) = if zero_for_one { (to_f64(*reserve0), to_f64(*reserve1)) } else { (to_f64(*reserve1), to_f64(*reserve0)) };
+                (gamma * r_in * r_out / rate).sqrt() - r_in
+            }
+            // γ·P' = rate selling token0, γ/P' = rate selling token1, with P' = √P'²
+            PoolSnapshot::V3 { state, .. } => {
+                let l = state.liquidity as f64;
+                let sqrt_p = to_f64(state.sqrt_price_x96) / 2f64.powi(96);
+                if zero_for_one {
+                    let target = (rate / gamma).sqrt();
+                    l * (1.0 / target - 1.0 / sqrt_p)
+                } else {
+                    let target = (gamma / rate).sqrt();
+                    l * (target - sqrt_p)
+                }
+            }
+        };
+        // Both pool types charge the fee on the input: `net` is what reaches the curve.
+        Ok((net / gamma).max(0.0))
+    }
+
+    /// Reserve of `token_in` the pool trades against: the real reserve of a V2 pair, the
+    /// virtual in-range reserve (`L/√P` or `L·√P`) of a V3 pool.
+    pub fn depth(&self, token_in: Address) -> Result<U256> {
+        let zero_for_one = self.zero_for_one(token_in)?;
+        match self {
+            PoolSnapshot::V2 { reserve0, reserve1, .. } => Ok(if zero_for_one { *reserve0 } else { *reserve1 }),
+            PoolSnapshot::V3 { state, .. } => {
+                let l = U256::from(state.liquidity);
+                if zero_for_one {
+                    mul_div(l, q96(), state.sqrt_price_x96)
+                } else {
+                    mul_div(l, state.sqrt_price_x96, q96())
+                }
+            }
+        }
+    }
+
+    /// The snapshot after swapping `amount_in` of `token_in`; used to chain several trades
+    /// through the same pool.
+    pub fn apply_swap(&self, token_in: Address, amount_in: U256) -> Result<Self> {
+        let zero_for_one = self.zero_for_one(token_in)?;
+        let fee = self.fee(zero_for_one);
+        let mut next = self.clone();
+        match &mut next {
+            PoolSnapshot::V2 { reserve0, reserve1, .. } => {
+                let (reserve_in, reserve_out) = if zero_for_one { (reserve0, reserve1) } else { (reserve1, reserve0) };
+                let out = v2_amount_out(*reserve_in, *reserve_out, amount_in, fee)?;
+                *reserve_in += amount_in;
+                *reserve_out -= out;
+            }
+            PoolSnapshot::V3 { state, ticks, .. } => {
+                *state = v3_swap(state, ticks.as_ref(), to_i256(amount_in)?, fee, zero_for_one)?.state;
+            }
+        }
+        Ok(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool_adapters::DirectionalFee;
+
+    fn v2(reserve0: u128, reserve1: u128) -> PoolSnapshot {
+        PoolSnapshot::V2 {
+            address: Address::repeat_byte(0x11),
+            token0: Address::repeat_byte(0xa0),
+            token1: Address::repeat_byte(0xb0),
+            reserve0: U256::from(reserve0),
+            reserve1: U256::from(reserve1),
+            fee: 0.003,
+        }
+    }
+
+    #[test]
+    fn v2_matches_router_get_amount_out() {
+        // 1 WETH into 4000 WETH / 10M USDC at 0.3%: 997e15 * 1e13 / (4000e18 + 997e15)
+        let out = v2_amount_out(U256::exp10(21) * 4, U256::exp10(13), U256::exp10(18), 0.003).unwrap();
+        assert_eq!(out, U256::from(2_491_878_899u64));
+    }
+
+    #[test]
+    fn v3_round_trip_without_fee_loses_only_rounding() {
+        // Fixture PCS pool: 2500 USDC per WETH.
+        let state = PoolState {
+            sqrt_price_x96: U256::from_dec_str("3961408125713216879677197").unwrap(),
+            tick: 0,
+            liquidity: 1_000_000_000_000_000_000,
+            fee: None,
+        };
+        let amount = U256::exp10(15);
+        let (out, sqrt_after) = v3_amount_out(&state, None, amount, 0.0, true).unwrap();
+        assert!(sqrt_after < state.sqrt_price_x96);
+
+        let moved = PoolState { sqrt_price_x96: sqrt_after, ..state };
+        let (back, _) = v3_amount_out(&moved, None, out, 0.0, false).unwrap();
+
+        let pool = PoolSnapshot::V3 {
+            address: Address::repeat_byte(0x11),
+            token0: Address::repeat_byte(0xa0),
+            token1: Address::repeat_byte(0xb0),
+            state: PoolState { tick: get_tick_at_sqrt_ratio(state.sqrt_price_x96).unwrap(), ..state },
+            fee: 0.0,
+            ticks: None,
+        };
+        let PoolSnapshot::V3 { state: swapped, .. } = pool.apply_swap(Address::repeat_byte(0xa0), amount).unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(swapped.sqrt_price_x96, sqrt_after);
+        assert_eq!(swapped.tick, get_tick_at_sqrt_ratio(sqrt_after).unwrap());
+        // Flooring the USDC leg costs at most one raw USDC, ~4e8 wei of WETH.
+        assert!(back <= amount && amount - back < amount / 100_000, "back = {back}");
+    }
+
+    #[test]
+    fn exact_out_inverts_exact_in() {
+        let pool = v2(1_000_000_000, 2_000_000_000);
+        let (token0, _) = pool.tokens();
+        let amount_in = pool.amount_in(token0, U256::from(1_000_000u64)).unwrap();
+        assert!(pool.amount_out(token0, amount_in).unwrap() >= U256::from(1_000_000u64));
+        assert!(pool.amount_out(token0, amount_in - 1).unwrap() < U256::from(1_000_000u64));
+
+        let state = PoolState {
+            sqrt_price_x96: U256::from_dec_str("3961408125713216879677197").unwrap(),
+            tick: 0,
+            liquidity: 1_000_000_000_000_000_000,
+            fee: None,
+        };
+        for zero_for_one in [true, false] {
+            let want = if zero_for_one { U256::from(2_500_000u64) } else { U256::exp10(15) };
+            let (amount_in, _) = v3_amount_in(&state, None, want, 0.003, zero_for_one).unwrap();
+            let (got, _) = v3_amount_out(&state, None, amount_in, 0.003, zero_for_one).unwrap();
+            assert!(got >= want, "zero_for_one={zero_for_one}: {got} < {want}");
+        }
+    }
+
+    #[test]
+    fn spot_price_is_net_of_fee_in_both_directions() {
+        let pool = v2(1_000, 2_000);
+        let (token0, token1) = pool.tokens();
+        assert!((pool.spot_price(token0).unwrap() - 2.0 * 0.997).abs() < 1e-12);
+        assert!((pool.spot_price(token1).unwrap() - 0.5 * 0.997).abs() < 1e-12);
+        assert!(pool.spot_price(Address::zero()).is_err());
+    }
+
+    #[test]
+    fn algebra_snapshots_charge_the_fee_of_each_direction() {
+        let pool = PoolSnapshot::V3 {
+            address: Address::repeat_byte(0x11),
+            token0: Address::repeat_byte(0xa0),
+            token1: Address::repeat_byte(0xb0),
+            state: PoolState {
+                sqrt_price_x96: U256::from(2).pow(U256::from(96)),
+                tick: 0,
+                liquidity: 1_000_000_000_000_000_000,
+                fee: Some(DirectionalFee { zero_for_one: 100, one_for_zero: 3_000 }),
+            },
+            fee: 0.0005,
+            ticks: None,
+        };
+        let (token0, token1) = pool.tokens();
+        assert_eq!((pool.fee(true), pool.fee(false)), (0.0001, 0.003));
+        assert!((pool.spot_price(token0).unwrap() - 0.9999).abs() < 1e-12);
+        assert!((pool.spot_price(token1).unwrap() - 0.997).abs() < 1e-12);
+    }
+
+    #[test]
+    fn swaps_cross_initialized_ticks_and_stop_at_the_fetched_window() {
+        use crate::ticks::PopulatedTick;
+
+        let l: u128 = 1_000_000_000_000_000_000;
+        let state = PoolState { sqrt_price_x96: q96(), tick: 0, liquidity: l, fee: None };
+        // Half the liquidity ends at tick -10, the other half at -2560, the edge of word -1.
+        let ticks = PoolTicks::new(
+            10,
+            100,
+            -1..=0,
+            vec![
+                PopulatedTick { tick: -2560, liquidity_net: (l / 2) as i128, liquidity_gross: l / 2 },
+                PopulatedTick { tick: -10, liquidity_net: (l / 2) as i128, liquidity_gross: l / 2 },
+            ],
+        );
+        let amount = I256::exp10(15);
+
+        let flat = v3_swap(&state, None, amount, 0.003, true).unwrap();
+        let crossed = v3_swap(&state, Some(&ticks), amount, 0.003, true).unwrap();
+        assert_eq!(crossed.amount_in, flat.amount_in);
+        assert!(crossed.amount_out < flat.amount_out, "{} >= {}", crossed.amount_out, flat.amount_out);
+        assert_eq!(crossed.state.liquidity, l / 2);
+        assert!(crossed.state.tick < -10 && crossed.state.tick > -2560, "tick {}", crossed.state.tick);
+        assert_eq!(crossed.state.tick, get_tick_at_sqrt_ratio(crossed.state.sqrt_price_x96).unwrap());
+
+        // Within the first range both agree to the wei.
+        let small = I256::exp10(12);
+        assert_eq!(v3_swap(&state, Some(&ticks), small, 0.003, true).unwrap(), v3_swap(&state, None, small, 0.003, true).unwrap());
+
+        assert!(v3_swap(&state, Some(&ticks), I256::exp10(18), 0.003, true).is_err());
+    }
+}
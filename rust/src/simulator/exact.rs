@@ -0,0 +1,96 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use ethers::types::U256;
+use num_bigint::{BigInt, Sign};
+use num_traits::{ToPrimitive, Zero};
+
+/// Significant digits kept when a ratio has no finite decimal expansion (reserve ratios).
+/// Everything else here is exact.
+pub const RATIO_PRECISION: u64 = 60;
+
+pub fn to_bigint(v: U256) -> BigInt {
+    let mut bytes = [0u8; 32];
+    v.to_big_endian(&mut bytes);
+    BigInt::from_bytes_be(Sign::Plus, &bytes)
+}
+
+/// `None` for negative values or values wider than 256 bits.
+pub fn from_bigint(v: &BigInt) -> Option<U256> {
+    let (sign, bytes) = v.to_bytes_be();
+    if sign == Sign::Minus || bytes.len() > 32 {
+        return None;
+    }
+    Some(U256::from_big_endian(&bytes))
+}
+
+/// Raw on-chain amount to whole tokens, e.g. `1500000` with 6 decimals to `1.500000`.
+pub fn to_decimal(raw: U256, decimals: u32) -> BigDecimal {
+    BigDecimal::new(to_bigint(raw), decimals as i64)
+}
+
+/// Whole tokens to a raw amount, rounding down to the token's last decimal.
+pub fn to_raw(amount: &BigDecimal, decimals: u32) -> Option<U256> {
+    let scaled = amount.with_scale(decimals as i64);
+    let (digits, _) = scaled.as_bigint_and_exponent();
+    from_bigint(&digits)
+}
+
+/// token1 per token0 in whole tokens: `sqrtPriceX96² / 2^192 · 10^(decimals0 - decimals1)`.
+/// `2^-192 = 5^192 / 10^192`, so the result is an exact terminating decimal.
+pub fn sqrt_price_to_price(sqrt_price_x96: U256, decimals0: u32, decimals1: u32) -> BigDecimal {
+    let sqrt = to_bigint(sqrt_price_x96);
+    let digits = &sqrt * &sqrt * BigInt::from(5u8).pow(192);
+    BigDecimal::new(digits, 192 - (decimals0 as i64 - decimals1 as i64)).normalized()
+}
+
+/// `numerator / denominator` to `RATIO_PRECISION` digits.
+pub fn ratio(numerator: &BigDecimal, denominator: &BigDecimal) -> Result<BigDecimal> {
+    if denominator.is_zero() {
+        return Err(anyhow!("Division of {} by zero", numerator));
+    }
+    Ok((numerator / denominator).with_prec(RATIO_PRECISION).normalized())
+}
+
+/// `amount_out - amount_in` in whole tokens of a token both sides are denominated in.
+/// Negative for a loss.
+pub fn profit(amount_in: U256, amount_out: U256, decimals: u32) -> BigDecimal {
+    to_decimal(amount_out, decimals) - to_decimal(amount_in, decimals)
+}
+
+/// Fast path for the optimizer and for logging; lossy.
+pub fn to_f64(v: &BigDecimal) -> f64 {
+    v.to_f64().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn sqrt_price_converts_without_rounding() {
+        // sqrtPriceX96 = 2^96 · 50: raw price 2500, shifted by 18 - 6 decimals.
+        let sqrt = U256::from(50u64) << 96;
+        assert_eq!(sqrt_price_to_price(sqrt, 18, 6), BigDecimal::from(2_500_000_000_000_000u64));
+        assert_eq!(sqrt_price_to_price(sqrt, 6, 6), BigDecimal::from(2500));
+
+        // One unit above moves the price by ~1e-27, which only the exact path can see.
+        let above = sqrt_price_to_price(sqrt + 1, 6, 6);
+        assert!(above > BigDecimal::from(2500));
+        assert!((to_f64(&above) - 2500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn amounts_round_trip_and_profit_is_signed() {
+        let raw = U256::from(1_500_000u64);
+        let decimal = to_decimal(raw, 6);
+        assert_eq!(decimal, BigDecimal::from_str("1.5").unwrap());
+        assert_eq!(to_raw(&decimal, 6), Some(raw));
+        assert_eq!(to_raw(&BigDecimal::from_str("1.2345678").unwrap(), 6), Some(U256::from(1_234_567u64)));
+        assert_eq!(from_bigint(&to_bigint(U256::MAX)), Some(U256::MAX));
+
+        assert_eq!(profit(raw, U256::from(1_000_000u64), 6), BigDecimal::from_str("-0.5").unwrap());
+        assert_eq!(ratio(&BigDecimal::from(1), &BigDecimal::from(3)).unwrap().digits(), RATIO_PRECISION);
+        assert!(ratio(&BigDecimal::from(1), &BigDecimal::zero()).is_err());
+    }
+}
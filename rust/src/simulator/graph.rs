@@ -0,0 +1,549 @@
+use crate::config::RouteSettings;
+use crate::discovery::BlockChunks;
+use crate::pools::PoolDescriptor;
+use crate::rpc;
+use crate::simulator::amm::{to_f64, PoolSnapshot};
+use crate::simulator::route::{Hop, Route, RouteResult};
+use crate::ArbitrageDirection;
+use anyhow::Result;
+use ethers::providers::Middleware;
+use ethers::types::{Address, Filter, U256};
+use futures::future::try_join_all;
+use log::{debug, error, info, warn};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// A directed swap `from → to` through one pool, weighted `-ln(price·(1 - fee))`.
+/// A cycle whose weights sum below zero returns more than it started with.
+#[derive(Debug, Clone)]
+struct Edge {
+    from: usize,
+    to: usize,
+    pool: Address,
+    weight: f64,
+}
+
+/// Tokens as nodes, one edge per pool and direction. Pools are added or refreshed one at a
+/// time, so only the touched edges are re-weighted when a pool's state changes.
+#[derive(Debug, Default)]
+pub struct TokenGraph {
+    tokens: Vec<Address>,
+    index: HashMap<Address, usize>,
+    edges: Vec<Edge>,
+    edges_by_pool: HashMap<Address, [usize; 2]>,
+    pools: HashMap<Address, (PoolDescriptor, PoolSnapshot)>,
+    /// Block every snapshot was read at; `None` for graphs built from given snapshots.
+    block: Option<u64>,
+    /// Blocks per `eth_getLogs` request in `refresh`, as in discovery's scan.
+    block_chunk: u64,
+}
+
+fn weight(snapshot: &PoolSnapshot, token_in: Address) -> f64 {
+    match snapshot.spot_price(token_in) {
+        Ok(price) if price > 0.0 && price.is_finite() => -price.ln(),
+        _ => f64::INFINITY,
+    }
+}
+
+impl TokenGraph {
+    pub fn new(block_chunk: u64) -> Self {
+        TokenGraph { block_chunk: block_chunk.max(1), ..Self::default() }
+    }
+
+    #[cfg(test)]
+    pub fn from_pools(pools: impl IntoIterator<Item = (PoolDescriptor, PoolSnapshot)>) -> Self {
+        let mut graph = Self::default();
+        for (descriptor, snapshot) in pools {
+            graph.upsert(descriptor, snapshot);
+        }
+        graph
+    }
+
+    fn node(&mut self, token: Address) -> usize {
+        if let Some(&i) = self.index.get(&token) {
+            return i;
+        }
+        self.tokens.push(token);
+        self.index.insert(token, self.tokens.len() - 1);
+        self.tokens.len() - 1
+    }
+
+    /// Adds a pool or replaces its state, re-weighting only its two edges.
+    pub fn upsert(&mut self, descriptor: PoolDescriptor, snapshot: PoolSnapshot) {
+        let (token0, token1) = snapshot.tokens();
+        let pool = snapshot.address();
+        let forward = weight(&snapshot, token0);
+        let backward = weight(&snapshot, token1);
+
+        match self.edges_by_pool.get(&pool) {
+            Some(&[e0, e1]) => {
+                self.edges[e0].weight = forward;
+                self.edges[e1].weight = backward;
+            }
+            None => {
+                let (n0, n1) = (self.node(token0), self.node(token1));
+                self.edges.push(Edge { from: n0, to: n1, pool, weight: forward });
+                self.edges.push(Edge { from: n1, to: n0, pool, weight: backward });
+                self.edges_by_pool.insert(pool, [self.edges.len() - 2, self.edges.len() - 1]);
+            }
+        }
+        self.pools.insert(pool, (descriptor, snapshot));
+    }
+
+    /// Brings the graph to the head block (see `refresh`), then reads the pools not yet in
+    /// it at that block and adds them; returns how many were added.
+    pub async fn add(&mut self, rpc_url: &str, descriptors: &[PoolDescriptor]) -> Result<usize> {
+        let head = rpc::provider(rpc_url)?.get_block_number().await?.as_u64();
+        self.refresh_to(rpc_url, head).await?;
+        let new: Vec<&PoolDescriptor> = descriptors.iter().filter(|d| !self.pools.contains_key(&d.address)).collect();
+        let snapshots = try_join_all(new.iter().map(|d| PoolSnapshot::fetch_at(rpc_url, d, head.into()))).await?;
+        for (descriptor, snapshot) in new.iter().zip(snapshots) {
+            self.upsert((*descriptor).clone(), snapshot);
+        }
+        if !self.pools.is_empty() {
+            self.block = Some(head);
+        }
+        Ok(new.len())
+    }
+
+    /// Re-reads, at the head block, only the pools that emitted a log since the graph's
+    /// block (a V2 `Sync`, a V3 `Swap`/`Mint`/`Burn`, ...), concurrently, and re-weights
+    /// the ones whose state moved. Returns how many did.
+    pub async fn refresh(&mut self, rpc_url: &str) -> Result<usize> {
+        let head = rpc::provider(rpc_url)?.get_block_number().await?.as_u64();
+        self.refresh_to(rpc_url, head).await
+    }
+
+    /// `refresh` up to `head`. The logs are read in `BlockChunks`; the graph is left as it
+    /// was if a range can't be read even one block at a time.
+    async fn refresh_to(&mut self, rpc_url: &str, head: u64) -> Result<usize> {
+        let client = rpc::provider(rpc_url)?;
+        let touched: Vec<PoolDescriptor> = match self.block {
+            Some(block) if block >= head => return Ok(0),
+            Some(block) => {
+                let mut watched: Vec<Address> = self.pools.keys().copied().collect();
+                watched.sort();
+                let mut emitted: HashSet<Address> = HashSet::new();
+                let mut chunks = BlockChunks::new(block + 1, head, self.block_chunk);
+                while let Some((from, to)) = chunks.range() {
+                    let filter = Filter::new().address(watched.clone()).from_block(from).to_block(to);
+                    match client.get_logs(&filter).await {
+                        Ok(logs) => {
+                            emitted.extend(logs.iter().map(|log| log.address));
+                            chunks.advance();
+                        }
+                        Err(e) => {
+                            let message = e.to_string();
+                            let chunk = chunks.shrink(e.into())?;
+                            warn!("Graph {}: {message}; retrying with {} blocks", rpc_url, chunk);
+                        }
+                    }
+                }
+                emitted.iter().filter_map(|pool| self.pools.get(pool)).map(|(d, _)| d.clone()).collect()
+            }
+            None => self.pools.values().map(|(d, _)| d.clone()).collect(),
+        };
+
+        let snapshots = try_join_all(touched.iter().map(|d| PoolSnapshot::fetch_at(rpc_url, d, head.into()))).await?;
+        let mut changed = 0;
+        for (descriptor, snapshot) in touched.into_iter().zip(snapshots) {
+            if self.pools[&descriptor.address].1 != snapshot {
+                self.upsert(descriptor, snapshot);
+                changed += 1;
+            }
+        }
+        if !self.pools.is_empty() {
+            self.block = Some(head);
+        }
+        Ok(changed)
+    }
+
+    pub fn snapshots(&self) -> HashMap<Address, PoolSnapshot> {
+        self.pools.iter().map(|(a, (_, s))| (*a, s.clone())).collect()
+    }
+
+    pub fn token_count(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn pool_count(&self) -> usize {
+        self.pools.len()
+    }
+
+    /// Bellman-Ford from a virtual source connected to every token. Each edge still
+    /// relaxable after `n - 1` rounds lies on or leads to a negative cycle, which is
+    /// recovered by walking predecessors. Cycles are deduplicated by their pool set.
+    pub fn negative_cycles(&self) -> Vec<Route> {
+        let n = self.tokens.len();
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let mut dist = vec![0.0f64; n];
+        let mut pred: Vec<Option<usize>> = vec![None; n];
+
+        for _ in 0..n - 1 {
+            let mut changed = false;
+            for (i, edge) in self.edges.iter().enumerate() {
+                let candidate = dist[edge.from] + edge.weight;
+                if candidate < dist[edge.to] - 1e-12 {
+                    dist[edge.to] = candidate;
+                    pred[edge.to] = Some(i);
+                    changed = true;
+                }
+            }
+            if !changed {
+                return Vec::new();
+            }
+        }
+
+        let mut seen: HashSet<Vec<Address>> = HashSet::new();
+        let mut cycles = Vec::new();
+
+        for edge in &self.edges {
+            if dist[edge.from] + edge.weight >= dist[edge.to] - 1e-12 {
+                continue;
+            }
+
+            // Step back n times to be sure we stand on the cycle, not on a path into it.
+            let mut node = edge.to;
+            for _ in 0..n {
+                match pred[node] {
+                    Some(e) => node = self.edges[e].from,
+                    None => break,
+                }
+            }
+
+            let start = node;
+            let mut cycle_edges = Vec::new();
+            while let Some(e) = pred[node] {
+                cycle_edges.push(e);
+                node = self.edges[e].from;
+                if node == start || cycle_edges.len() > n {
+                    break;
+                }
+            }
+            if node != start {
+                continue;
+            }
+            cycle_edges.reverse();
+
+            let mut key: Vec<Address> = cycle_edges.iter().map(|&e| self.edges[e].pool).collect();
+            key.sort();
+            if !seen.insert(key) {
+                continue;
+            }
+
+            let hops = cycle_edges
+                .iter()
+                .map(|&e| {
+                    let edge = &self.edges[e];
+                    Hop {
+                        pool: self.pools[&edge.pool].0.clone(),
+                        token_in: self.tokens[edge.from],
+                        token_out: self.tokens[edge.to],
+                    }
+                })
+                .collect();
+            match Route::new(hops) {
+                Ok(route) => {
+                    debug!("Negative cycle {:?}", route.tokens());
+                    cycles.push(route);
+                }
+                Err(e) => warn!("Discarding malformed cycle: {e:#}"),
+            }
+        }
+
+        cycles
+    }
+
+    /// Sizes every negative cycle with the exact simulators and keeps those that are
+    /// still profitable. The search runs over `(0, depth]` of the first hop's input side.
+    pub fn opportunities(&self) -> Vec<(Route, RouteResult)> {
+        let snapshots = self.snapshots();
+        let mut found = Vec::new();
+
+        for route in self.negative_cycles() {
+            let first = &snapshots[&route.hops[0].pool.address];
+            let upper = match first.depth(route.start_token()) {
+                Ok(depth) => depth,
+                Err(e) => {
+                    warn!("Skipping cycle {:?}: {e:#}", route.tokens());
+                    continue;
+                }
+            };
+            match best_amount(&route, &snapshots, upper) {
+                Ok(Some(result)) => {
+                    info!(
+                        "Cycle {:?}: in={} out={} via {} hops",
+                        route.tokens(),
+                        result.amount_in(),
+                        result.amount_out(),
+                        route.hops.len()
+                    );
+                    found.push((route, result));
+                }
+                Ok(None) => debug!("Cycle {:?} unprofitable after price impact", route.tokens()),
+                Err(e) => warn!("Sizing failed for cycle {:?}: {e:#}", route.tokens()),
+            }
+        }
+
+        found
+    }
+}
+
+fn profit_at(route: &Route, snapshots: &HashMap<Address, PoolSnapshot>, amount: f64) -> Option<(f64, RouteResult)> {
+    let amount_in = U256::from(amount.max(0.0) as u128);
+    let result = route.simulate(snapshots, amount_in).ok()?;
+    Some((to_f64(result.amount_out()) - to_f64(amount_in), result))
+}
+
+/// Golden-section search for the input maximizing `out - in` on `[0, upper]`. Profit along
+/// an AMM cycle is concave in the input, so the search converges on the single optimum.
+/// Returns `None` if no size is profitable.
+pub fn best_amount(route: &Route, snapshots: &HashMap<Address, PoolSnapshot>, upper: U256) -> Result<Option<RouteResult>> {
+    const INV_PHI: f64 = 0.618_033_988_749_895;
+
+    let (mut lo, mut hi) = (0.0, to_f64(upper));
+    let profit = |x: f64| profit_at(route, snapshots, x).map_or(f64::NEG_INFINITY, |(p, _)| p);
+
+    for _ in 0..200 {
+        if hi - lo < 1.0 {
+            break;
+        }
+        let a = hi - (hi - lo) * INV_PHI;
+        let b = lo + (hi - lo) * INV_PHI;
+        if profit(a) < profit(b) {
+            lo = a;
+        } else {
+            hi = b;
+        }
+    }
+
+    match profit_at(route, snapshots, (lo + hi) / 2.0) {
+        Some((p, result)) if p > 0.0 => Ok(Some(result)),
+        _ => Ok(None),
+    }
+}
+
+/// Pools handed to each provider's graph follower, keyed by provider URL.
+static WATCHED: OnceLock<Mutex<HashMap<String, mpsc::UnboundedSender<Vec<PoolDescriptor>>>>> = OnceLock::new();
+
+/// Adds the described pools of `directions` to a token graph per provider, logging the
+/// sized negative cycles it finds. The first call for a provider starts a follower that
+/// refreshes its graph every `poll_every`; later calls only hand it the new pools.
+pub fn watch(directions: &[ArbitrageDirection], settings: &RouteSettings) {
+    let mut by_provider: HashMap<&str, Vec<PoolDescriptor>> = HashMap::new();
+    for dir in directions {
+        by_provider.entry(&dir.provider).or_default().extend(dir.pools.iter().cloned());
+    }
+
+    let mut watched = WATCHED.get_or_init(Default::default).lock().unwrap();
+    for (provider, pools) in by_provider {
+        let sender = watched.entry(provider.to_string()).or_insert_with(|| {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            tokio::spawn(follow(provider.to_string(), receiver, settings.poll_interval(), settings.block_chunk));
+            sender
+        });
+        if sender.send(pools).is_err() {
+            error!("Graph follower for {} has stopped", provider);
+        }
+    }
+}
+
+async fn follow(rpc_url: String, mut pools: mpsc::UnboundedReceiver<Vec<PoolDescriptor>>, period: Duration, block_chunk: u64) {
+    let mut graph = TokenGraph::new(block_chunk);
+    let mut interval = tokio::time::interval(period);
+    interval.tick().await;
+    loop {
+        let changed = tokio::select! {
+            added = pools.recv() => {
+                let Some(added) = added else { return };
+                graph.add(&rpc_url, &added).await
+            }
+            _ = interval.tick() => graph.refresh(&rpc_url).await,
+        };
+        match changed {
+            Ok(0) => {}
+            Ok(changed) => {
+                info!(
+                    "Graph {}: {} pools changed, {} tokens, {} pools, {} opportunities",
+                    rpc_url,
+                    changed,
+                    graph.token_count(),
+                    graph.pool_count(),
+                    graph.opportunities().len()
+                );
+            }
+            Err(e) => error!("Graph {}: update failed: {e:#}", rpc_url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::{AmmKind, PoolVariant};
+    use crate::pools_abi::uniswap_v2_pair::{GetReservesCall, GetReservesReturn, Token0Call, Token1Call};
+    use crate::rpc::fixture::{eth_call_entry, FixtureEntry};
+    use crate::rpc::{FixtureClient, RpcClient};
+    use serde_json::json;
+
+    fn pool(n: u8, token0: Address, token1: Address, reserve0: u128, reserve1: u128) -> (PoolDescriptor, PoolSnapshot) {
+        let address = Address::repeat_byte(n);
+        (
+            PoolDescriptor { address, kind: AmmKind::V2, variant: PoolVariant::UniswapV2, dex: None, fee: 0.003 },
+            PoolSnapshot::V2 {
+                address,
+                token0,
+                token1,
+                reserve0: U256::from(reserve0),
+                reserve1: U256::from(reserve1),
+                fee: 0.003,
+            },
+        )
+    }
+
+    fn tokens() -> (Address, Address, Address) {
+        (Address::repeat_byte(0xa), Address::repeat_byte(0xb), Address::repeat_byte(0xc))
+    }
+
+    #[test]
+    fn consistent_prices_have_no_negative_cycle() {
+        let (a, b, c) = tokens();
+        let graph = TokenGraph::from_pools([
+            pool(1, a, b, 1_000_000_000, 2_000_000_000),
+            pool(2, b, c, 1_000_000_000, 3_000_000_000),
+            pool(3, a, c, 1_000_000_000, 6_000_000_000),
+        ]);
+        assert_eq!(graph.token_count(), 3);
+        assert!(graph.negative_cycles().is_empty());
+    }
+
+    #[test]
+    fn mispriced_pool_yields_one_sized_cycle_until_it_is_fixed() {
+        let (a, b, c) = tokens();
+        let mut graph = TokenGraph::from_pools([
+            pool(1, a, b, 1_000_000_000, 2_000_000_000),
+            pool(2, b, c, 1_000_000_000, 3_000_000_000),
+            pool(3, a, c, 1_000_000_000, 5_000_000_000),
+        ]);
+
+        let cycles = graph.negative_cycles();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].hops.len(), 3);
+
+        let opportunities = graph.opportunities();
+        assert_eq!(opportunities.len(), 1);
+        let result = &opportunities[0].1;
+        assert!(result.amount_out() > result.amount_in());
+
+        let (descriptor, snapshot) = pool(3, a, c, 1_000_000_000, 6_000_000_000);
+        graph.upsert(descriptor, snapshot);
+        assert_eq!(graph.pool_count(), 3);
+        assert!(graph.negative_cycles().is_empty());
+    }
+
+    fn pair_reads(pair: Address, block: &str, token0: Address, token1: Address, reserve1: u128) -> Vec<FixtureEntry> {
+        let reserves = GetReservesReturn { reserve_0: 1_000_000_000, reserve_1: reserve1, block_timestamp_last: 0 };
+        vec![
+            eth_call_entry(pair, block, GetReservesCall, reserves),
+            eth_call_entry(pair, block, Token0Call, token0),
+            eth_call_entry(pair, block, Token1Call, token1),
+        ]
+    }
+
+    fn head(block: &str) -> FixtureEntry {
+        FixtureEntry::new("eth_blockNumber", json!(null), json!(block))
+    }
+
+    #[tokio::test]
+    async fn refresh_rereads_only_pools_that_emitted_logs() {
+        const URL: &str = "fixture://graph-refresh";
+        let (a, b, c) = tokens();
+        let (p1, p2, p3) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let descriptors: Vec<PoolDescriptor> =
+            [(1, a, b), (2, b, c), (3, a, c)].iter().map(|(n, t0, t1)| pool(*n, *t0, *t1, 0, 0).0).collect();
+
+        let mut initial = vec![head("0x10")];
+        initial.extend(pair_reads(p1, "0x10", a, b, 2_000_000_000));
+        initial.extend(pair_reads(p2, "0x10", b, c, 3_000_000_000));
+        initial.extend(pair_reads(p3, "0x10", a, c, 6_000_000_000));
+        let mut graph = TokenGraph::new(2_000);
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(initial));
+        assert_eq!(rpc::scoped(URL, client, graph.add(URL, &descriptors)).await.unwrap(), 3);
+        assert!(graph.negative_cycles().is_empty());
+
+        // Only pool 3 logged anything; pools 1 and 2 have no reads recorded at 0x12.
+        let filter = Filter::new().address(vec![p1, p2, p3]).from_block(0x11).to_block(0x12);
+        let log = ethers::types::Log { address: p3, ..Default::default() };
+        let mut moved = vec![
+            head("0x12"),
+            FixtureEntry::new("eth_getLogs", json!([filter]), json!([log])),
+        ];
+        moved.extend(pair_reads(p3, "0x12", a, c, 5_000_000_000));
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(moved));
+        assert_eq!(rpc::scoped(URL, client, graph.refresh(URL)).await.unwrap(), 1);
+        assert_eq!(graph.negative_cycles().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn refresh_halves_log_ranges_the_node_rejects() {
+        const URL: &str = "fixture://graph-chunks";
+        let (a, b, _) = tokens();
+        let p1 = Address::repeat_byte(1);
+        let mut graph = TokenGraph::new(2);
+
+        let mut initial = vec![head("0x10")];
+        initial.extend(pair_reads(p1, "0x10", a, b, 2_000_000_000));
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(initial));
+        rpc::scoped(URL, client, graph.add(URL, &[pool(1, a, b, 0, 0).0])).await.unwrap();
+
+        // 0x11..=0x12 has no recorded answer, so it is split; 0x13..=0x14 reads whole.
+        let logs = |from: u64, to: u64, emitted: Vec<ethers::types::Log>| {
+            let filter = Filter::new().address(vec![p1]).from_block(from).to_block(to);
+            FixtureEntry::new("eth_getLogs", json!([filter]), json!(emitted))
+        };
+        let log = ethers::types::Log { address: p1, ..Default::default() };
+        let mut moved = vec![head("0x14"), logs(0x11, 0x11, vec![]), logs(0x12, 0x12, vec![log]), logs(0x13, 0x14, vec![])];
+        moved.extend(pair_reads(p1, "0x14", a, b, 2_100_000_000));
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(moved));
+        assert_eq!(rpc::scoped(URL, client, graph.refresh(URL)).await.unwrap(), 1);
+        assert_eq!(graph.block, Some(0x14));
+
+        // Nothing answers 0x15 even on its own: the graph stays where it was.
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(vec![head("0x15")]));
+        assert!(rpc::scoped(URL, client, graph.refresh(URL)).await.is_err());
+        assert_eq!(graph.block, Some(0x14));
+    }
+
+    #[tokio::test]
+    async fn pools_added_later_are_read_at_the_head_with_the_rest() {
+        const URL: &str = "fixture://graph-add";
+        let (a, b, c) = tokens();
+        let (p1, p2) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let mut graph = TokenGraph::new(2_000);
+
+        let mut initial = vec![head("0x10")];
+        initial.extend(pair_reads(p1, "0x10", a, b, 2_000_000_000));
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(initial));
+        rpc::scoped(URL, client, graph.add(URL, &[pool(1, a, b, 0, 0).0])).await.unwrap();
+
+        // Pool 1 moved meanwhile; both end up read at 0x12.
+        let filter = Filter::new().address(vec![p1]).from_block(0x11).to_block(0x12);
+        let log = ethers::types::Log { address: p1, ..Default::default() };
+        let mut later = vec![head("0x12"), FixtureEntry::new("eth_getLogs", json!([filter]), json!([log]))];
+        later.extend(pair_reads(p1, "0x12", a, b, 2_100_000_000));
+        later.extend(pair_reads(p2, "0x12", b, c, 3_000_000_000));
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(later));
+        let descriptors = [pool(1, a, b, 0, 0).0, pool(2, b, c, 0, 0).0];
+        assert_eq!(rpc::scoped(URL, client, graph.add(URL, &descriptors)).await.unwrap(), 1);
+
+        assert_eq!(graph.block, Some(0x12));
+        assert_eq!(graph.pool_count(), 2);
+        let PoolSnapshot::V2 { reserve1, .. } = &graph.pools[&p1].1 else { unreachable!() };
+        assert_eq!(*reserve1, U256::from(2_100_000_000u64));
+    }
+}
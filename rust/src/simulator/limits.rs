@@ -0,0 +1,195 @@
+use crate::dexes::AmmKind;
+use crate::pools::PoolDescriptor;
+use crate::pools_abi::uniswap_v3_immutables::UniswapV3Immutables;
+use crate::rpc;
+use crate::simulator::amm::{from_f64, mul_div, q96, to_f64, PoolSnapshot};
+use crate::simulator::v3_math::tick_math::get_sqrt_ratio_at_tick;
+use anyhow::{anyhow, Result};
+use ethers::types::{Address, BlockId, U256};
+
+/// Tick spacing of a concentrated-liquidity pool, read from its `tickSpacing()` at `block`;
+/// `None` for V2 pairs. Liquidity can only change on multiples of it, so the edge of the
+/// current spacing range is a safe bound for in-range liquidity.
+pub async fn fetch_tick_spacing(rpc_url: &str, pool: &PoolDescriptor, block: BlockId) -> Result<Option<i32>> {
+    if pool.kind == AmmKind::V2 {
+        return Ok(None);
+    }
+    let contract = UniswapV3Immutables::new(pool.address, rpc::provider(rpc_url)?);
+    let spacing = contract.tick_spacing().block(block).call().await?;
+    if spacing <= 0 {
+        return Err(anyhow!("Invalid tick spacing {} for pool {:?}", spacing, pool.address));
+    }
+    Ok(Some(spacing))
+}
+
+/// V2 inverse of `calculate_price_impact`, in raw units of the input token. Prices come
+/// from the reserves after the whole input, fee included, has been added:
+/// `(R_in + γx)(R_in + x) = R_in² / (1 - X)`, solved for `x`.
+pub fn v2_max_input_for_impact(reserve_in: f64, fee: f64, max_impact: f64) -> f64 {
+    let gamma = 1.0 - fee;
+    let b = reserve_in * (1.0 + gamma);
+    let c = reserve_in * reserve_in * (1.0 - 1.0 / (1.0 - max_impact));
+    ((-b + (b * b - 4.0 * gamma * c).sqrt()) / (2.0 * gamma)).max(0.0)
+}
+
+/// V3 inverse of `calculate_price_impact` within the current tick. The out-per-in price
+/// falls by `X` when `√P` ends at `√P·√(1 - X)` selling token0, or `√P / √(1 - X)` selling token1.
+pub fn v3_max_input_for_impact(sqrt_price_x96: U256, liquidity: u128, fee: f64, max_impact: f64, zero_for_one: bool) -> f64 {
+    let l = liquidity as f64;
+    let sqrt_p = to_f64(sqrt_price_x96) / 2f64.powi(96);
+    let net = if zero_for_one {
+        l * (1.0 / (sqrt_p * (1.0 - max_impact).sqrt()) - 1.0 / sqrt_p)
+    } else {
+        l * (sqrt_p / (1.0 - max_impact).sqrt() - sqrt_p)
+    };
+    (net / (1.0 - fee)).max(0.0)
+}
+
+/// Largest input of `token_in` whose price impact, as `calculate_price_impact` measures it
+/// (relative move of the pool's out-per-in price from before to after the swap), stays
+/// within `max_impact`.
+pub fn max_input_for_impact(pool: &PoolSnapshot, token_in: Address, max_impact: f64) -> Result<U256> {
+    if !(0.0..1.0).contains(&max_impact) {
+        return Err(anyhow!("Price impact bound must be in [0, 1), got {}", max_impact));
+    }
+    let zero_for_one = pool.zero_for_one(token_in)?;
+
+    let amount = match pool {
+        PoolSnapshot::V2 { .. } => v2_max_input_for_impact(to_f64(pool.depth(token_in)?), pool.fee(zero_for_one), max_impact),
+        PoolSnapshot::V3 { state, .. } => {
+            v3_max_input_for_impact(state.sqrt_price_x96, state.liquidity, pool.fee(zero_for_one), max_impact, zero_for_one)
+        }
+    };
+
+    Ok(from_f64(amount))
+}
+
+/// Largest input before a V3 pool leaves the tick-spacing range it is in, i.e. before
+/// its in-range liquidity may run out. `None` for V2 pairs, which never do.
+pub fn max_input_in_range(pool: &PoolSnapshot, token_in: Address, spacing: i32) -> Result<Option<U256>> {
+    let PoolSnapshot::V3 { state, .. } = pool else {
+        return Ok(None);
+    };
+    if spacing <= 0 {
+        return Err(anyhow!("Invalid tick spacing {}", spacing));
+    }
+    let zero_for_one = pool.zero_for_one(token_in)?;
+
+    let lower_tick = state.tick.div_euclid(spacing) * spacing;
+    let l = U256::from(state.liquidity);
+    let sqrt_p = state.sqrt_price_x96;
+
+    let net = if zero_for_one {
+        // Price falls towards the lower edge: dx = L·Q96·(√P - √Pl) / (√P·√Pl)
+        let edge = get_sqrt_ratio_at_tick(lower_tick)?;
+        if edge >= sqrt_p {
+            return Ok(Some(U256::zero()));
+        }
+        mul_div(l << 96, sqrt_p - edge, sqrt_p)? / edge
+    } else {
+        // Price rises towards the upper edge: dy = L·(√Pu - √P) / Q96
+        let edge = get_sqrt_ratio_at_tick(lower_tick + spacing)?;
+        if edge <= sqrt_p {
+            return Ok(Some(U256::zero()));
+        }
+        mul_div(l, edge - sqrt_p, q96())?
+    };
+
+    Ok(Some(from_f64(to_f64(net) / (1.0 - pool.fee(zero_for_one)))))
+}
+
+/// The tighter of the impact bound and, for V3 pools with a known spacing, the
+/// in-range bound. This is the size loan sizing and risk limits should cap at.
+pub fn max_input(pool: &PoolSnapshot, token_in: Address, max_impact: f64, spacing: Option<i32>) -> Result<U256> {
+    let by_impact = max_input_for_impact(pool, token_in, max_impact)?;
+    let by_range = match spacing {
+        Some(spacing) => max_input_in_range(pool, token_in, spacing)?,
+        None => None,
+    };
+    Ok(by_range.map_or(by_impact, |r| r.min(by_impact)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool_adapters::PoolState;
+    use crate::threads::SimulateTradeLoopV2;
+
+    fn price_after(pool: &PoolSnapshot, token_in: Address, amount: U256) -> f64 {
+        let after = pool.apply_swap(token_in, amount).unwrap();
+        // gross out-per-in price, undoing the fee `spot_price` nets out
+        after.spot_price(token_in).unwrap() / (1.0 - pool.fee(pool.zero_for_one(token_in).unwrap()))
+    }
+
+    #[test]
+    fn v2_bound_lands_on_the_requested_impact() {
+        let a = Address::repeat_byte(0xa);
+        let pool = PoolSnapshot::V2 {
+            address: Address::repeat_byte(1),
+            token0: a,
+            token1: Address::repeat_byte(0xb),
+            reserve0: U256::exp10(21) * 4,
+            reserve1: U256::exp10(13),
+            fee: 0.003,
+        };
+        let before = pool.spot_price(a).unwrap() / 0.997;
+
+        let x = max_input_for_impact(&pool, a, 0.01).unwrap();
+        let impact = SimulateTradeLoopV2::calculate_price_impact(before, price_after(&pool, a, x));
+        assert!((impact - 0.01).abs() < 1e-6, "impact = {impact}");
+        assert_eq!(max_input_in_range(&pool, a, 60).unwrap(), None);
+    }
+
+    #[test]
+    fn v3_bound_respects_both_impact_and_tick_range() {
+        let a = Address::repeat_byte(0xa);
+        // 1.0001^tick = 2.5e-9 at tick -198080; 3961408125713216879677197 is √2.5e-9 · 2^96
+        let pool = PoolSnapshot::V3 {
+            address: Address::repeat_byte(1),
+            token0: a,
+            token1: Address::repeat_byte(0xb),
+            state: PoolState {
+                sqrt_price_x96: U256::from_dec_str("3961408125713216879677197").unwrap(),
+                tick: -198080,
+                liquidity: 1_000_000_000_000_000_000,
+                fee: None,
+            },
+            fee: 0.0005,
+            ticks: None,
+        };
+        let before = pool.spot_price(a).unwrap() / 0.9995;
+
+        let x = max_input_for_impact(&pool, a, 0.02).unwrap();
+        let impact = SimulateTradeLoopV2::calculate_price_impact(before, price_after(&pool, a, x));
+        assert!((impact - 0.02).abs() < 1e-6, "impact = {impact}");
+
+        // A 60-tick range is ~0.6% wide, so it binds before a 2% impact does.
+        let in_range = max_input_in_range(&pool, a, 60).unwrap().unwrap();
+        assert!(in_range < x);
+        assert_eq!(max_input(&pool, a, 0.02, Some(60)).unwrap(), in_range);
+    }
+
+    #[tokio::test]
+    async fn tick_spacing_comes_from_the_pool() {
+        use crate::dexes::PoolVariant;
+        use crate::pools_abi::uniswap_v3_immutables::TickSpacingCall;
+        use crate::rpc::fixture::eth_call_entry;
+        use crate::rpc::{FixtureClient, RpcClient};
+
+        const URL: &str = "fixture://tick-spacing";
+        let pool = PoolDescriptor {
+            address: Address::repeat_byte(1),
+            kind: AmmKind::V3,
+            variant: PoolVariant::PancakeV3,
+            dex: None,
+            fee: 0.0025,
+        };
+        let entries = vec![eth_call_entry(pool.address, "0x10", TickSpacingCall, 50i32)];
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        let spacing = rpc::scoped(URL, client, fetch_tick_spacing(URL, &pool, 16u64.into())).await.unwrap();
+        assert_eq!(spacing, Some(50));
+
+        let pair = PoolDescriptor { kind: AmmKind::V2, variant: PoolVariant::UniswapV2, ..pool };
+        assert_eq!(fetch_tick_spacing(URL, &pair, 16u64.into()).await.unwrap(), None);
+    }
+}
@@ -1,19 +1,218 @@
-pub mod v2;
-pub mod v3;
+pub mod amm;
+pub mod exact;
+pub mod graph;
+pub mod limits;
+pub mod pool_simulator;
+pub mod quote;
+pub mod route;
+pub mod sizing;
+pub mod v3_math;
+pub mod verify;
 
+use crate::units::{Price, Token0, Token1, TokenAmount, TokenRef};
 use crate::ArbitrageDirection;
-use v2::simulate_v2;
-use v3::simulate_v3;
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use pool_simulator::PoolSimulator;
 
+/// Whole token0 each leg of `simulate_direction` trades.
+const PROBE_AMOUNT: f64 = 1.0;
+
+/// Prices a direction's two legs with the simulators registered for its pools' variants:
+/// sell `PROBE_AMOUNT` token0 on `path[1]` for token1, and buy as much token0 back on
+/// `path[2]`. Returns `(sell pool, buy pool, sell price, buy price, spread)`, with both
+/// prices the average token1 per token0 of their leg.
 pub async fn simulate_direction(
     dir: &ArbitrageDirection,
 ) -> Option<(String, String, f64, f64, f64)> {
-    match dir.pool_type.as_str() {
-        "V2" => simulate_v2(dir).await,
-        "V3" => simulate_v3(dir).await,
-        _ => {
-            log::error!("Unknown pool_type: {}", dir.pool_type);
+    match simulate_legs(dir).await {
+        Ok((sell, buy)) => {
+            let (sell_price, buy_price) = (sell.to_f64(), buy.to_f64());
+            let spread = sell_price - buy_price;
+            info!(
+                "OK: sell_pool={} buy_pool={} sell_price={:.9} buy_price={:.9} spread={:.9}",
+                dir.path[1], dir.path[2], sell_price, buy_price, spread
+            );
+            Some((dir.path[1].clone(), dir.path[2].clone(), sell_price, buy_price, spread))
+        }
+        Err(e) => {
+            error!("Simulation failed for direction {:?}: {e:#}", dir.path);
             None
         }
     }
 }
+
+async fn simulate_legs(dir: &ArbitrageDirection) -> Result<(Price<Token0, Token1>, Price<Token0, Token1>)> {
+    let (Some(sell), Some(buy)) = (dir.pools.get(1), dir.pools.get(2)) else {
+        return Err(anyhow!("Pools not described for direction {:?}", dir.path));
+    };
+    let token0 = TokenRef::resolve(&dir.provider, dir.token0.parse()?).await?;
+    let token1 = TokenRef::resolve(&dir.provider, dir.token1.parse()?).await?;
+
+    let (sell, buy): (Box<dyn PoolSimulator>, Box<dyn PoolSimulator>) =
+        futures::try_join!(pool_simulator::load(&dir.provider, sell), pool_simulator::load(&dir.provider, buy))?;
+    let loan = TokenAmount::from_f64(PROBE_AMOUNT, token0)?;
+
+    let received = TokenAmount::new(sell.quote_exact_in(token0.address, loan.raw)?, token1);
+    let cost = TokenAmount::new(buy.quote_exact_out(token1.address, loan.raw)?, token1);
+    Ok((Price::from_amounts(&loan, &received)?, Price::from_amounts(&loan, &cost)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::{AmmKind, PoolVariant};
+    use crate::pools::PoolDescriptor;
+    use crate::rpc::{self, FixtureClient, RpcClient};
+    use ethers::types::U256;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    /// Runs `fut` with `dir.provider` served from its fixture file, for this test only.
+    async fn replay<F: std::future::Future>(dir: &ArbitrageDirection, fut: F) -> F::Output {
+        let name = &dir.provider["fixture://".len()..];
+        let client = RpcClient::Fixture(FixtureClient::replay(fixture(&format!("{}.json", name))).unwrap());
+        rpc::scoped(&dir.provider, client, fut).await
+    }
+
+    fn direction(pool_type: &str, provider: &str, token0: &str, token1: &str, path: [&str; 3]) -> ArbitrageDirection {
+        ArbitrageDirection {
+            pool_type: pool_type.to_string(),
+            token0: token0.to_string(),
+            token1: token1.to_string(),
+            path: path.iter().map(|p| p.to_string()).collect(),
+            roi: 0.0,
+            profit: 0.0,
+            priceDifference: 0.0,
+            pool_fee: None,
+            provider: provider.to_string(),
+            chain: None,
+            pools: path
+                .iter()
+                .map(|p| PoolDescriptor {
+                    address: p.parse().unwrap(),
+                    kind: AmmKind::from_pool_type(pool_type).unwrap(),
+                    variant: match pool_type {
+                        "V2" => PoolVariant::UniswapV2,
+                        _ => PoolVariant::UniswapV3,
+                    },
+                    dex: None,
+                    fee: 0.003,
+                })
+                .collect(),
+        }
+    }
+
+    fn v2_direction() -> ArbitrageDirection {
+        direction(
+            "V2",
+            "fixture://v2_ethereum",
+            "0xC02aaa39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            [
+                "0x2E8135bE71230c6B1B4045696d41C09Db0414226",
+                "0x397ff1542f962076d0bfe58ea045ffa2d347aca0",
+                "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc",
+            ],
+        )
+    }
+
+    fn v3_direction() -> ArbitrageDirection {
+        direction(
+            "V3",
+            "fixture://v3_arbitrum",
+            "0x82af49447d8a07e3bd95bd0d56f35241523fbab1",
+            "0xaf88d065e77c8cC2239327C5EDb3A432268e5831",
+            [
+                "0xf3eb87c1f6020982173c908e7eb31aa66c1f0296",
+                "0x7fCDC35463E3770c2fB992716Cd070B63540b947",
+                "0x6f38e884725a116C9C7fBF208e79FE8828a2595F",
+            ],
+        )
+    }
+
+    #[tokio::test]
+    async fn v2_direction_replays_fixture() {
+        let dir = v2_direction();
+        let (sell_pool, buy_pool, sell, buy, spread) = replay(&dir, simulate_direction(&dir)).await.unwrap();
+
+        // Both legs pay the pools' 0.3% fee: ~2499.4 sells for 0.3% less, ~2475.3 costs 0.3% more.
+        assert_eq!(sell_pool, "0x397ff1542f962076d0bfe58ea045ffa2d347aca0");
+        assert_eq!(buy_pool, "0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+        assert!((sell - 2491.88).abs() < 0.05, "sell = {sell}");
+        assert!((buy - 2483.00).abs() < 0.05, "buy = {buy}");
+        assert!((spread - (sell - buy)).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn v3_direction_replays_fixture() {
+        let dir = v3_direction();
+        let (_, _, sell, buy, spread) = replay(&dir, simulate_direction(&dir)).await.unwrap();
+
+        assert!((sell - 2492.38).abs() < 0.05, "sell = {sell}");
+        assert!((buy - 2482.57).abs() < 0.05, "buy = {buy}");
+        assert!(spread > 9.0 && spread < 11.0, "spread = {spread}");
+    }
+
+    /// Pays out twice its input and sells at par, whatever the chain says.
+    #[derive(Debug, Clone)]
+    struct FixedRate(PoolDescriptor);
+
+    #[async_trait::async_trait]
+    impl PoolSimulator for FixedRate {
+        fn descriptor(&self) -> &PoolDescriptor {
+            &self.0
+        }
+
+        async fn fetch_state(&mut self, _rpc_url: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn quote_exact_in(&self, _token_in: ethers::types::Address, amount_in: U256) -> Result<U256> {
+            Ok(amount_in * 2)
+        }
+
+        fn quote_exact_out(&self, _token_in: ethers::types::Address, amount_out: U256) -> Result<U256> {
+            Ok(amount_out)
+        }
+
+        fn spot_price(&self, _token_in: ethers::types::Address) -> Result<f64> {
+            Ok(2.0)
+        }
+
+        fn apply_swap(&mut self, token_in: ethers::types::Address, amount_in: U256) -> Result<U256> {
+            self.quote_exact_in(token_in, amount_in)
+        }
+
+        fn box_clone(&self) -> Box<dyn PoolSimulator> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn directions_are_priced_by_the_simulator_registered_for_their_variant() {
+        use crate::pools_abi::erc20_abi::DecimalsCall;
+        use crate::rpc::fixture::{eth_call_entry, FixtureEntry};
+        use serde_json::json;
+
+        const URL: &str = "fixture://registry-dispatch";
+        let tokens = ["0a", "0b"].map(|byte| format!("0x{}", byte.repeat(20)));
+        let mut entries = vec![FixtureEntry::new("eth_chainId", json!(null), json!("0x7a6c"))];
+        for token in &tokens {
+            entries.push(eth_call_entry(token.parse().unwrap(), "latest", DecimalsCall, 18u8));
+        }
+        let pools = ["01", "02", "03"].map(|byte| format!("0x{}", byte.repeat(20)));
+        let mut dir = direction("V3", URL, &tokens[0], &tokens[1], [&pools[0], &pools[1], &pools[2]].map(String::as_str));
+        dir.pools.iter_mut().for_each(|pool| pool.variant = PoolVariant::Algebra);
+
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        let fixed_rate: pool_simulator::SimulatorFactory = |descriptor| Box::new(FixedRate(descriptor));
+        let (_, _, sell, buy, spread) =
+            pool_simulator::scoped(PoolVariant::Algebra, fixed_rate, rpc::scoped(URL, client, simulate_direction(&dir)))
+                .await
+                .unwrap();
+        assert_eq!((sell, buy, spread), (2.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::dexes::PoolVariant;
+use crate::pools::PoolDescriptor;
+use crate::simulator::amm::PoolSnapshot;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::types::{Address, U256};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::OnceLock;
+
+/// One pool's swap model. Implementations own whatever state their AMM needs; callers
+/// only see token amounts in raw units.
+#[async_trait]
+pub trait PoolSimulator: Send + Sync + Debug {
+    fn descriptor(&self) -> &PoolDescriptor;
+
+    /// Reads the pool's current state from the chain behind `rpc_url`.
+    async fn fetch_state(&mut self, rpc_url: &str) -> Result<()>;
+
+    /// Output for selling `amount_in` of `token_in`.
+    fn quote_exact_in(&self, token_in: Address, amount_in: U256) -> Result<U256>;
+
+    /// Input of `token_in` needed to receive `amount_out` of the other token.
+    fn quote_exact_out(&self, token_in: Address, amount_out: U256) -> Result<U256>;
+
+    /// Marginal output per input in raw units, net of fee.
+    fn spot_price(&self, token_in: Address) -> Result<f64>;
+
+    /// Performs the swap on the local state and returns the output.
+    fn apply_swap(&mut self, token_in: Address, amount_in: U256) -> Result<U256>;
+
+    fn box_clone(&self) -> Box<dyn PoolSimulator>;
+}
+
+impl Clone for Box<dyn PoolSimulator> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Constant-product and concentrated-liquidity pools, both backed by a `PoolSnapshot`.
+#[derive(Debug, Clone)]
+pub struct SnapshotSimulator {
+    descriptor: PoolDescriptor,
+    state: Option<PoolSnapshot>,
+}
+
+impl SnapshotSimulator {
+    pub fn new(descriptor: PoolDescriptor) -> Self {
+        SnapshotSimulator { descriptor, state: None }
+    }
+
+    #[cfg(test)]
+    pub fn with_state(descriptor: PoolDescriptor, state: PoolSnapshot) -> Self {
+        SnapshotSimulator { descriptor, state: Some(state) }
+    }
+
+    fn state(&self) -> Result<&PoolSnapshot> {
+        self.state
+            .as_ref()
+            .ok_or_else(|| anyhow!("No state fetched for pool {:?}", self.descriptor.address))
+    }
+}
+
+#[async_trait]
+impl PoolSimulator for SnapshotSimulator {
+    fn descriptor(&self) -> &PoolDescriptor {
+        &self.descriptor
+    }
+
+    async fn fetch_state(&mut self, rpc_url: &str) -> Result<()> {
+        self.state = Some(PoolSnapshot::fetch(rpc_url, &self.descriptor).await?);
+        Ok(())
+    }
+
+    fn quote_exact_in(&self, token_in: Address, amount_in: U256) -> Result<U256> {
+        self.state()?.amount_out(token_in, amount_in)
+    }
+
+    fn quote_exact_out(&self, token_in: Address, amount_out: U256) -> Result<U256> {
+        self.state()?.amount_in(token_in, amount_out)
+    }
+
+    fn spot_price(&self, token_in: Address) -> Result<f64> {
+        self.state()?.spot_price(token_in)
+    }
+
+    fn apply_swap(&mut self, token_in: Address, amount_in: U256) -> Result<U256> {
+        let state = self.state()?;
+        let out = state.amount_out(token_in, amount_in)?;
+        self.state = Some(state.apply_swap(token_in, amount_in)?);
+        Ok(out)
+    }
+
+    fn box_clone(&self) -> Box<dyn PoolSimulator> {
+        Box::new(self.clone())
+    }
+}
+
+/// Builds an unfetched simulator for a pool.
+pub type SimulatorFactory = fn(PoolDescriptor) -> Box<dyn PoolSimulator>;
+
+fn snapshot_simulator(descriptor: PoolDescriptor) -> Box<dyn PoolSimulator> {
+    Box::new(SnapshotSimulator::new(descriptor))
+}
+
+static FACTORIES: OnceLock<HashMap<PoolVariant, SimulatorFactory>> = OnceLock::new();
+
+fn factories() -> &'static HashMap<PoolVariant, SimulatorFactory> {
+    FACTORIES.get_or_init(|| {
+        HashMap::from([
+            (PoolVariant::UniswapV2, snapshot_simulator as SimulatorFactory),
+            (PoolVariant::UniswapV3, snapshot_simulator as SimulatorFactory),
+            (PoolVariant::PancakeV3, snapshot_simulator as SimulatorFactory),
+            (PoolVariant::Algebra, snapshot_simulator as SimulatorFactory),
+        ])
+    })
+}
+
+tokio::task_local! {
+    static SCOPED: HashMap<PoolVariant, SimulatorFactory>;
+}
+
+/// Runs `fut` with `factory` building the simulators for `variant` pools loaded inside it,
+/// without touching the built-in registry. Used by tests to plug a simulator in; tasks
+/// spawned from `fut` don't see the override.
+#[cfg(test)]
+pub async fn scoped<F: std::future::Future>(variant: PoolVariant, factory: SimulatorFactory, fut: F) -> F::Output {
+    let mut factories = SCOPED.try_with(|f| f.clone()).unwrap_or_default();
+    factories.insert(variant, factory);
+    SCOPED.scope(factories, fut).await
+}
+
+pub fn for_pool(descriptor: &PoolDescriptor) -> Result<Box<dyn PoolSimulator>> {
+    let scoped = SCOPED.try_with(|f| f.get(&descriptor.variant).copied()).ok().flatten();
+    let factory = scoped
+        .or_else(|| factories().get(&descriptor.variant).copied())
+        .ok_or_else(|| anyhow!("No simulator registered for {:?}", descriptor.variant))?;
+    Ok(factory(descriptor.clone()))
+}
+
+/// `for_pool` followed by `fetch_state`.
+pub async fn load(rpc_url: &str, descriptor: &PoolDescriptor) -> Result<Box<dyn PoolSimulator>> {
+    let mut simulator = for_pool(descriptor)?;
+    simulator.fetch_state(rpc_url).await?;
+    Ok(simulator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::AmmKind;
+
+    #[test]
+    fn registry_builds_simulators_that_track_their_own_state() {
+        let address = Address::repeat_byte(1);
+        let (a, b) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb));
+        let descriptor = PoolDescriptor { address, kind: AmmKind::V2, variant: PoolVariant::UniswapV2, dex: None, fee: 0.003 };
+
+        let mut unfetched = for_pool(&descriptor).unwrap();
+        assert!(unfetched.spot_price(a).is_err());
+        assert!(unfetched.apply_swap(a, U256::one()).is_err());
+
+        let snapshot = PoolSnapshot::V2 {
+            address,
+            token0: a,
+            token1: b,
+            reserve0: U256::from(1_000_000u64),
+            reserve1: U256::from(2_000_000u64),
+            fee: 0.003,
+        };
+        let mut sim: Box<dyn PoolSimulator> = Box::new(SnapshotSimulator::with_state(descriptor, snapshot));
+        let before = sim.spot_price(a).unwrap();
+        let quoted = sim.quote_exact_in(a, U256::from(10_000u64)).unwrap();
+
+        let fork = sim.clone();
+        assert_eq!(sim.apply_swap(a, U256::from(10_000u64)).unwrap(), quoted);
+        assert!(sim.spot_price(a).unwrap() < before);
+        assert_eq!(fork.spot_price(a).unwrap(), before);
+
+        let needed = fork.quote_exact_out(a, quoted).unwrap();
+        assert!(needed <= U256::from(10_000u64));
+    }
+}
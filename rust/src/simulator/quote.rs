@@ -0,0 +1,337 @@
+use crate::config::QuoteSettings;
+use crate::directions::pair_key;
+use crate::pools::PoolDescriptor;
+use crate::rpc;
+use crate::simulator::amm::{to_f64, PoolSnapshot};
+use crate::units::{TokenAmount, TokenRef};
+use crate::ArbitrageDirection;
+use anyhow::{anyhow, Result};
+use ethers::providers::Middleware;
+use ethers::types::{Address, U256};
+use futures::future::join_all;
+use log::{error, info, warn};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// The part of an order routed through one pool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Split {
+    pub pool: Address,
+    pub amount_in: U256,
+    pub amount_out: U256,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// Pools that receive a non-zero share, largest first.
+    pub splits: Vec<Split>,
+}
+
+/// Quotes orders over a fixed set of pool snapshots.
+#[derive(Debug, Clone, Default)]
+pub struct Quoter {
+    pools: Vec<PoolSnapshot>,
+}
+
+impl Quoter {
+    pub fn new(pools: Vec<PoolSnapshot>) -> Self {
+        Quoter { pools }
+    }
+
+    /// Reads every pool at the head block, leaving out pools whose state can't be read.
+    pub async fn fetch(rpc_url: &str, pools: &[PoolDescriptor]) -> Result<Self> {
+        let block = rpc::provider(rpc_url)?.get_block_number().await?;
+        let fetched = join_all(pools.iter().map(|pool| PoolSnapshot::fetch_at(rpc_url, pool, block.into()))).await;
+        let mut snapshots = Vec::new();
+        for (pool, snapshot) in pools.iter().zip(fetched) {
+            match snapshot {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(e) => warn!("Skipping pool {:?} for quotes: {e:#}", pool.address),
+            }
+        }
+        Ok(Quoter::new(snapshots))
+    }
+
+    /// Best output for selling `amount` of `token_in` across every pool of the pair.
+    ///
+    /// Marginal-price equalization: the optimal split leaves every used pool at the same
+    /// marginal rate. That rate is found by bisection on `Σ input_to_rate(rate) = amount`,
+    /// after which each share is quoted exactly. The single best pool is returned instead
+    /// whenever rounding makes the split worse. A pool that can't fill its share (a V3
+    /// snapshot swapping past its fetched ticks) is left out; only when no pool can quote
+    /// is it an error.
+    pub fn quote(&self, token_in: Address, token_out: Address, amount: U256) -> Result<Quote> {
+        let pools: Vec<&PoolSnapshot> = self
+            .pools
+            .iter()
+            .filter(|p| p.other(token_in) == Some(token_out))
+            .collect();
+        if pools.is_empty() {
+            return Err(anyhow!("No pools for {:?}→{:?}", token_in, token_out));
+        }
+
+        let mut candidates = pools.clone();
+        let mut split = None;
+        while !candidates.is_empty() {
+            match split_across(&candidates, token_in, amount) {
+                Ok(splits) => {
+                    split = Some(splits);
+                    break;
+                }
+                Err(failed) => {
+                    warn!("Pool {:?} can't fill its share of {:?}→{:?}, leaving it out", candidates[failed].address(), token_in, token_out);
+                    candidates.remove(failed);
+                }
+            }
+        }
+
+        let mut best_single: Option<Split> = None;
+        for pool in &pools {
+            let Ok(out) = pool.amount_out(token_in, amount) else {
+                continue;
+            };
+            if best_single.as_ref().is_none_or(|best| out > best.amount_out) {
+                best_single = Some(Split { pool: pool.address(), amount_in: amount, amount_out: out });
+            }
+        }
+
+        let split_out = split
+            .as_ref()
+            .map(|splits| splits.iter().fold(U256::zero(), |acc, s| acc + s.amount_out));
+        let (amount_out, splits) = match (best_single, split, split_out) {
+            (Some(single), Some(splits), Some(split_out)) if split_out > single.amount_out => (split_out, splits),
+            (Some(single), _, _) => (single.amount_out, vec![single]),
+            (None, Some(splits), Some(split_out)) => (split_out, splits),
+            _ => return Err(anyhow!("No pool can quote {} of {:?}→{:?}", amount, token_in, token_out)),
+        };
+
+        info!(
+            "Quote {:?}→{:?}: in={} out={} across {} pool(s)",
+            token_in, token_out, amount, amount_out, splits.len()
+        );
+        Ok(Quote { token_in, token_out, amount_in: amount, amount_out, splits })
+    }
+}
+
+/// Splits `amount` across `pools` at a common marginal rate, largest share first; the
+/// index of the first pool that can't quote its share otherwise.
+fn split_across(pools: &[&PoolSnapshot], token_in: Address, amount: U256) -> std::result::Result<Vec<Split>, usize> {
+    let target = to_f64(amount);
+    let total_at = |rate: f64| -> f64 {
+        pools.iter().map(|p| p.input_to_rate(token_in, rate).unwrap_or(0.0)).sum()
+    };
+
+    // Every pool's rate only falls as it absorbs input, so the answer lies below the best spot rate.
+    let mut hi = pools
+        .iter()
+        .filter_map(|p| p.spot_price(token_in).ok())
+        .fold(0.0f64, f64::max);
+    let mut lo = hi;
+    while total_at(lo) < target && lo > f64::MIN_POSITIVE {
+        lo /= 2.0;
+    }
+    for _ in 0..200 {
+        let mid = (lo * hi).sqrt();
+        if total_at(mid) > target {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+        if hi / lo - 1.0 < 1e-12 {
+            break;
+        }
+    }
+
+    let shares: Vec<f64> = pools.iter().map(|p| p.input_to_rate(token_in, hi).unwrap_or(0.0)).collect();
+    let sum: f64 = shares.iter().sum();
+
+    let mut splits = Vec::new();
+    let mut assigned = U256::zero();
+    let last = shares.iter().enumerate().fold(0, |best, (i, s)| if *s > shares[best] { i } else { best });
+    for (i, (pool, share)) in pools.iter().zip(&shares).enumerate() {
+        if i == last || *share <= 0.0 {
+            continue;
+        }
+        let amount_in = U256::from((share / sum * target).floor() as u128).min(amount - assigned);
+        if amount_in.is_zero() {
+            continue;
+        }
+        assigned += amount_in;
+        let amount_out = pool.amount_out(token_in, amount_in).map_err(|_| i)?;
+        splits.push(Split { pool: pool.address(), amount_in, amount_out });
+    }
+    // The largest share absorbs the rounding remainder.
+    let remainder = amount - assigned;
+    splits.push(Split {
+        pool: pools[last].address(),
+        amount_in: remainder,
+        amount_out: pools[last].amount_out(token_in, remainder).map_err(|_| last)?,
+    });
+    splits.retain(|s| !s.amount_in.is_zero());
+    splits.sort_by_key(|s| Reverse(s.amount_in));
+    Ok(splits)
+}
+
+/// Quotes selling `amount` whole token0 for token1 once per pair and provider, split across
+/// every described pool of that pair among `directions`. This is the depth the engine can
+/// exit a direction's position through.
+pub async fn quote_directions(directions: &[ArbitrageDirection], settings: &QuoteSettings) -> Vec<Quote> {
+    let mut pairs: BTreeMap<(&str, String), (&ArbitrageDirection, Vec<PoolDescriptor>)> = BTreeMap::new();
+    for dir in directions {
+        let (_, pools) = pairs
+            .entry((dir.provider.as_str(), pair_key(&dir.token0, &dir.token1)))
+            .or_insert_with(|| (dir, Vec::new()));
+        for pool in &dir.pools {
+            if !pools.iter().any(|p| p.address == pool.address) {
+                pools.push(pool.clone());
+            }
+        }
+    }
+
+    let mut quotes = Vec::new();
+    for ((provider, pair), (dir, pools)) in pairs {
+        let quote = async {
+            let (token_in, token_out): (Address, Address) = (dir.token0.parse()?, dir.token1.parse()?);
+            let amount = TokenAmount::from_f64(settings.amount, TokenRef::resolve(provider, token_in).await?)?;
+            Quoter::fetch(provider, &pools).await?.quote(token_in, token_out, amount.raw)
+        };
+        match quote.await {
+            Ok(quote) => quotes.push(quote),
+            Err(e) => error!("Quote for {} on {} failed: {e:#}", pair, provider),
+        }
+    }
+    quotes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v2(n: u8, reserve0: u128, reserve1: u128) -> PoolSnapshot {
+        PoolSnapshot::V2 {
+            address: Address::repeat_byte(n),
+            token0: Address::repeat_byte(0xa),
+            token1: Address::repeat_byte(0xb),
+            reserve0: U256::from(reserve0),
+            reserve1: U256::from(reserve1),
+            fee: 0.003,
+        }
+    }
+
+    #[test]
+    fn identical_pools_split_evenly_and_beat_a_single_pool() {
+        let (a, b) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb));
+        let quoter = Quoter::new(vec![v2(1, 1_000_000, 2_000_000), v2(2, 1_000_000, 2_000_000)]);
+        let amount = U256::from(200_000u64);
+
+        let quote = quoter.quote(a, b, amount).unwrap();
+        assert_eq!(quote.splits.len(), 2);
+        let (x, y) = (quote.splits[0].amount_in, quote.splits[1].amount_in);
+        assert_eq!(x + y, amount);
+        assert!(x - y <= U256::from(2u64), "{x} vs {y}");
+
+        let single = v2(1, 1_000_000, 2_000_000).amount_out(a, amount).unwrap();
+        assert!(quote.amount_out > single);
+    }
+
+    #[test]
+    fn small_orders_stay_on_the_best_pool() {
+        let (a, b) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb));
+        let quoter = Quoter::new(vec![v2(1, 1_000_000, 2_000_000), v2(2, 1_000_000, 1_900_000)]);
+
+        let quote = quoter.quote(a, b, U256::from(1_000u64)).unwrap();
+        assert_eq!(quote.splits.len(), 1);
+        assert_eq!(quote.splits[0].pool, Address::repeat_byte(1));
+        assert!(quoter.quote(b, Address::repeat_byte(0xc), U256::one()).is_err());
+    }
+
+    #[test]
+    fn pools_that_cannot_quote_are_left_out() {
+        use crate::pool_adapters::PoolState;
+        use crate::simulator::amm::from_f64;
+        use crate::ticks::PoolTicks;
+
+        let (a, b) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb));
+        // Best spot rate of the three, but no initialized ticks fetched: any swap runs off them.
+        let stranded = PoolSnapshot::V3 {
+            address: Address::repeat_byte(3),
+            token0: a,
+            token1: b,
+            state: PoolState {
+                sqrt_price_x96: from_f64(2.2f64.sqrt() * 2f64.powi(96)),
+                tick: 7885,
+                liquidity: 1_000_000,
+                fee: None,
+            },
+            fee: 0.003,
+            ticks: Some(PoolTicks::new(60, 0, 0..=0, vec![])),
+        };
+        assert!(stranded.amount_out(a, U256::from(1_000u64)).is_err());
+
+        let amount = U256::from(200_000u64);
+        let healthy = Quoter::new(vec![v2(1, 1_000_000, 2_000_000), v2(2, 1_000_000, 2_000_000)]).quote(a, b, amount).unwrap();
+        let quoter = Quoter::new(vec![v2(1, 1_000_000, 2_000_000), stranded.clone(), v2(2, 1_000_000, 2_000_000)]);
+        let quote = quoter.quote(a, b, amount).unwrap();
+        assert!(quote.splits.iter().all(|s| s.pool != Address::repeat_byte(3)));
+        assert_eq!(quote.amount_out, healthy.amount_out);
+
+        assert!(Quoter::new(vec![stranded]).quote(a, b, amount).is_err());
+    }
+
+    #[tokio::test]
+    async fn directions_are_quoted_once_per_pair_across_all_their_pools() {
+        use crate::dexes::{AmmKind, PoolVariant};
+        use crate::pools_abi::erc20_abi::DecimalsCall;
+        use crate::pools_abi::uniswap_v2_pair::{GetReservesCall, GetReservesReturn, Token0Call, Token1Call};
+        use crate::rpc::fixture::{eth_call_entry, FixtureEntry};
+        use crate::rpc::{FixtureClient, RpcClient};
+        use serde_json::json;
+
+        const URL: &str = "fixture://quote-directions";
+        let (a, b) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb));
+        let mut entries = vec![
+            FixtureEntry::new("eth_chainId", json!(null), json!("0x7a6b")),
+            FixtureEntry::new("eth_blockNumber", json!(null), json!("0x10")),
+            eth_call_entry(a, "latest", DecimalsCall, 6u8),
+        ];
+        for n in 1..=3u8 {
+            let pair = Address::repeat_byte(n);
+            let reserves = GetReservesReturn { reserve_0: 1_000_000_000, reserve_1: 2_000_000_000, block_timestamp_last: 0 };
+            entries.push(eth_call_entry(pair, "0x10", GetReservesCall, reserves));
+            entries.push(eth_call_entry(pair, "0x10", Token0Call, a));
+            entries.push(eth_call_entry(pair, "0x10", Token1Call, b));
+        }
+
+        let descriptor = |n: u8| PoolDescriptor {
+            address: Address::repeat_byte(n),
+            kind: AmmKind::V2,
+            variant: PoolVariant::UniswapV2,
+            dex: None,
+            fee: 0.003,
+        };
+        let direction = |path: [u8; 3]| ArbitrageDirection {
+            pool_type: "V2".to_string(),
+            token0: format!("{:?}", a),
+            token1: format!("{:?}", b),
+            path: path.iter().map(|n| format!("{:?}", Address::repeat_byte(*n))).collect(),
+            roi: 0.0,
+            profit: 0.0,
+            priceDifference: 0.0,
+            pool_fee: None,
+            provider: URL.to_string(),
+            chain: None,
+            pools: path.iter().map(|n| descriptor(*n)).collect(),
+        };
+        let directions = [direction([1, 2, 3]), direction([3, 1, 2])];
+
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        let quotes = rpc::scoped(URL, client, quote_directions(&directions, &QuoteSettings::default())).await;
+        assert_eq!(quotes.len(), 1);
+        assert_eq!(quotes[0].amount_in, U256::from(1_000_000u64));
+        assert_eq!(quotes[0].splits.len(), 3);
+    }
+}
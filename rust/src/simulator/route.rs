@@ -0,0 +1,448 @@
+use crate::config::RouteSettings;
+use crate::pools::PoolDescriptor;
+use crate::rpc;
+use crate::simulator::amm::PoolSnapshot;
+use crate::simulator::exact;
+use crate::simulator::graph::best_amount;
+use crate::simulator::limits;
+use crate::simulator::sizing::optimal_amount;
+use crate::units::{TokenAmount, TokenRef};
+use crate::ArbitrageDirection;
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use ethers::providers::Middleware;
+use ethers::types::{Address, BlockId, U256};
+use futures::future::try_join_all;
+use log::{error, info, warn};
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// One swap in a route: sell `token_in` for `token_out` on `pool`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hop {
+    pub pool: PoolDescriptor,
+    pub token_in: Address,
+    pub token_out: Address,
+}
+
+/// A chain of hops where each hop sells what the previous one bought, e.g.
+/// WETH→USDC→ARB→WETH. Unlike `ArbitrageDirection`, hops may trade different pairs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub hops: Vec<Hop>,
+}
+
+/// Amounts along a simulated route; `amounts[0]` is the input, `amounts[i + 1]` the output of hop `i`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteResult {
+    pub amounts: Vec<U256>,
+}
+
+impl RouteResult {
+    pub fn amount_in(&self) -> U256 {
+        self.amounts[0]
+    }
+
+    pub fn amount_out(&self) -> U256 {
+        *self.amounts.last().unwrap()
+    }
+
+    /// Output minus input in the start token; only meaningful for cycles.
+    pub fn profit(&self) -> Option<U256> {
+        self.amount_out().checked_sub(self.amount_in())
+    }
+
+    /// Signed, exact profit in whole start tokens, for reporting.
+    pub fn profit_decimal(&self, decimals: u32) -> BigDecimal {
+        exact::profit(self.amount_in(), self.amount_out(), decimals)
+    }
+}
+
+impl Route {
+    pub fn new(hops: Vec<Hop>) -> Result<Self> {
+        if hops.is_empty() {
+            return Err(anyhow!("Route has no hops"));
+        }
+        for pair in hops.windows(2) {
+            if pair[0].token_out != pair[1].token_in {
+                return Err(anyhow!(
+                    "Hop via {:?} outputs {:?} but the next hop sells {:?}",
+                    pair[0].pool.address, pair[0].token_out, pair[1].token_in
+                ));
+            }
+        }
+        Ok(Route { hops })
+    }
+
+    pub fn start_token(&self) -> Address {
+        self.hops[0].token_in
+    }
+
+    pub fn end_token(&self) -> Address {
+        self.hops[self.hops.len() - 1].token_out
+    }
+
+    pub fn is_cycle(&self) -> bool {
+        self.start_token() == self.end_token()
+    }
+
+    /// Tokens visited, starting and ending with the start token for a cycle.
+    pub fn tokens(&self) -> Vec<Address> {
+        std::iter::once(self.start_token())
+            .chain(self.hops.iter().map(|h| h.token_out))
+            .collect()
+    }
+
+    /// The cycle a direction trades: sell token0 for token1 on `path[1]`, buy token0 back
+    /// on `path[2]`. `path[0]` only lends the token0.
+    pub fn from_direction(dir: &ArbitrageDirection) -> Result<Self> {
+        let (Some(sell), Some(buy)) = (dir.pools.get(1), dir.pools.get(2)) else {
+            return Err(anyhow!("Pools not described for direction {:?}", dir.path));
+        };
+        let token0: Address = dir.token0.parse()?;
+        let token1: Address = dir.token1.parse()?;
+        Route::new(vec![
+            Hop { pool: sell.clone(), token_in: token0, token_out: token1 },
+            Hop { pool: buy.clone(), token_in: token1, token_out: token0 },
+        ])
+    }
+
+    /// Reads every pool on the route once, all as of `block`.
+    pub async fn fetch_snapshots_at(&self, rpc_url: &str, block: BlockId) -> Result<HashMap<Address, PoolSnapshot>> {
+        let mut snapshots = HashMap::new();
+        for hop in &self.hops {
+            if let Entry::Vacant(entry) = snapshots.entry(hop.pool.address) {
+                entry.insert(PoolSnapshot::fetch_at(rpc_url, &hop.pool, block).await?);
+            }
+        }
+        Ok(snapshots)
+    }
+
+    /// Runs `amount_in` through every hop with the V2/V3 swap math. A pool visited twice
+    /// sees the state left by the earlier hop.
+    pub fn simulate(&self, snapshots: &HashMap<Address, PoolSnapshot>, amount_in: U256) -> Result<RouteResult> {
+        let mut state: HashMap<Address, PoolSnapshot> = HashMap::new();
+        let mut amounts = Vec::with_capacity(self.hops.len() + 1);
+        amounts.push(amount_in);
+
+        let mut amount = amount_in;
+        for hop in &self.hops {
+            let snapshot = match state.get(&hop.pool.address) {
+                Some(s) => s,
+                None => snapshots
+                    .get(&hop.pool.address)
+                    .ok_or_else(|| anyhow!("No snapshot for pool {:?}", hop.pool.address))?,
+            };
+            if snapshot.other(hop.token_in) != Some(hop.token_out) {
+                return Err(anyhow!("Pool {:?} doesn't trade {:?}→{:?}", hop.pool.address, hop.token_in, hop.token_out));
+            }
+
+            let out = snapshot.amount_out(hop.token_in, amount)?;
+            let next = snapshot.apply_swap(hop.token_in, amount)?;
+            state.insert(hop.pool.address, next);
+            amount = out;
+            amounts.push(out);
+        }
+
+        Ok(RouteResult { amounts })
+    }
+}
+
+/// A route sized against pool state read at one block, so the same block can be used to
+/// check it on-chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizedRoute {
+    pub block: u64,
+    pub route: Route,
+    pub result: RouteResult,
+}
+
+/// Reads the direction's pools at the head block and sizes its cycle with
+/// `sizing::optimal_amount`, up to `max_amount` whole token0 and to what the sell pool takes
+/// within `max_impact` (and, without fetched ticks, within its current tick-spacing range).
+/// `loan_cost` is what repaying each unit borrowed costs, flash fee included (see
+/// `execution::flash_fee_rate`). `None` when no size is profitable after it.
+pub async fn size_direction(
+    dir: &ArbitrageDirection,
+    max_amount: f64,
+    max_impact: f64,
+    loan_cost: f64,
+) -> Result<Option<SizedRoute>> {
+    let route = Route::from_direction(dir)?;
+    let token_in = TokenRef::resolve(&dir.provider, route.start_token()).await?;
+    let upper = TokenAmount::from_f64(max_amount, token_in)?;
+
+    let block = rpc::provider(&dir.provider)?.get_block_number().await?.as_u64();
+    let snapshots = route.fetch_snapshots_at(&dir.provider, block.into()).await?;
+    let (sell, buy) = (&route.hops[0], &route.hops[1]);
+    let (sell_snapshot, buy_snapshot) = (&snapshots[&sell.pool.address], &snapshots[&buy.pool.address]);
+
+    let spacing = match sell_snapshot {
+        PoolSnapshot::V3 { ticks: None, .. } => limits::fetch_tick_spacing(&dir.provider, &sell.pool, block.into()).await?,
+        _ => None,
+    };
+    let cap = limits::max_input(sell_snapshot, token_in.address, max_impact, spacing)?.min(upper.raw);
+    let amount = optimal_amount(sell_snapshot, buy_snapshot, token_in.address, cap, loan_cost)?;
+    if amount.is_zero() {
+        return Ok(None);
+    }
+    let result = route.simulate(&snapshots, amount)?;
+    if result.profit().is_none_or(|profit| profit.is_zero()) {
+        return Ok(None);
+    }
+    info!(
+        "Sized {:?} at block {}: in={} out={} profit={}",
+        route.tokens(),
+        block,
+        TokenAmount::new(result.amount_in(), token_in).to_decimal(),
+        TokenAmount::new(result.amount_out(), token_in).to_decimal(),
+        result.profit_decimal(token_in.decimals as u32)
+    );
+    Ok(Some(SizedRoute { block, route, result }))
+}
+
+/// Reads `pools` once at the head block and sizes every cycle of up to `max_hops` hops from
+/// `start` with `best_amount`, up to `amount` whole start tokens. Returns the profitable
+/// cycles, most profitable first.
+pub async fn search(
+    rpc_url: &str,
+    pools: &[PoolDescriptor],
+    start: Address,
+    max_hops: usize,
+    amount: f64,
+) -> Result<Vec<SizedRoute>> {
+    let token = TokenRef::resolve(rpc_url, start).await?;
+    let upper = TokenAmount::from_f64(amount, token)?.raw;
+    let block = rpc::provider(rpc_url)?.get_block_number().await?.as_u64();
+    let snapshots = try_join_all(pools.iter().map(|pool| PoolSnapshot::fetch_at(rpc_url, pool, block.into()))).await?;
+    let pools: Vec<(PoolDescriptor, PoolSnapshot)> = pools.iter().cloned().zip(snapshots).collect();
+    let by_address: HashMap<Address, PoolSnapshot> = pools.iter().map(|(d, s)| (d.address, s.clone())).collect();
+
+    let mut found = Vec::new();
+    for route in find_cycles(&pools, start, max_hops) {
+        match best_amount(&route, &by_address, upper) {
+            Ok(Some(result)) => found.push(SizedRoute { block, route, result }),
+            Ok(None) => {}
+            Err(e) => warn!("Skipping route {:?}: {e:#}", route.tokens()),
+        }
+    }
+    found.sort_by_key(|sized| Reverse(sized.result.profit()));
+    for sized in &found {
+        info!(
+            "Route {:?} at block {}: in={} out={} profit={}",
+            sized.route.tokens(),
+            block,
+            TokenAmount::new(sized.result.amount_in(), token).to_decimal(),
+            TokenAmount::new(sized.result.amount_out(), token).to_decimal(),
+            sized.result.profit_decimal(token.decimals as u32)
+        );
+    }
+    Ok(found)
+}
+
+/// Runs `search` from every direction's token0 over the described pools of all directions
+/// sharing its provider.
+pub async fn search_directions(directions: &[ArbitrageDirection], settings: &RouteSettings) -> Vec<SizedRoute> {
+    let mut by_provider: BTreeMap<&str, (Vec<PoolDescriptor>, BTreeSet<Address>)> = BTreeMap::new();
+    for dir in directions {
+        let (pools, starts) = by_provider.entry(dir.provider.as_str()).or_default();
+        for pool in &dir.pools {
+            if !pools.iter().any(|p| p.address == pool.address) {
+                pools.push(pool.clone());
+            }
+        }
+        match dir.token0.parse() {
+            Ok(token0) => {
+                starts.insert(token0);
+            }
+            Err(_) => warn!("Direction {:?} has an invalid token0 {}", dir.path, dir.token0),
+        }
+    }
+
+    let mut found = Vec::new();
+    for (provider, (pools, starts)) in by_provider {
+        for start in starts {
+            match search(provider, &pools, start, settings.max_hops, settings.amount).await {
+                Ok(routes) => found.extend(routes),
+                Err(e) => error!("Route search from {:?} on {} failed: {e:#}", start, provider),
+            }
+        }
+    }
+    found
+}
+
+/// Every cycle of 2..=`max_hops` hops that starts and ends at `start`, using each pool at
+/// most once. Pools are taken as `(descriptor, snapshot)` so token pairs are known.
+pub fn find_cycles(pools: &[(PoolDescriptor, PoolSnapshot)], start: Address, max_hops: usize) -> Vec<Route> {
+    fn walk(
+        pools: &[(PoolDescriptor, PoolSnapshot)],
+        start: Address,
+        max_hops: usize,
+        token: Address,
+        hops: &mut Vec<Hop>,
+        used: &mut Vec<bool>,
+        routes: &mut Vec<Route>,
+    ) {
+        if hops.len() == max_hops {
+            return;
+        }
+        for (i, (descriptor, snapshot)) in pools.iter().enumerate() {
+            if used[i] {
+                continue;
+            }
+            let Some(next) = snapshot.other(token) else {
+                continue;
+            };
+
+            used[i] = true;
+            hops.push(Hop { pool: descriptor.clone(), token_in: token, token_out: next });
+            if next == start {
+                if hops.len() >= 2 {
+                    routes.push(Route { hops: hops.clone() });
+                }
+            } else {
+                walk(pools, start, max_hops, next, hops, used, routes);
+            }
+            hops.pop();
+            used[i] = false;
+        }
+    }
+
+    let mut routes = Vec::new();
+    walk(pools, start, max_hops, start, &mut Vec::new(), &mut vec![false; pools.len()], &mut routes);
+    routes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::{AmmKind, PoolVariant};
+    use crate::pools_abi::uniswap_v2_pair::{GetReservesCall, GetReservesReturn, Token0Call, Token1Call};
+    use crate::rpc::fixture::{eth_call_entry, FixtureEntry};
+    use crate::rpc::{FixtureClient, RpcClient};
+    use serde_json::json;
+
+    fn pool(n: u8, token0: Address, token1: Address, reserve0: u128, reserve1: u128) -> (PoolDescriptor, PoolSnapshot) {
+        let address = Address::repeat_byte(n);
+        (
+            PoolDescriptor { address, kind: AmmKind::V2, variant: PoolVariant::UniswapV2, dex: None, fee: 0.003 },
+            PoolSnapshot::V2 {
+                address,
+                token0,
+                token1,
+                reserve0: U256::from(reserve0),
+                reserve1: U256::from(reserve1),
+                fee: 0.003,
+            },
+        )
+    }
+
+    #[test]
+    fn triangular_cycle_is_found_and_simulated() {
+        let (a, b, c) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb), Address::repeat_byte(0xc));
+        // a→b at 2, b→c at 3, c→a at 0.2: a 20% round trip before fees and impact.
+        let pools = vec![
+            pool(1, a, b, 1_000_000_000, 2_000_000_000),
+            pool(2, b, c, 1_000_000_000, 3_000_000_000),
+            pool(3, a, c, 1_000_000_000, 5_000_000_000),
+        ];
+
+        let routes = find_cycles(&pools, a, 3);
+        assert_eq!(routes.len(), 2);
+        assert!(routes.iter().all(|r| r.is_cycle() && r.hops.len() == 3));
+
+        let snapshots: HashMap<_, _> = pools.iter().map(|(d, s)| (d.address, s.clone())).collect();
+        let forward = routes.iter().find(|r| r.tokens() == vec![a, b, c, a]).unwrap();
+        let result = forward.simulate(&snapshots, U256::from(1_000_000u64)).unwrap();
+        assert_eq!(result.amounts.len(), 4);
+        assert!(result.profit().unwrap() > U256::from(150_000u64), "{:?}", result);
+
+        let backward = routes.iter().find(|r| r.tokens() == vec![a, c, b, a]).unwrap();
+        assert!(backward.simulate(&snapshots, U256::from(1_000_000u64)).unwrap().profit().is_none());
+    }
+
+    #[test]
+    fn route_rejects_disconnected_hops() {
+        let (a, b, c) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb), Address::repeat_byte(0xc));
+        let (p1, _) = pool(1, a, b, 1, 1);
+        let (p2, _) = pool(2, a, c, 1, 1);
+        let hops = vec![
+            Hop { pool: p1, token_in: a, token_out: b },
+            Hop { pool: p2, token_in: a, token_out: c },
+        ];
+        assert!(Route::new(hops).is_err());
+    }
+
+    #[tokio::test]
+    async fn direction_routes_read_both_pools_at_the_pinned_block() {
+        const URL: &str = "fixture://route";
+        let (a, b) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb));
+        let descriptors: Vec<PoolDescriptor> = (1..=3).map(|n| pool(n, a, b, 0, 0).0).collect();
+        let dir = ArbitrageDirection {
+            pool_type: "V2".to_string(),
+            token0: format!("{:?}", a),
+            token1: format!("{:?}", b),
+            path: descriptors.iter().map(|d| format!("{:?}", d.address)).collect(),
+            roi: 0.0,
+            profit: 0.0,
+            priceDifference: 0.0,
+            pool_fee: None,
+            provider: URL.to_string(),
+            chain: None,
+            pools: descriptors,
+        };
+
+        let route = Route::from_direction(&dir).unwrap();
+        assert_eq!(route.tokens(), vec![a, b, a]);
+        assert_eq!((route.hops[0].pool.address, route.hops[1].pool.address), (Address::repeat_byte(2), Address::repeat_byte(3)));
+
+        // Only block 0x10 is recorded: an unpinned read would miss the fixture.
+        let mut entries = Vec::new();
+        for (n, reserve1) in [(2u8, 2_100_000_000u128), (3, 1_900_000_000)] {
+            let pair = Address::repeat_byte(n);
+            let reserves = GetReservesReturn { reserve_0: 1_000_000_000, reserve_1: reserve1, block_timestamp_last: 0 };
+            entries.push(eth_call_entry(pair, "0x10", GetReservesCall, reserves));
+            entries.push(eth_call_entry(pair, "0x10", Token0Call, a));
+            entries.push(eth_call_entry(pair, "0x10", Token1Call, b));
+        }
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        let snapshots = rpc::scoped(URL, client, route.fetch_snapshots_at(URL, 16u64.into())).await.unwrap();
+
+        let sized = best_amount(&route, &snapshots, U256::from(100_000_000u64)).unwrap().unwrap();
+        assert!(sized.profit().unwrap() > U256::zero());
+        assert!(sized.amount_in() < U256::from(100_000_000u64));
+    }
+
+    #[tokio::test]
+    async fn search_reads_pools_at_head_and_returns_profitable_cycles() {
+        use crate::pools_abi::erc20_abi::DecimalsCall;
+
+        const URL: &str = "fixture://route-search";
+        let (a, b, c) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb), Address::repeat_byte(0xc));
+        let pools = [(1u8, a, b, 2_000_000_000u128), (2, b, c, 3_000_000_000), (3, a, c, 5_000_000_000)];
+
+        let mut entries = vec![
+            FixtureEntry::new("eth_chainId", json!(null), json!("0x7a6a")),
+            FixtureEntry::new("eth_blockNumber", json!(null), json!("0x10")),
+            eth_call_entry(a, "latest", DecimalsCall, 6u8),
+        ];
+        for (n, token0, token1, reserve1) in pools {
+            let pair = Address::repeat_byte(n);
+            let reserves = GetReservesReturn { reserve_0: 1_000_000_000, reserve_1: reserve1, block_timestamp_last: 0 };
+            entries.push(eth_call_entry(pair, "0x10", GetReservesCall, reserves));
+            entries.push(eth_call_entry(pair, "0x10", Token0Call, token0));
+            entries.push(eth_call_entry(pair, "0x10", Token1Call, token1));
+        }
+        let descriptors: Vec<PoolDescriptor> = pools.iter().map(|(n, t0, t1, _)| pool(*n, *t0, *t1, 0, 0).0).collect();
+
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        let found = rpc::scoped(URL, client, search(URL, &descriptors, a, 3, 1.0)).await.unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].block, 16);
+        assert_eq!(found[0].route.tokens(), vec![a, b, c, a]);
+        assert!(found[0].result.profit().unwrap() > U256::zero());
+        assert!(found[0].result.amount_in() <= U256::from(1_000_000u64));
+    }
+}
@@ -0,0 +1,223 @@
+use crate::simulator::amm::{from_f64, to_f64, PoolSnapshot};
+use anyhow::{anyhow, Result};
+use ethers::types::{Address, U256};
+
+/// Input of `token` that maximizes `buy(sell(x)) - loan_cost·x` when selling `token` into
+/// `sell` and buying it back from `buy`: the point where both marginal prices meet net of
+/// fees and of what the loan costs, `loan_cost = 1` for a free one.
+///
+/// Two constant-product legs compose into one: with `(a1, b1)` the sell pool's reserves of
+/// `token` and the other token, `(b2, a2)` the buy pool's, and `γ = 1 - fee`,
+///
+///     out(x) = K·x / (D + M·x),  K = γ1·γ2·a2·b1,  D = a1·b2,  M = γ1·(b2 + γ2·b1)
+///
+/// and `out'(x) = loan_cost` gives `x* = (√(K·D/loan_cost) - D) / M`. V3 pools are plugged in with their
+/// virtual reserves, which is exact while both swaps stay inside the current tick; use
+/// `equalizing_amount_iterative` when they may not. Zero means there is no spread to take.
+pub fn equalizing_amount(sell: &PoolSnapshot, buy: &PoolSnapshot, token: Address, loan_cost: f64) -> Result<U256> {
+    let other = sell
+        .other(token)
+        .ok_or_else(|| anyhow!("Sell pool {:?} doesn't trade {:?}", sell.address(), token))?;
+    if buy.other(other) != Some(token) {
+        return Err(anyhow!("Buy pool {:?} doesn't trade {:?}/{:?}", buy.address(), other, token));
+    }
+
+    let (a1, b1) = (to_f64(sell.depth(token)?), to_f64(sell.depth(other)?));
+    let (b2, a2) = (to_f64(buy.depth(other)?), to_f64(buy.depth(token)?));
+    let (g1, g2) = (1.0 - sell.fee(sell.zero_for_one(token)?), 1.0 - buy.fee(buy.zero_for_one(other)?));
+
+    let k = g1 * g2 * a2 * b1;
+    let d = a1 * b2;
+    let m = g1 * (b2 + g2 * b1);
+    if m <= 0.0 {
+        return Err(anyhow!("Degenerate pools"));
+    }
+
+    Ok(from_f64(((k * d / loan_cost).sqrt() - d) / m))
+}
+
+/// Same optimum found by bisection on the slope of the round trip `buy(sell(x))`, for V3
+/// snapshots with `PoolTicks`, whose swaps cross initialized ticks and change liquidity on
+/// the way. `upper` bounds the search, e.g. the loan size.
+pub fn equalizing_amount_iterative(
+    sell: &PoolSnapshot,
+    buy: &PoolSnapshot,
+    token: Address,
+    upper: U256,
+    loan_cost: f64,
+) -> Result<U256> {
+    let other = sell
+        .other(token)
+        .ok_or_else(|| anyhow!("Sell pool {:?} doesn't trade {:?}", sell.address(), token))?;
+
+    let round_trip = |x: U256| -> Result<f64> { Ok(to_f64(buy.amount_out(other, sell.amount_out(token, x)?)?)) };
+
+    // out'(x) by central difference, with a step wide enough that integer rounding of the
+    // amounts doesn't swamp it: above `loan_cost` a bit more still pays.
+    let marginal = |x: U256| -> Result<f64> {
+        let step = (x.max(upper / 1_000) / 1_000).max(U256::one());
+        let (lo, hi) = (x.saturating_sub(step), x + step);
+        Ok((round_trip(hi)? - round_trip(lo)?) / to_f64(hi - lo))
+    };
+
+    if marginal(U256::zero())? <= loan_cost {
+        return Ok(U256::zero());
+    }
+    // Sizes the pools can't fill, e.g. past the fetched ticks, count as past the optimum.
+    if marginal(upper).is_ok_and(|m| m >= loan_cost) {
+        return Ok(upper);
+    }
+
+    let (mut lo, mut hi) = (U256::zero(), upper);
+    while hi - lo > U256::one() {
+        let mid = lo + (hi - lo) / 2;
+        if marginal(mid).is_ok_and(|m| m > loan_cost) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Ok(lo)
+}
+
+/// Loan size for a sell/buy pair of pools, capped at `upper`: the closed form while both
+/// legs are constant-product pairs or single-range V3 pools, where it is exact, and the
+/// bisection once a V3 leg carries ticks it may cross. `loan_cost` is what repaying each
+/// unit borrowed costs, flash fee included.
+pub fn optimal_amount(sell: &PoolSnapshot, buy: &PoolSnapshot, token: Address, upper: U256, loan_cost: f64) -> Result<U256> {
+    let crosses_ticks = |pool: &PoolSnapshot| matches!(pool, PoolSnapshot::V3 { ticks: Some(_), .. });
+    if crosses_ticks(sell) || crosses_ticks(buy) {
+        equalizing_amount_iterative(sell, buy, token, upper, loan_cost)
+    } else {
+        Ok(equalizing_amount(sell, buy, token, loan_cost)?.min(upper))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool_adapters::PoolState;
+
+    fn v2(n: u8, reserve0: u128, reserve1: u128) -> PoolSnapshot {
+        PoolSnapshot::V2 {
+            address: Address::repeat_byte(n),
+            token0: Address::repeat_byte(0xa),
+            token1: Address::repeat_byte(0xb),
+            reserve0: U256::from(reserve0),
+            reserve1: U256::from(reserve1),
+            fee: 0.003,
+        }
+    }
+
+    fn profit(sell: &PoolSnapshot, buy: &PoolSnapshot, x: U256) -> f64 {
+        let a = Address::repeat_byte(0xa);
+        let bought = sell.amount_out(a, x).unwrap();
+        to_f64(buy.amount_out(Address::repeat_byte(0xb), bought).unwrap()) - to_f64(x)
+    }
+
+    #[test]
+    fn v2_closed_form_is_the_profit_maximum() {
+        // Sell token a where it's worth 2.05, buy it back where it costs 2.
+        let (sell, buy) = (v2(1, 1_000_000_000_000, 2_050_000_000_000), v2(2, 1_000_000_000_000, 2_000_000_000_000));
+        let a = Address::repeat_byte(0xa);
+
+        let x = equalizing_amount(&sell, &buy, a, 1.0).unwrap();
+        assert!(x > U256::zero());
+        let best = profit(&sell, &buy, x);
+        for dx in [x / 10, x / 100] {
+            assert!(profit(&sell, &buy, x + dx) <= best);
+            assert!(profit(&sell, &buy, x - dx) <= best);
+        }
+
+        let iterative = equalizing_amount_iterative(&sell, &buy, a, x * 4, 1.0).unwrap();
+        let gap = if iterative > x { iterative - x } else { x - iterative };
+        assert!(gap < x / 1_000, "closed={x} iterative={iterative}");
+
+        assert_eq!(equalizing_amount(&buy, &sell, a, 1.0).unwrap(), U256::zero());
+
+        assert_eq!(optimal_amount(&sell, &buy, a, x * 4, 1.0).unwrap(), x);
+        assert_eq!(optimal_amount(&sell, &buy, a, x / 2, 1.0).unwrap(), x / 2);
+    }
+
+    #[test]
+    fn a_loan_fee_shrinks_the_optimum_and_can_close_the_spread() {
+        let (sell, buy) = (v2(1, 1_000_000_000_000, 2_050_000_000_000), v2(2, 1_000_000_000_000, 2_000_000_000_000));
+        let a = Address::repeat_byte(0xa);
+        let cost = 1.0 + 3.0 / 997.0;
+        let net = |x: U256| profit(&sell, &buy, x) - to_f64(x) * (cost - 1.0);
+
+        let free = equalizing_amount(&sell, &buy, a, 1.0).unwrap();
+        let x = equalizing_amount(&sell, &buy, a, cost).unwrap();
+        assert!(x < free, "with fee={x} free={free}");
+        assert!(net(x) >= net(free));
+        for dx in [x / 10, x / 100] {
+            assert!(net(x + dx) <= net(x));
+            assert!(net(x - dx) <= net(x));
+        }
+        let iterative = equalizing_amount_iterative(&sell, &buy, a, free * 4, cost).unwrap();
+        let gap = if iterative > x { iterative - x } else { x - iterative };
+        assert!(gap < x / 1_000, "closed={x} iterative={iterative}");
+
+        // A 0.75% gap pays both 0.3% swap fees but not the flash fee on top.
+        let thin = v2(1, 1_000_000_000_000, 2_015_000_000_000);
+        assert!(equalizing_amount(&thin, &buy, a, 1.0).unwrap() > U256::zero());
+        assert_eq!(optimal_amount(&thin, &buy, a, free, cost).unwrap(), U256::zero());
+    }
+
+    #[test]
+    fn v3_uses_virtual_reserves() {
+        let a = Address::repeat_byte(0xa);
+        let sell = PoolSnapshot::V3 {
+            address: Address::repeat_byte(1),
+            token0: a,
+            token1: Address::repeat_byte(0xb),
+            // price 2500 with 18/6 decimals, as in the PCS fixture
+            state: PoolState {
+                sqrt_price_x96: U256::from_dec_str("3961408125713216879677197").unwrap(),
+                tick: 0,
+                liquidity: 1_000_000_000_000_000_000,
+                fee: None,
+            },
+            fee: 0.0005,
+            ticks: None,
+        };
+        let buy = v2(2, 4_000_000_000_000_000_000_000, 9_800_000_000_000);
+
+        let x = equalizing_amount(&sell, &buy, a, 1.0).unwrap();
+        let iterative = equalizing_amount_iterative(&sell, &buy, a, x * 4, 1.0).unwrap();
+        let gap = if iterative > x { iterative - x } else { x - iterative };
+        assert!(gap < x / 100, "closed={x} iterative={iterative}");
+    }
+
+    #[test]
+    fn ticks_switch_sizing_to_the_bisection() {
+        use crate::ticks::{PoolTicks, PopulatedTick};
+
+        let (a, l) = (Address::repeat_byte(0xa), 1_000_000_000_000_000_000_000u128);
+        // Price 1 at tick 0; all liquidity ends at tick -20, so selling a runs dry early.
+        let ticks = PoolTicks::new(10, 100, -1..=0, vec![PopulatedTick { tick: -20, liquidity_net: l as i128, liquidity_gross: l }]);
+        let single_range = PoolSnapshot::V3 {
+            address: Address::repeat_byte(1),
+            token0: a,
+            token1: Address::repeat_byte(0xb),
+            state: PoolState { sqrt_price_x96: U256::one() << 96, tick: 0, liquidity: l, fee: None },
+            fee: 0.0005,
+            ticks: None,
+        };
+        let PoolSnapshot::V3 { address, token0, token1, state, fee, .. } = single_range.clone() else {
+            unreachable!()
+        };
+        let with_ticks = PoolSnapshot::V3 { address, token0, token1, state, fee, ticks: Some(ticks) };
+        let buy = v2(2, 1_000_000_000_000_000_000_000, 980_000_000_000_000_000_000);
+        let upper = U256::exp10(21);
+
+        let closed = optimal_amount(&single_range, &buy, a, upper, 1.0).unwrap();
+        assert_eq!(closed, equalizing_amount(&single_range, &buy, a, 1.0).unwrap());
+
+        // The closed form wants more than the range holds; the bisection stops inside it.
+        let crossing = optimal_amount(&with_ticks, &buy, a, upper, 1.0).unwrap();
+        assert!(crossing < closed, "crossing={crossing} closed={closed}");
+        assert!(with_ticks.amount_out(a, crossing).is_ok());
+        assert!(with_ticks.amount_out(a, closed).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::{anyhow, Result};
+use ethers::types::{U256, U512};
+
+/// `FullMath.mulDiv`: `⌊a·b / denominator⌋` with a 512-bit intermediate.
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Result<U256> {
+    if denominator.is_zero() {
+        return Err(anyhow!("mul_div by zero"));
+    }
+    U256::try_from(a.full_mul(b) / U512::from(denominator)).map_err(|_| anyhow!("mul_div overflow"))
+}
+
+/// `FullMath.mulDivRoundingUp`: `⌈a·b / denominator⌉`.
+pub fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> Result<U256> {
+    let result = mul_div(a, b, denominator)?;
+    if (a.full_mul(b) % U512::from(denominator)).is_zero() {
+        return Ok(result);
+    }
+    if result == U256::MAX {
+        return Err(anyhow!("mul_div overflow"));
+    }
+    Ok(result + 1)
+}
+
+/// `UnsafeMath.divRoundingUp`. Division by zero yields zero, as in the EVM.
+pub fn div_rounding_up(x: U256, y: U256) -> U256 {
+    if y.is_zero() {
+        return U256::zero();
+    }
+    let quotient = x / y;
+    if (x % y).is_zero() {
+        quotient
+    } else {
+        quotient + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q128() -> U256 {
+        U256::one() << 128
+    }
+
+    // Cases from v3-core `test/FullMath.spec.ts`.
+    #[test]
+    fn mul_div_matches_reference() {
+        let q = q128();
+        assert!(mul_div(q, U256::from(5u8), U256::zero()).is_err());
+        assert!(mul_div(q, q, U256::one()).is_err());
+        assert!(mul_div(U256::MAX, U256::MAX, U256::MAX - 1).is_err());
+        assert_eq!(mul_div(U256::MAX, U256::MAX, U256::MAX).unwrap(), U256::MAX);
+
+        assert_eq!(mul_div(q, q * 50 / 100, q * 150 / 100).unwrap(), q / 3);
+        assert_eq!(mul_div(q, q * 35, q * 8).unwrap(), q * 4375 / 1000);
+        assert_eq!(mul_div(q, q * 1000, q * 3000).unwrap(), q / 3);
+    }
+
+    #[test]
+    fn mul_div_rounding_up_matches_reference() {
+        let q = q128();
+        assert!(mul_div_rounding_up(q, U256::from(5u8), U256::zero()).is_err());
+        assert!(mul_div_rounding_up(U256::MAX, U256::MAX, U256::MAX - 1).is_err());
+        // 535006138814359 · 432862656469423142931042426214547535783388063929571229938474969 /
+        // 2 overflows only after rounding up.
+        assert!(mul_div_rounding_up(
+            U256::from(535_006_138_814_359u64),
+            U256::from_dec_str("432862656469423142931042426214547535783388063929571229938474969").unwrap(),
+            U256::from(2u8)
+        )
+        .is_err());
+        assert_eq!(mul_div_rounding_up(U256::MAX, U256::MAX, U256::MAX).unwrap(), U256::MAX);
+
+        assert_eq!(mul_div_rounding_up(q, q * 50 / 100, q * 150 / 100).unwrap(), q / 3 + 1);
+        assert_eq!(mul_div_rounding_up(q, q * 35, q * 8).unwrap(), q * 4375 / 1000);
+        assert_eq!(mul_div_rounding_up(q, q * 1000, q * 3000).unwrap(), q / 3 + 1);
+
+        assert_eq!(div_rounding_up(U256::from(7u8), U256::from(2u8)), U256::from(4u8));
+        assert_eq!(div_rounding_up(U256::from(8u8), U256::from(2u8)), U256::from(4u8));
+        assert_eq!(div_rounding_up(U256::one(), U256::zero()), U256::zero());
+    }
+}
@@ -0,0 +1,28 @@
+use anyhow::{anyhow, Result};
+
+/// `LiquidityMath.addDelta`: applies a signed liquidity change, failing on underflow
+/// (`LS`) or overflow (`LA`) like the pool does when crossing a tick.
+pub fn add_delta(x: u128, y: i128) -> Result<u128> {
+    if y < 0 {
+        x.checked_sub(y.unsigned_abs()).ok_or_else(|| anyhow!("LS: liquidity {} - {}", x, y.unsigned_abs()))
+    } else {
+        x.checked_add(y as u128).ok_or_else(|| anyhow!("LA: liquidity {} + {}", x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cases from v3-core `test/LiquidityMath.spec.ts`.
+    #[test]
+    fn add_delta_matches_reference() {
+        assert_eq!(add_delta(1, 0).unwrap(), 1);
+        assert_eq!(add_delta(1, -1).unwrap(), 0);
+        assert_eq!(add_delta(1, 1).unwrap(), 2);
+        assert!(add_delta(u128::MAX - 14, 15).is_err());
+        assert!(add_delta(0, -1).is_err());
+        assert!(add_delta(3, -4).is_err());
+        assert!(add_delta(0, i128::MIN).is_err());
+    }
+}
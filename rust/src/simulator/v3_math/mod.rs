@@ -0,0 +1,8 @@
+// Ports of Uniswap V3's math libraries (v3-core `contracts/libraries`). Every function
+// keeps its Solidity name in snake_case, rounds the same way and fails where the
+// reference reverts, so quotes built on them match the pool to the wei.
+pub mod full_math;
+pub mod liquidity_math;
+pub mod sqrt_price_math;
+pub mod swap_math;
+pub mod tick_math;
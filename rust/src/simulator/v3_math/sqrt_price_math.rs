@@ -0,0 +1,230 @@
+use crate::simulator::v3_math::full_math::{div_rounding_up, mul_div, mul_div_rounding_up};
+use anyhow::{anyhow, Result};
+use ethers::types::U256;
+
+fn q96() -> U256 {
+    U256::one() << 96
+}
+
+fn max_u160() -> U256 {
+    (U256::one() << 160) - 1
+}
+
+fn to_u160(v: U256) -> Result<U256> {
+    if v > max_u160() {
+        return Err(anyhow!("sqrt price overflows uint160"));
+    }
+    Ok(v)
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromAmount0RoundingUp`: the price after adding or
+/// removing `amount` of token0, rounded up so the pool never gives away too much.
+pub fn get_next_sqrt_price_from_amount0_rounding_up(
+    sqrt_p_x96: U256,
+    liquidity: u128,
+    amount: U256,
+    add: bool,
+) -> Result<U256> {
+    if amount.is_zero() {
+        return Ok(sqrt_p_x96);
+    }
+    let numerator1 = U256::from(liquidity) << 96;
+
+    if add {
+        if let Some(product) = amount.checked_mul(sqrt_p_x96) {
+            if let Some(denominator) = numerator1.checked_add(product) {
+                return mul_div_rounding_up(numerator1, sqrt_p_x96, denominator);
+            }
+        }
+        let denominator = (numerator1 / sqrt_p_x96)
+            .checked_add(amount)
+            .ok_or_else(|| anyhow!("sqrt price denominator overflows"))?;
+        Ok(div_rounding_up(numerator1, denominator))
+    } else {
+        let product = amount
+            .checked_mul(sqrt_p_x96)
+            .filter(|p| numerator1 > *p)
+            .ok_or_else(|| anyhow!("Not enough token0 liquidity for output {}", amount))?;
+        to_u160(mul_div_rounding_up(numerator1, sqrt_p_x96, numerator1 - product)?)
+    }
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromAmount1RoundingDown`: the price after adding or
+/// removing `amount` of token1, rounded down.
+pub fn get_next_sqrt_price_from_amount1_rounding_down(
+    sqrt_p_x96: U256,
+    liquidity: u128,
+    amount: U256,
+    add: bool,
+) -> Result<U256> {
+    let liquidity = U256::from(liquidity);
+    if add {
+        let quotient = if amount <= max_u160() {
+            (amount << 96) / liquidity
+        } else {
+            mul_div(amount, q96(), liquidity)?
+        };
+        to_u160(
+            sqrt_p_x96
+                .checked_add(quotient)
+                .ok_or_else(|| anyhow!("sqrt price overflows"))?,
+        )
+    } else {
+        let quotient = if amount <= max_u160() {
+            div_rounding_up(amount << 96, liquidity)
+        } else {
+            mul_div_rounding_up(amount, q96(), liquidity)?
+        };
+        if sqrt_p_x96 <= quotient {
+            return Err(anyhow!("Not enough token1 liquidity for output {}", amount));
+        }
+        Ok(sqrt_p_x96 - quotient)
+    }
+}
+
+fn check_pool(sqrt_p_x96: U256, liquidity: u128) -> Result<()> {
+    if sqrt_p_x96.is_zero() {
+        return Err(anyhow!("Invalid sqrt price"));
+    }
+    if liquidity == 0 {
+        return Err(anyhow!("Pool has no liquidity"));
+    }
+    Ok(())
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromInput`: price after swapping in `amount_in`, which
+/// is token0 when `zero_for_one`. Rounds so the price doesn't pass the target.
+pub fn get_next_sqrt_price_from_input(
+    sqrt_p_x96: U256,
+    liquidity: u128,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Result<U256> {
+    check_pool(sqrt_p_x96, liquidity)?;
+    if zero_for_one {
+        get_next_sqrt_price_from_amount0_rounding_up(sqrt_p_x96, liquidity, amount_in, true)
+    } else {
+        get_next_sqrt_price_from_amount1_rounding_down(sqrt_p_x96, liquidity, amount_in, true)
+    }
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromOutput`: price after taking out `amount_out`, which
+/// is token1 when `zero_for_one`.
+pub fn get_next_sqrt_price_from_output(
+    sqrt_p_x96: U256,
+    liquidity: u128,
+    amount_out: U256,
+    zero_for_one: bool,
+) -> Result<U256> {
+    check_pool(sqrt_p_x96, liquidity)?;
+    if zero_for_one {
+        get_next_sqrt_price_from_amount1_rounding_down(sqrt_p_x96, liquidity, amount_out, false)
+    } else {
+        get_next_sqrt_price_from_amount0_rounding_up(sqrt_p_x96, liquidity, amount_out, false)
+    }
+}
+
+/// `SqrtPriceMath.getAmount0Delta`: `L·(√b - √a) / (√a·√b)` token0 between two prices.
+pub fn get_amount0_delta(sqrt_ratio_a_x96: U256, sqrt_ratio_b_x96: U256, liquidity: u128, round_up: bool) -> Result<U256> {
+    let (lower, upper) = if sqrt_ratio_a_x96 > sqrt_ratio_b_x96 {
+        (sqrt_ratio_b_x96, sqrt_ratio_a_x96)
+    } else {
+        (sqrt_ratio_a_x96, sqrt_ratio_b_x96)
+    };
+    if lower.is_zero() {
+        return Err(anyhow!("Invalid sqrt price"));
+    }
+    let numerator1 = U256::from(liquidity) << 96;
+    let numerator2 = upper - lower;
+
+    if round_up {
+        Ok(div_rounding_up(mul_div_rounding_up(numerator1, numerator2, upper)?, lower))
+    } else {
+        Ok(mul_div(numerator1, numerator2, upper)? / lower)
+    }
+}
+
+/// `SqrtPriceMath.getAmount1Delta`: `L·(√b - √a)` token1 between two prices.
+pub fn get_amount1_delta(sqrt_ratio_a_x96: U256, sqrt_ratio_b_x96: U256, liquidity: u128, round_up: bool) -> Result<U256> {
+    let (lower, upper) = if sqrt_ratio_a_x96 > sqrt_ratio_b_x96 {
+        (sqrt_ratio_b_x96, sqrt_ratio_a_x96)
+    } else {
+        (sqrt_ratio_a_x96, sqrt_ratio_b_x96)
+    };
+    if round_up {
+        mul_div_rounding_up(U256::from(liquidity), upper - lower, q96())
+    } else {
+        mul_div(U256::from(liquidity), upper - lower, q96())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> U256 {
+        U256::from_dec_str(s).unwrap()
+    }
+
+    /// `encodePriceSqrt(121, 100)` = 1.1 · 2^96.
+    fn price_121_100() -> U256 {
+        dec("87150978765690771352898345369")
+    }
+
+    const E18: u128 = 1_000_000_000_000_000_000;
+
+    // Cases from v3-core `test/SqrtPriceMath.spec.ts`.
+    #[test]
+    fn next_sqrt_price_from_input_matches_reference() {
+        let one = q96();
+        assert!(get_next_sqrt_price_from_input(U256::zero(), 0, U256::exp10(17), false).is_err());
+        assert!(get_next_sqrt_price_from_input(one, 0, U256::exp10(17), true).is_err());
+        assert!(get_next_sqrt_price_from_input(max_u160(), 1024, U256::from(1024u64), false).is_err());
+
+        assert_eq!(get_next_sqrt_price_from_input(one, E18, U256::zero(), true).unwrap(), one);
+        assert_eq!(get_next_sqrt_price_from_input(one, E18, U256::exp10(17), false).unwrap(), price_121_100());
+        assert_eq!(
+            get_next_sqrt_price_from_input(one, E18, U256::exp10(17), true).unwrap(),
+            dec("72025602285694852357767227579")
+        );
+        assert_eq!(
+            get_next_sqrt_price_from_input(one, 10 * E18, U256::one() << 100, true).unwrap(),
+            dec("624999999995069620")
+        );
+        // Any input is absorbed without the price reaching zero.
+        assert_eq!(get_next_sqrt_price_from_input(U256::one(), 1, U256::one() << 255, true).unwrap(), U256::one());
+    }
+
+    #[test]
+    fn next_sqrt_price_from_output_matches_reference() {
+        let one = q96();
+        assert!(get_next_sqrt_price_from_output(one, 0, U256::one(), true).is_err());
+        // Exactly the virtual reserve of token0 can't be taken out.
+        assert!(get_next_sqrt_price_from_output(dec("20282409603651670423947251286016"), 1024, U256::from(4u8), false).is_err());
+        assert!(get_next_sqrt_price_from_output(dec("20282409603651670423947251286016"), 1024, U256::from(262144u64), true).is_err());
+
+        assert_eq!(
+            get_next_sqrt_price_from_output(one, E18, U256::exp10(17), true).unwrap(),
+            dec("71305346262837903834189555302")
+        );
+        assert_eq!(
+            get_next_sqrt_price_from_output(one, E18, U256::exp10(17), false).unwrap(),
+            dec("88031291682515930659493278152")
+        );
+    }
+
+    #[test]
+    fn amount_deltas_match_reference() {
+        let one = q96();
+        assert_eq!(get_amount0_delta(one, one, 0, true).unwrap(), U256::zero());
+        assert_eq!(get_amount1_delta(one, price_121_100(), 0, true).unwrap(), U256::zero());
+
+        let up0 = get_amount0_delta(one, price_121_100(), E18, true).unwrap();
+        assert_eq!(up0, U256::from(90909090909090910u64));
+        assert_eq!(get_amount0_delta(price_121_100(), one, E18, false).unwrap(), up0 - 1);
+
+        let up1 = get_amount1_delta(one, price_121_100(), E18, true).unwrap();
+        assert_eq!(up1, U256::exp10(17));
+        assert_eq!(get_amount1_delta(one, price_121_100(), E18, false).unwrap(), up1 - 1);
+    }
+}
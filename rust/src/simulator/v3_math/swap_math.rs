@@ -0,0 +1,163 @@
+use crate::simulator::v3_math::full_math::{mul_div, mul_div_rounding_up};
+use crate::simulator::v3_math::sqrt_price_math::{
+    get_amount0_delta, get_amount1_delta, get_next_sqrt_price_from_input, get_next_sqrt_price_from_output,
+};
+use anyhow::Result;
+use ethers::types::{I256, U256};
+
+/// Result of one `computeSwapStep`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_ratio_next_x96: U256,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub fee_amount: U256,
+}
+
+/// `SwapMath.computeSwapStep`: swaps as much of `amount_remaining` as one range of
+/// constant liquidity allows, stopping at `sqrt_ratio_target_x96`. A non-negative
+/// `amount_remaining` is an exact input, a negative one an exact output.
+pub fn compute_swap_step(
+    sqrt_ratio_current_x96: U256,
+    sqrt_ratio_target_x96: U256,
+    liquidity: u128,
+    amount_remaining: I256,
+    fee_pips: u32,
+) -> Result<SwapStep> {
+    let zero_for_one = sqrt_ratio_current_x96 >= sqrt_ratio_target_x96;
+    let exact_in = !amount_remaining.is_negative();
+    let remaining = amount_remaining.unsigned_abs();
+    let pips = U256::from(1_000_000u64);
+    let fee = U256::from(fee_pips);
+
+    let mut amount_in = U256::zero();
+    let mut amount_out = U256::zero();
+    let sqrt_ratio_next_x96;
+
+    if exact_in {
+        let remaining_less_fee = mul_div(remaining, pips - fee, pips)?;
+        amount_in = if zero_for_one {
+            get_amount0_delta(sqrt_ratio_target_x96, sqrt_ratio_current_x96, liquidity, true)?
+        } else {
+            get_amount1_delta(sqrt_ratio_current_x96, sqrt_ratio_target_x96, liquidity, true)?
+        };
+        sqrt_ratio_next_x96 = if remaining_less_fee >= amount_in {
+            sqrt_ratio_target_x96
+        } else {
+            get_next_sqrt_price_from_input(sqrt_ratio_current_x96, liquidity, remaining_less_fee, zero_for_one)?
+        };
+    } else {
+        amount_out = if zero_for_one {
+            get_amount1_delta(sqrt_ratio_target_x96, sqrt_ratio_current_x96, liquidity, false)?
+        } else {
+            get_amount0_delta(sqrt_ratio_current_x96, sqrt_ratio_target_x96, liquidity, false)?
+        };
+        sqrt_ratio_next_x96 = if remaining >= amount_out {
+            sqrt_ratio_target_x96
+        } else {
+            get_next_sqrt_price_from_output(sqrt_ratio_current_x96, liquidity, remaining, zero_for_one)?
+        };
+    }
+
+    let max = sqrt_ratio_target_x96 == sqrt_ratio_next_x96;
+
+    if zero_for_one {
+        if !max || !exact_in {
+            amount_in = get_amount0_delta(sqrt_ratio_next_x96, sqrt_ratio_current_x96, liquidity, true)?;
+        }
+        if !max || exact_in {
+            amount_out = get_amount1_delta(sqrt_ratio_next_x96, sqrt_ratio_current_x96, liquidity, false)?;
+        }
+    } else {
+        if !max || !exact_in {
+            amount_in = get_amount1_delta(sqrt_ratio_current_x96, sqrt_ratio_next_x96, liquidity, true)?;
+        }
+        if !max || exact_in {
+            amount_out = get_amount0_delta(sqrt_ratio_current_x96, sqrt_ratio_next_x96, liquidity, false)?;
+        }
+    }
+
+    // The output can't exceed what was asked for.
+    if !exact_in && amount_out > remaining {
+        amount_out = remaining;
+    }
+
+    let fee_amount = if exact_in && sqrt_ratio_next_x96 != sqrt_ratio_target_x96 {
+        // Didn't reach the target, so the rest of the input is the fee.
+        remaining - amount_in
+    } else {
+        mul_div_rounding_up(amount_in, fee, pips - fee)?
+    };
+
+    Ok(SwapStep { sqrt_ratio_next_x96, amount_in, amount_out, fee_amount })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> U256 {
+        U256::from_dec_str(s).unwrap()
+    }
+
+    fn step(current: U256, target: U256, liquidity: u128, remaining: i128, fee: u32) -> SwapStep {
+        compute_swap_step(current, target, liquidity, I256::from(remaining), fee).unwrap()
+    }
+
+    // Cases from v3-core `test/SwapMath.spec.ts`.
+    #[test]
+    fn capped_at_price_target() {
+        let (one, target) = (U256::one() << 96, dec("79623317895830914510639640423"));
+        let liquidity = 2_000_000_000_000_000_000;
+
+        for remaining in [1_000_000_000_000_000_000i128, -1_000_000_000_000_000_000] {
+            let s = step(one, target, liquidity, remaining, 600);
+            assert_eq!(s.sqrt_ratio_next_x96, target);
+            assert_eq!(s.amount_in, U256::from(9975124224178055u64));
+            assert_eq!(s.amount_out, U256::from(9925619580021728u64));
+            assert_eq!(s.fee_amount, U256::from(5988667735148u64));
+        }
+    }
+
+    #[test]
+    fn fully_spent_before_price_target() {
+        let one = U256::one() << 96;
+        let liquidity = 2_000_000_000_000_000_000;
+
+        let s = step(one, dec("250541448375047931186413801569"), liquidity, 1_000_000_000_000_000_000, 600);
+        assert_eq!(s.amount_in, U256::from(999400000000000000u64));
+        assert_eq!(s.fee_amount, U256::from(600000000000000u64));
+        assert_eq!(s.amount_out, U256::from(666399946655997866u64));
+        assert_eq!(s.amount_in + s.fee_amount, U256::exp10(18));
+
+        let s = step(one, dec("792281625142643375935439503360"), liquidity, -1_000_000_000_000_000_000, 600);
+        assert_eq!(s.amount_in, U256::from(2000000000000000000u64));
+        assert_eq!(s.fee_amount, U256::from(1200720432259356u64));
+        assert_eq!(s.amount_out, U256::exp10(18));
+    }
+
+    #[test]
+    fn edge_cases() {
+        // Output capped at the requested amount.
+        let s = step(
+            dec("417332158212080721273783715441582"),
+            dec("1452870262520218020823638996"),
+            159344665391607089467575320103,
+            -1,
+            1,
+        );
+        assert_eq!((s.amount_in, s.amount_out, s.fee_amount), (U256::one(), U256::one(), U256::one()));
+        assert_eq!(s.sqrt_ratio_next_x96, dec("417332158212080721273783715441581"));
+
+        // Target price of 1 takes only part of the input.
+        let s = step(U256::from(2u8), U256::one(), 1, 3915081100057732413702495386755767, 1);
+        assert_eq!(s.amount_in, dec("39614081257132168796771975168"));
+        assert_eq!(s.fee_amount, dec("39614120871253040049813"));
+        assert_eq!((s.amount_out, s.sqrt_ratio_next_x96), (U256::zero(), U256::one()));
+
+        // Input too small to move the price is taken entirely as fee.
+        let s = step(U256::from(2413u64), dec("79887613182836312"), 1985041575832132834610021537970, 10, 1872);
+        assert_eq!((s.amount_in, s.amount_out, s.fee_amount), (U256::zero(), U256::zero(), U256::from(10u8)));
+        assert_eq!(s.sqrt_ratio_next_x96, U256::from(2413u64));
+    }
+}
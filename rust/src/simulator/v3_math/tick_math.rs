@@ -0,0 +1,160 @@
+use anyhow::{anyhow, Result};
+use ethers::types::U256;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+/// Lowest tick whose price fits a `sqrtPriceX96`: `log_{1.0001} 2^-128`.
+pub const MIN_TICK: i32 = -887272;
+pub const MAX_TICK: i32 = -MIN_TICK;
+
+/// `get_sqrt_ratio_at_tick(MIN_TICK)`.
+pub const MIN_SQRT_RATIO: U256 = U256([4295128739, 0, 0, 0]);
+/// `get_sqrt_ratio_at_tick(MAX_TICK)`, 1461446703485210103287273052203988822378723970342.
+pub const MAX_SQRT_RATIO: U256 = U256([0x5d951d5263988d26, 0xefd1fc6a50648849, 0xfffd8963, 0]);
+
+/// `1 / √1.0001^(2^i)` as Q128.128 for bits 1 through 19 of the absolute tick.
+const RATIOS: [u128; 19] = [
+    0xfff97272373d413259a46990580e213a,
+    0xfff2e50f5f656932ef12357cf3c7fdcc,
+    0xffe5caca7e10e4e61c3624eaa0941cd0,
+    0xffcb9843d60f6159c9db58835c926644,
+    0xff973b41fa98c081472e6896dfb254c0,
+    0xff2ea16466c96a3843ec78b326b52861,
+    0xfe5dee046a99a2a811c461f1969c3053,
+    0xfcbe86c7900a88aedcffc83b479aa3a4,
+    0xf987a7253ac413176f2b074cf7815e54,
+    0xf3392b0822b70005940c7a398e4b70f3,
+    0xe7159475a2c29b7443b29c7fa6e889d9,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e5,
+    0x70d869a156d2a1b890bb3df62baf32f7,
+    0x31be135f97d08fd981231505542fcfa6,
+    0x9aa508b5b7a84e1c677de54f3e99bc9,
+    0x5d6af8dedb81196699c329225ee604,
+    0x2216e584f5fa1ea926041bedfe98,
+    0x48a170391f7dc42444e8fa2,
+];
+
+/// `TickMath.getSqrtRatioAtTick`: `√1.0001^tick · 2^96`, rounded up.
+pub fn get_sqrt_ratio_at_tick(tick: i32) -> Result<U256> {
+    let abs_tick = tick.unsigned_abs();
+    if abs_tick > MAX_TICK as u32 {
+        return Err(anyhow!("T: tick {} out of range", tick));
+    }
+
+    let mut ratio = if abs_tick & 0x1 != 0 {
+        U256::from(0xfffcb933bd6fad37aa2d162d1a594001u128)
+    } else {
+        U256::one() << 128
+    };
+    for (i, r) in RATIOS.iter().enumerate() {
+        if abs_tick & (0x2 << i) != 0 {
+            ratio = (ratio * U256::from(*r)) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+
+    // Q128.128 to Q64.96, rounding up so the result round-trips through `get_tick_at_sqrt_ratio`.
+    let shifted = ratio >> 32;
+    if (ratio & U256::from(u32::MAX)).is_zero() {
+        Ok(shifted)
+    } else {
+        Ok(shifted + 1)
+    }
+}
+
+/// `TickMath.getTickAtSqrtRatio`: the greatest tick whose sqrt ratio is at most
+/// `sqrt_price_x96`, via a fixed-point log2.
+pub fn get_tick_at_sqrt_ratio(sqrt_price_x96: U256) -> Result<i32> {
+    if sqrt_price_x96 < MIN_SQRT_RATIO || sqrt_price_x96 >= MAX_SQRT_RATIO {
+        return Err(anyhow!("R: sqrt price {} out of range", sqrt_price_x96));
+    }
+    let ratio = sqrt_price_x96 << 32;
+
+    let msb = ratio.bits() - 1;
+    let mut r = if msb >= 128 { ratio >> (msb - 127) } else { ratio << (127 - msb) };
+
+    // Integer part of log2(ratio) in Q64.64, then 14 fractional bits by repeated squaring.
+    let mut log_2: i128 = (msb as i128 - 128) << 64;
+    for i in 0..14 {
+        r = (r * r) >> 127;
+        let f = r >> 128;
+        log_2 |= (f.as_u128() as i128) << (63 - i);
+        r >>= f.as_usize();
+    }
+
+    // log_√1.0001(ratio) in Q128.128, with error bounds that pin the tick to one of two.
+    let log_sqrt10001 = BigInt::from(log_2) * BigInt::from(255738958999603826347141u128);
+    let tick_low = ((&log_sqrt10001 - BigInt::from(3402992956809132418596140100660247210u128)) >> 128usize)
+        .to_i32()
+        .ok_or_else(|| anyhow!("tick out of range"))?;
+    let tick_high = ((&log_sqrt10001 + BigInt::from(291339464771989622907027621153398088495u128)) >> 128usize)
+        .to_i32()
+        .ok_or_else(|| anyhow!("tick out of range"))?;
+
+    if tick_low == tick_high {
+        Ok(tick_low)
+    } else if get_sqrt_ratio_at_tick(tick_high)? <= sqrt_price_x96 {
+        Ok(tick_high)
+    } else {
+        Ok(tick_low)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> U256 {
+        U256::from_dec_str(s).unwrap()
+    }
+
+    // Cases from v3-core `test/TickMath.spec.ts` and its snapshot.
+    #[test]
+    fn sqrt_ratio_at_tick_matches_reference() {
+        assert!(get_sqrt_ratio_at_tick(MIN_TICK - 1).is_err());
+        assert!(get_sqrt_ratio_at_tick(MAX_TICK + 1).is_err());
+
+        assert_eq!(get_sqrt_ratio_at_tick(MIN_TICK).unwrap(), MIN_SQRT_RATIO);
+        assert_eq!(get_sqrt_ratio_at_tick(MIN_TICK + 1).unwrap(), U256::from(4295343490u64));
+        assert_eq!(get_sqrt_ratio_at_tick(MAX_TICK - 1).unwrap(), dec("1461373636630004318706518188784493106690254656249"));
+        assert_eq!(get_sqrt_ratio_at_tick(MAX_TICK).unwrap(), dec("1461446703485210103287273052203988822378723970342"));
+        assert_eq!(get_sqrt_ratio_at_tick(0).unwrap(), U256::one() << 96);
+
+        for (tick, expected) in [
+            (50, "79426470787362580746886972461"),
+            (-50, "79030349367926598376800521322"),
+            (100, "79625275426524748796330556128"),
+            (-100, "78833030112140176575862854579"),
+            (1000, "83290069058676223003182343270"),
+            (-1000, "75364347830767020784054125655"),
+            (50000, "965075977353221155028623082916"),
+            (250000, "21246587762933397357449903968194344"),
+            (738203, "847134979253254120489401328389043031315994541"),
+            (-738203, "7409801140451"),
+        ] {
+            assert_eq!(get_sqrt_ratio_at_tick(tick).unwrap(), dec(expected), "tick {tick}");
+        }
+    }
+
+    #[test]
+    fn tick_at_sqrt_ratio_matches_reference() {
+        assert!(get_tick_at_sqrt_ratio(MIN_SQRT_RATIO - 1).is_err());
+        assert!(get_tick_at_sqrt_ratio(MAX_SQRT_RATIO).is_err());
+
+        assert_eq!(get_tick_at_sqrt_ratio(MIN_SQRT_RATIO).unwrap(), MIN_TICK);
+        assert_eq!(get_tick_at_sqrt_ratio(U256::from(4295343490u64)).unwrap(), MIN_TICK + 1);
+        assert_eq!(get_tick_at_sqrt_ratio(MAX_SQRT_RATIO - 1).unwrap(), MAX_TICK - 1);
+        assert_eq!(get_tick_at_sqrt_ratio(U256::one() << 96).unwrap(), 0);
+        assert_eq!(get_tick_at_sqrt_ratio(dec("3961408125713216879677197")).unwrap(), -198080);
+
+        // Every tick's own ratio maps back to it, and one below maps to the tick under it.
+        for tick in (MIN_TICK + 1..MAX_TICK).step_by(7919) {
+            let ratio = get_sqrt_ratio_at_tick(tick).unwrap();
+            assert_eq!(get_tick_at_sqrt_ratio(ratio).unwrap(), tick);
+            assert_eq!(get_tick_at_sqrt_ratio(ratio - 1).unwrap(), tick - 1);
+        }
+    }
+}
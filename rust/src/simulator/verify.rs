@@ -0,0 +1,389 @@
+use crate::config::VerifySettings;
+use crate::dexes::DexInfo;
+use crate::pool_adapters;
+use crate::pools::{v3_fee_to_fraction, PoolDescriptor};
+use crate::pools_abi::quoter_v2::{QuoteExactInputSingleParams, QuoterV2};
+use crate::pools_abi::uniswap_v2_pair::UniswapV2Pair;
+use crate::pools_abi::uniswap_v2_router::UniswapV2Router02;
+use crate::pools_abi::uniswap_v3_immutables::UniswapV3Immutables;
+use crate::rpc::{self, RpcProvider};
+use crate::simulator::amm::{fee_pips, v3_amount_out};
+use crate::simulator::exact;
+use crate::threads::{SimulateTradeLoop, SimulateTradeLoopV2};
+use crate::units::{TokenAmount, TokenRef};
+use crate::ArbitrageDirection;
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use ethers::providers::Middleware;
+use ethers::types::{Address, U256};
+use log::{error, info, warn};
+use std::fmt;
+use std::sync::Arc;
+
+/// One simulated leg next to the DEX's own quote for the same input at the same block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegCheck {
+    pub pool: Address,
+    pub dex: &'static str,
+    pub block: u64,
+    pub amount_in: TokenAmount,
+    pub simulated: TokenAmount,
+    pub quoted: TokenAmount,
+}
+
+impl LegCheck {
+    /// `|simulated - quoted|` in whole output tokens.
+    pub fn abs_error(&self) -> BigDecimal {
+        (self.simulated.to_decimal() - self.quoted.to_decimal()).abs()
+    }
+
+    /// `abs_error / quoted`; an error when the quoter returned nothing.
+    pub fn rel_error(&self) -> Result<BigDecimal> {
+        exact::ratio(&self.abs_error(), &self.quoted.to_decimal())
+    }
+}
+
+impl fmt::Display for LegCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:?} @{}: in {} simulated {} quoted {} abs error {} rel error {}",
+            self.dex,
+            self.pool,
+            self.block,
+            self.amount_in.to_decimal(),
+            self.simulated.to_decimal(),
+            self.quoted.to_decimal(),
+            self.abs_error(),
+            match self.rel_error() {
+                Ok(rel) => format!("{:.3e}", exact::to_f64(&rel)),
+                Err(_) => "n/a".to_string(),
+            }
+        )
+    }
+}
+
+fn quoter(pool: &PoolDescriptor) -> Result<(&'static DexInfo, Address)> {
+    let dex = pool
+        .dex
+        .ok_or_else(|| anyhow!("Pool {:?} has no known DEX to quote against", pool.address))?;
+    let quoter = dex
+        .quoter_address()
+        .ok_or_else(|| anyhow!("{} has no Uniswap-compatible quoter", dex.name))?;
+    Ok((dex, quoter))
+}
+
+/// `SimulateTradeLoopV2::simulate_price_after_swap` on reserves read at `block` against
+/// `UniswapV2Router02.getAmountsOut` at the same block.
+pub async fn verify_v2_leg(
+    client: Arc<RpcProvider>,
+    rpc_url: &str,
+    pool: &PoolDescriptor,
+    token_in: Address,
+    token_out: Address,
+    amount: f64,
+    block: u64,
+) -> Result<LegCheck> {
+    let (dex, router) = quoter(pool)?;
+    let pair = UniswapV2Pair::new(pool.address, client.clone());
+    let (reserve0, reserve1, _) = pair.get_reserves().block(block).call().await?;
+    let (reserve0, reserve1) = (U256::from(reserve0), U256::from(reserve1));
+    let (reserve_in, reserve_out) = if pair.token_0().block(block).call().await? == token_in {
+        (reserve0, reserve1)
+    } else {
+        (reserve1, reserve0)
+    };
+
+    let reserve_in = TokenAmount::new(reserve_in, TokenRef::resolve(rpc_url, token_in).await?);
+    let reserve_out = TokenAmount::new(reserve_out, TokenRef::resolve(rpc_url, token_out).await?);
+    let amount_in = TokenAmount::from_f64(amount, reserve_in.token)?;
+
+    let sim = SimulateTradeLoopV2::new(Some(pool.fee));
+    let simulated = sim
+        .simulate_price_after_swap(&reserve_in, &reserve_out, &amount_in, false, false)
+        .await?
+        .amount_out;
+
+    let amounts = UniswapV2Router02::new(router, client)
+        .get_amounts_out(amount_in.raw, vec![token_in, token_out])
+        .block(block)
+        .call()
+        .await?;
+    let quoted = amounts
+        .last()
+        .copied()
+        .ok_or_else(|| anyhow!("getAmountsOut returned no amounts"))?;
+
+    Ok(LegCheck {
+        pool: pool.address,
+        dex: dex.name,
+        block,
+        amount_in,
+        simulated,
+        quoted: TokenAmount::new(quoted, reserve_out.token),
+    })
+}
+
+/// A V3 leg on state read at `block` against `QuoterV2.quoteExactInputSingle` at the same
+/// block. Selling token0 goes through `SimulateTradeLoop::simulate_curve_price_movement`,
+/// which only takes token0 as input; selling token1 through the `computeSwapStep` port.
+pub async fn verify_v3_leg(
+    client: Arc<RpcProvider>,
+    rpc_url: &str,
+    pool: &PoolDescriptor,
+    token_in: Address,
+    token_out: Address,
+    amount: f64,
+    block: u64,
+) -> Result<LegCheck> {
+    let (dex, quoter) = quoter(pool)?;
+    let state = pool_adapters::fetch_pool_state_at(client.clone(), pool.variant, pool.address, block.into()).await?;
+    let zero_for_one = UniswapV3Immutables::new(pool.address, client.clone()).token_0().block(block).call().await? == token_in;
+
+    let token_in_ref = TokenRef::resolve(rpc_url, token_in).await?;
+    let token_out_ref = TokenRef::resolve(rpc_url, token_out).await?;
+    let amount_in = TokenAmount::from_f64(amount, token_in_ref)?;
+
+    let swap_fee = state.fee_for(zero_for_one).map_or(pool.fee, v3_fee_to_fraction);
+    let simulated = if zero_for_one {
+        SimulateTradeLoop::new(Some(swap_fee))
+            .simulate_curve_price_movement(state.sqrt_price_x96, state.liquidity, &amount_in, token_out_ref, false, false)
+            .await?
+            .amount_out
+    } else {
+        TokenAmount::new(v3_amount_out(&state, None, amount_in.raw, swap_fee, false)?.0, token_out_ref)
+    };
+
+    let fee = fee_pips(pool.fee).as_u32();
+    let (quoted, _, _, _) = QuoterV2::new(quoter, client)
+        .quote_exact_input_single(QuoteExactInputSingleParams {
+            token_in,
+            token_out,
+            amount_in: amount_in.raw,
+            fee,
+            sqrt_price_limit_x96: U256::zero(),
+        })
+        .block(block)
+        .call()
+        .await?;
+
+    Ok(LegCheck {
+        pool: pool.address,
+        dex: dex.name,
+        block,
+        amount_in,
+        simulated,
+        quoted: TokenAmount::new(quoted, token_out_ref),
+    })
+}
+
+/// Checks both legs of a direction at one block: the sell pool token0 → token1 and the buy
+/// pool token1 → token0.
+pub async fn verify_direction(dir: &ArbitrageDirection, settings: &VerifySettings) -> Result<Vec<LegCheck>> {
+    let (Some(sell), Some(buy)) = (dir.pools.get(1), dir.pools.get(2)) else {
+        return Err(anyhow!("Pools not described for direction {:?}", dir.path));
+    };
+    let token0: Address = dir.token0.parse()?;
+    let token1: Address = dir.token1.parse()?;
+
+    let client = rpc::provider(&dir.provider)?;
+    let block = client.get_block_number().await?.as_u64();
+
+    let legs = match dir.pool_type.as_str() {
+        "V2" => vec![
+            verify_v2_leg(client.clone(), &dir.provider, sell, token0, token1, settings.amount, block).await,
+            verify_v2_leg(client, &dir.provider, buy, token1, token0, settings.amount, block).await,
+        ],
+        "V3" => vec![
+            verify_v3_leg(client.clone(), &dir.provider, sell, token0, token1, settings.amount, block).await,
+            verify_v3_leg(client, &dir.provider, buy, token1, token0, settings.amount, block).await,
+        ],
+        other => return Err(anyhow!("Unknown pool_type: {}", other)),
+    };
+
+    let mut checks = Vec::new();
+    for leg in legs {
+        match leg {
+            Ok(check) => checks.push(check),
+            Err(e) => warn!("Verify leg failed for direction {:?}: {e:#}", dir.path),
+        }
+    }
+    Ok(checks)
+}
+
+/// Runs `verify_direction` over every direction and logs each leg plus the worst relative
+/// error seen.
+pub async fn verify_directions(directions: &[ArbitrageDirection], settings: &VerifySettings) -> Vec<LegCheck> {
+    let mut checks = Vec::new();
+    for dir in directions {
+        match verify_direction(dir, settings).await {
+            Ok(legs) => {
+                for leg in &legs {
+                    info!("Verify: {}", leg);
+                }
+                checks.extend(legs);
+            }
+            Err(e) => error!("Verify failed for direction {:?}: {e:#}", dir.path),
+        }
+    }
+
+    if let Some(worst) = checks.iter().max_by_key(|check| check.rel_error().ok()) {
+        info!("Verify: {} legs checked, worst {}", checks.len(), worst);
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::{self, AmmKind, PoolVariant};
+    use crate::pool_adapters::PoolState;
+    use crate::pools_abi::erc20_abi::DecimalsCall;
+    use crate::pools_abi::quoter_v2::{QuoteExactInputSingleCall, QuoteExactInputSingleReturn};
+    use crate::pools_abi::uniswap_v2_pair::{GetReservesCall, GetReservesReturn, Token0Call};
+    use crate::pools_abi::uniswap_v2_router::GetAmountsOutCall;
+    use crate::pools_abi::uniswap_v3_liquidity::LiquidityCall;
+    use crate::pools_abi::uniswap_v3_slot0::{Slot0Call, Slot0Return};
+    use crate::rpc::fixture::{eth_call_entry, FixtureEntry};
+    use crate::rpc::{FixtureClient, RpcClient};
+    use serde_json::json;
+    use std::str::FromStr;
+
+    const ARBITRUM: u64 = 42161;
+
+    fn dex(name: &str) -> &'static DexInfo {
+        dexes::on_chain(ARBITRUM).find(|d| d.name == name).unwrap()
+    }
+
+    /// The chain id and an 18-decimal `a` and 6-decimal `b`, as the token registry reads them.
+    fn tokens(chain_id: &str, a: Address, b: Address) -> Vec<FixtureEntry> {
+        vec![
+            FixtureEntry::new("eth_chainId", json!(null), json!(chain_id)),
+            eth_call_entry(a, "latest", DecimalsCall, 18u8),
+            eth_call_entry(b, "latest", DecimalsCall, 6u8),
+        ]
+    }
+
+    async fn replay<T>(url: &str, entries: Vec<FixtureEntry>, legs: impl std::future::Future<Output = Result<T>>) -> T {
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+        rpc::scoped(url, client, legs).await.unwrap()
+    }
+
+    #[test]
+    fn quoter_params_encode_as_a_tuple() {
+        use crate::pools_abi::quoter_v2::QuoteExactInputSingleCall;
+        use ethers::abi::AbiEncode;
+        use ethers::utils::id;
+
+        let call = QuoteExactInputSingleCall {
+            params: QuoteExactInputSingleParams {
+                token_in: Address::repeat_byte(0xa),
+                token_out: Address::repeat_byte(0xb),
+                amount_in: U256::exp10(18),
+                fee: 500,
+                sqrt_price_limit_x96: U256::zero(),
+            },
+        };
+        let calldata = call.encode();
+        assert_eq!(calldata[..4], id("quoteExactInputSingle((address,address,uint256,uint24,uint160))"));
+        // a static tuple is encoded inline: selector plus five words
+        assert_eq!(calldata.len(), 4 + 5 * 32);
+    }
+
+    #[test]
+    fn errors_are_measured_against_the_quote() {
+        let usdc = TokenRef::new(Address::repeat_byte(0xb), 6);
+        let weth = TokenRef::new(Address::repeat_byte(0xa), 18);
+        let check = LegCheck {
+            pool: Address::repeat_byte(1),
+            dex: "uniswap_v2",
+            block: 100,
+            amount_in: TokenAmount::new(U256::exp10(18), weth),
+            simulated: TokenAmount::new(U256::from(2_499_000_000u64), usdc),
+            quoted: TokenAmount::new(U256::from(2_500_000_000u64), usdc),
+        };
+        assert_eq!(check.abs_error(), BigDecimal::from(1));
+        assert_eq!(check.rel_error().unwrap(), BigDecimal::from_str("0.0004").unwrap());
+
+        let exact = LegCheck { simulated: check.quoted, ..check.clone() };
+        assert_eq!(exact.abs_error(), BigDecimal::from(0));
+        assert!(LegCheck { quoted: TokenAmount::zero(usdc), ..check }.rel_error().is_err());
+    }
+
+    #[tokio::test]
+    async fn v2_legs_match_get_amounts_out_for_a_quarter_percent_pair() {
+        const URL: &str = "fixture://verify-v2";
+        let (a, b, pair) = (Address::repeat_byte(0x2a), Address::repeat_byte(0x2b), Address::repeat_byte(0x21));
+        let dex = dex("pancakeswap_v2");
+        let pool = PoolDescriptor { address: pair, kind: AmmKind::V2, variant: PoolVariant::UniswapV2, dex: Some(dex), fee: 0.0025 };
+        let (reserve_a, reserve_b) = (U256::exp10(21), U256::from(2_500_000_000_000u64));
+        let amount_in = U256::exp10(18);
+
+        // PancakeSwap V2's getAmountOut: a 9975/10000 fee multiplier.
+        let amount_out = amount_in * 9975 * reserve_b / (reserve_a * 10000 + amount_in * 9975);
+        let mut entries = tokens("0x7a70", a, b);
+        let reserves = GetReservesReturn { reserve_0: reserve_a.as_u128(), reserve_1: reserve_b.as_u128(), block_timestamp_last: 0 };
+        entries.push(eth_call_entry(pair, "0x10", GetReservesCall, reserves));
+        entries.push(eth_call_entry(pair, "0x10", Token0Call, a));
+        let get_amounts_out = GetAmountsOutCall { amount_in, path: vec![a, b] };
+        entries.push(eth_call_entry(dex.quoter_address().unwrap(), "0x10", get_amounts_out, vec![amount_in, amount_out]));
+
+        let check = replay(URL, entries, async { verify_v2_leg(rpc::provider(URL)?, URL, &pool, a, b, 1.0, 16).await }).await;
+        assert_eq!((check.dex, check.block, check.amount_in.raw), ("pancakeswap_v2", 16, amount_in));
+        assert_eq!(check.quoted.raw, amount_out);
+        assert_eq!(check.abs_error(), BigDecimal::from(0));
+    }
+
+    #[tokio::test]
+    async fn v3_legs_are_quoted_at_the_pool_fee_tier_from_either_side() {
+        const URL: &str = "fixture://verify-v3";
+        let (a, b, address) = (Address::repeat_byte(0x3a), Address::repeat_byte(0x3b), Address::repeat_byte(0x31));
+        let dex = dex("uniswap_v3");
+        let pool = PoolDescriptor { address, kind: AmmKind::V3, variant: PoolVariant::UniswapV3, dex: Some(dex), fee: 0.0005 };
+        // 2500 b per a: sqrt(2500e6 / 1e18) * 2^96.
+        let state = PoolState {
+            sqrt_price_x96: U256::from_dec_str("3961408125713216879677197").unwrap(),
+            tick: -198080,
+            liquidity: 10u128.pow(18),
+            fee: None,
+        };
+        let slot0 = Slot0Return {
+            sqrt_price_x96: state.sqrt_price_x96,
+            tick: state.tick,
+            observation_index: 0,
+            observation_cardinality: 1,
+            observation_cardinality_next: 1,
+            fee_protocol: 0,
+            unlocked: true,
+        };
+
+        let mut entries = tokens("0x7a71", a, b);
+        entries.push(eth_call_entry(address, "0x10", Slot0Call, slot0));
+        entries.push(eth_call_entry(address, "0x10", LiquidityCall, state.liquidity));
+        entries.push(eth_call_entry(address, "0x10", Token0Call, a));
+        let quotes = [(a, b, U256::exp10(18), true), (b, a, U256::from(2_500_000_000u64), false)];
+        for (token_in, token_out, amount_in, zero_for_one) in quotes {
+            let (amount_out, sqrt_price_after) = v3_amount_out(&state, None, amount_in, pool.fee, zero_for_one).unwrap();
+            let params = QuoteExactInputSingleParams { token_in, token_out, amount_in, fee: 500, sqrt_price_limit_x96: U256::zero() };
+            let quote = QuoteExactInputSingleReturn {
+                amount_out,
+                sqrt_price_x96_after: sqrt_price_after,
+                initialized_ticks_crossed: 0,
+                gas_estimate: U256::from(80_000u64),
+            };
+            entries.push(eth_call_entry(dex.quoter_address().unwrap(), "0x10", QuoteExactInputSingleCall { params }, quote));
+        }
+
+        let (sell, buy) = replay(URL, entries, async {
+            let client = rpc::provider(URL)?;
+            let sell = verify_v3_leg(client.clone(), URL, &pool, a, b, 1.0, 16).await?;
+            let buy = verify_v3_leg(client, URL, &pool, b, a, 2500.0, 16).await?;
+            Ok((sell, buy))
+        })
+        .await;
+        for check in [sell, buy] {
+            assert_eq!((check.dex, check.block), ("uniswap_v3", 16));
+            assert_eq!(check.abs_error(), BigDecimal::from(0), "{}", check);
+        }
+    }
+}
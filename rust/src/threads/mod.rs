@@ -1,10 +1,8 @@
-mod dex_price_fetcher;
+//mod dex_price_fetcher;
 mod simulate_trade_loop_v3;
 mod simulate_trade_loop_v2;
 
 //pub use dex_price_fetcher::run_price_fetcher;
 pub use simulate_trade_loop_v3::SimulateTradeLoop;
-pub use simulate_trade_loop_v3::SimResult;
 
 pub use simulate_trade_loop_v2::SimulateTradeLoopV2;
-pub use simulate_trade_loop_v2::SimPriceResult;
\ No newline at end of file
@@ -1,267 +1,188 @@
 // src/dex_simulator.rs
-use crate::pools_abi::erc20_abi::ERC20;
-use crate::pools_abi::uniswap_v3_liquidity::UniswapV3Liquidity;
-use crate::pools_abi::uniswap_v3_slot0::UniswapV3Slot0;
 use anyhow::Result;
-use ethers::providers::{Http, Provider};
-use ethers::types::{Address, U256};
+use crate::simulator::amm::fee_pips;
+use crate::simulator::v3_math::sqrt_price_math;
+use crate::units::{Price, Token0, Token1, TokenAmount, TokenRef};
+use ethers::types::U256;
 use log::{info, warn};
-use std::sync::Arc;
+use std::fmt;
 
-#[derive(Debug)]
+/// Outcome of one V3 curve simulation. Sqrt prices are raw on-chain values; prices are
+/// exact token1 per token0. Formatting is left to the caller.
+#[derive(Debug, Clone, PartialEq)]
 pub struct SimResult {
-    pub initial_price: String,
-    pub final_price: String,
-    pub average_sell_curve_price: String,
-    pub usdc_amount_to_trade: String,
+    /// token0 pushed along the curve, net of fee when selling.
+    pub amount_in: TokenAmount,
+    /// token1 received, net of fee when buying.
+    pub amount_out: TokenAmount,
+    pub sqrt_price_before: U256,
+    pub sqrt_price_after: U256,
+    pub initial_price: Price<Token0, Token1>,
+    pub final_price: Price<Token0, Token1>,
+    pub average_sell_curve_price: Price<Token0, Token1>,
 }
 
-#[derive(Debug)]
-pub struct PoolState {
-    pub sqrt_price_x96: U256,
-    pub liquidity: u128,
+impl fmt::Display for SimResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "price {} -> {}, average {}, out {}",
+            self.initial_price.value.round(6),
+            self.final_price.value.round(6),
+            self.average_sell_curve_price.value.round(2),
+            self.amount_out.to_decimal().round(2)
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct SimulateTradeLoop {
-    pub rpc_url: String,
-    pub pool_address: String,
-    pub token0: String,
-    pub token1: String,
     pub fee: f64,
-    pub ethers_provider: String,
-    pub web3_provider: String,
 }
 
 impl SimulateTradeLoop {
-    pub fn new(
-        rpc_url: impl Into<String>,
-        pool_address: impl Into<String>,
-        token0: impl Into<String>,
-        token1: impl Into<String>,
-        fee: Option<f64>,
-    ) -> Self {
-        let rpc_url = rpc_url.into();
-        Self {
-            rpc_url: rpc_url.clone(),
-            pool_address: pool_address.into(),
-            token0: token0.into(),
-            token1: token1.into(),
-            fee: fee.unwrap_or(0.003),
-            ethers_provider: format!("ethers::provider({})", rpc_url),
-            web3_provider: format!("web3::provider({})", rpc_url),
-        }
+    pub fn new(fee: Option<f64>) -> Self {
+        Self { fee: fee.unwrap_or(0.003) }
     }
 
-    pub async fn simulate_swaps(
+    /// Moves the curve by `amount_in` of token0: selling it when `b_revert` is false (fee
+    /// taken from the input), buying it otherwise (fee taken from the token1 output).
+    pub async fn simulate_curve_price_movement(
         &self,
-        token_loan: f64,
-        pool_label: u8,
+        sqrt_price_x96: U256,
+        liquidity: u128,
+        amount_in: &TokenAmount,
+        token1: TokenRef,
+        b_revert: bool,
         b_show_debug: bool,
     ) -> Result<SimResult> {
-        let state = self.get_pool_state().await?;
-
-        let token0_address: Address = self.token0.parse()?;
-        let token1_address: Address = self.token1.parse()?;
-
-        let decimals0 = self.get_token_decimals(&token0_address).await?;
-        let decimals1 = self.get_token_decimals(&token1_address).await?;
+        info!("slot0() raw result = {:?}", sqrt_price_x96);
 
-        let sqrt_price_x96 = state.sqrt_price_x96;
-        let liquidity = state.liquidity;
+        let token0 = amount_in.token;
+        let pips = U256::from(1_000_000u64);
+        let fee_pips_kept = pips - fee_pips(self.fee);
 
-        info!(
-            "simulate_swaps before| slot0() raw result = {:?}",
-            sqrt_price_x96
-        );
+        if b_show_debug {
+            info!("decimals0 = {}, decimals1 = {}", token0.decimals, token1.decimals);
+        }
 
         if liquidity == 0 {
             anyhow::bail!("Pool has no liquidity");
         }
-
         if sqrt_price_x96.is_zero() {
             anyhow::bail!("Invalid sqrtPriceX96");
         }
 
-        info!(
-            "simulate_swaps after | slot0() raw result = {:?}",
-            sqrt_price_x96
-        );
-
-        let result: SimResult = match pool_label {
-            0 => {
-                self.simulate_curve_price_movement(
-                    sqrt_price_x96,
-                    liquidity,
-                    &token_loan,
-                    decimals0.into(),
-                    decimals1.into(),
-                    false,
-                    b_show_debug,
-                )
-                .await?
-            }
-            1 => {
-                self.simulate_curve_price_movement(
-                    sqrt_price_x96,
-                    liquidity,
-                    &token_loan,
-                    decimals0.into(),
-                    decimals1.into(),
-                    true,
-                    b_show_debug,
-                )
-                .await?
-            }
-            _ => anyhow::bail!("Invalid poolLabel: {}", pool_label),
+        let amount = if !b_revert {
+            amount_in.mul_div(fee_pips_kept, pips)?
+        } else {
+            *amount_in
         };
 
-        Ok(result)
-    }
-
-    pub async fn simulate_curve_price_movement(
-        &self,
-        sqrt_price_x96: U256,
-        liquidity: u128,
-        amount_in_decimal: &f64,
-        decimals0: u32,
-        decimals1: u32,
-        b_revert: bool,
-        b_show_debug: bool,
-    ) -> Result<SimResult> {
-        info!("slot0() raw result = {:?}", sqrt_price_x96);
-
-        if b_show_debug {
-            info!("decimals0 = {}, decimals1 = {}", decimals0, decimals1);
-            info!("decimal_diff = {}", decimal_diff);
-            info!("decimal_factor = {}", decimal_factor);
-        }
-
-        let q96 = U256::from(2).pow(U256::from(96));
-        let q192 = U256::from(2).pow(U256::from(192));
-
-        let mut amount = *amount_in_decimal;
-        if !b_revert {
-            amount *= fee_multiplier;
-        }
-
         let sqrt_p0 = sqrt_price_x96;
-        let l = U256::from(liquidity);
-
-        let scale = 10u128.pow(decimals0);
-        let dx = U256::from((amount * scale as f64).round() as u128);
+        let dx = amount.raw;
 
         if b_show_debug {
             info!("dx = amount * 10^decimals0 = {}", dx);
         }
 
-        let dx_adj = dx.checked_mul(sqrt_p0).unwrap() / q96;
-
-        let denominator = if !b_revert { l + dx_adj } else { l - dx_adj };
+        // Selling adds token0 to the pool, buying takes it out; the port errors instead of
+        // wrapping when the output exceeds what the liquidity can give.
+        let sqrt_p1 =
+            sqrt_price_math::get_next_sqrt_price_from_amount0_rounding_up(sqrt_p0, liquidity, dx, !b_revert)?;
 
         if b_show_debug {
-            info!(
-                "denominator = l {} dx_adj = {}",
-                if !b_revert { "+" } else { "-" },
-                denominator
-            );
+            info!("sqrt_p1 = {}", sqrt_p1);
         }
 
-        let sqrt_p1 = l * sqrt_p0 / denominator;
+        let price_before = Price::from_sqrt_price_x96(sqrt_p0, token0, token1);
+        let price_after = Price::from_sqrt_price_x96(sqrt_p1, token0, token1);
 
         if b_show_debug {
-            info!("sqrt_p1 = l * sqrt_p0 / denominator = {}", sqrt_p1);
+            info!("initial_price = {}", price_before);
+            info!("final_price = {}", price_after);
         }
 
-        let sqrt_p0_f64 = sqrt_p0.to_string().parse::<f64>()?;
-        let sqrt_p1_f64 = sqrt_p1.to_string().parse::<f64>()?;
-        let q192_f64 = q192.to_string().parse::<f64>()?;
-
-        let price_before = sqrt_p0_f64 * sqrt_p0_f64 / q192_f64 * decimal_factor as f64;
-        let price_after  = sqrt_p1_f64 * sqrt_p1_f64 / q192_f64 * decimal_factor as f64;
+        // dy = L * |sqrt_p0 - sqrt_p1| / Q96, rounded in the pool's favour
+        let dy = sqrt_price_math::get_amount1_delta(sqrt_p0, sqrt_p1, liquidity, b_revert)?;
+        let dy = TokenAmount::new(dy, token1);
+        let avg_price = Price::from_amounts(&amount, &dy)?;
 
         if b_show_debug {
-            info!(
-                "initial_price = price_before_raw * decimal_factor = {:.6}",
-                price_before
-            );
-            info!(
-                "final_price = price_after_raw * decimal_factor = {:.6}",
-                price_after
-            );
+            info!("dy_usdc_decimal = dy / 10^decimals1 = {}", dy.to_decimal());
+            info!("avg_price_in_usdc = dy_usdc_decimal / amount = {}", avg_price);
         }
 
-        let dy_usdc = dy.as_u128() as f64 / 10f64.powi(decimals1 as i32);
-        let avg_price = dy_usdc / amount;
+        let amount_out = if b_revert { dy.mul_div(fee_pips_kept, pips)? } else { dy };
 
-        if b_show_debug {
-            info!("dy_usdc_decimal = dy / 10^decimals1 = {}", dy_usdc);
-            info!(
-                "avg_price_in_usdc = dy_usdc_decimal / amount = {}",
-                avg_price
-            );
-        }
-
-        let mut usdc_amount_to_trade = avg_price * amount;
-        if b_revert {
-            usdc_amount_to_trade *= fee_multiplier;
-        }
+        let result = SimResult {
+            amount_in: amount,
+            amount_out,
+            sqrt_price_before: sqrt_p0,
+            sqrt_price_after: sqrt_p1,
+            initial_price: price_before,
+            final_price: price_after,
+            average_sell_curve_price: avg_price,
+        };
 
         if b_show_debug {
             let label = if !b_revert { "PoolB" } else { "PoolC" };
             warn!("*******V3*******");
             info!("Show debug for {}", label);
-            info!("PriceBeforeSwap: {:.2}", price_before);
-            info!("PriceAfterSwap: {:.2}", price_after);
-            info!("AverageCurveSellPrice: {:.2}", avg_price);
-            info!("TotalGiveAmount: {:.2}", usdc_amount_to_trade);
+            info!("{}", result);
         }
 
-        Ok(SimResult {
-            initial_price: format!("{:.6}", price_before),
-            final_price: format!("{:.6}", price_after),
-            average_sell_curve_price: format!("{:.2}", avg_price),
-            usdc_amount_to_trade: format!("{:.2}", usdc_amount_to_trade),
-        })
+        Ok(result)
     }
+}
 
-    pub async fn get_pool_state(&self) -> Result<PoolState> {
-        let provider = Provider::<Http>::try_from(self.rpc_url.clone())?;
-        let client = Arc::new(provider);
-
-        let address: Address = self.pool_address.parse()?;
-        let pool_slot = UniswapV3Slot0::new(address, client.clone());
-        let pool_liquidity = UniswapV3Liquidity::new(address, client);
-
-        let (sqrt_price_x96, _, _, _, _, _, _) = pool_slot.slot_0().call().await?;
-        let liquidity_raw = pool_liquidity.liquidity().call().await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Address;
 
-        info!(
-            "slot0() raw result = {:?}",
-            pool_slot.slot_0().call().await?
-        );
+    fn tokens() -> (TokenRef, TokenRef) {
+        (TokenRef::new(Address::repeat_byte(1), 18), TokenRef::new(Address::repeat_byte(2), 6))
+    }
 
-        Ok(PoolState {
-            sqrt_price_x96: sqrt_price_x96,
-            liquidity: liquidity_raw,
-        })
+    fn sim() -> SimulateTradeLoop {
+        SimulateTradeLoop::new(Some(0.003))
     }
 
-    pub async fn get_token_decimals(&self, token_address: &Address) -> Result<u8> {
-        let provider = Provider::<Http>::try_from(self.rpc_url.clone())?;
-        let client = Arc::new(provider);
+    #[tokio::test]
+    async fn buying_more_than_the_liquidity_holds_is_an_error() {
+        let (token0, token1) = tokens();
+        let sqrt_price = U256::one() << 96;
+        let amount = TokenAmount::new(U256::from(2_000u64), token0);
+        assert!(sim()
+            .simulate_curve_price_movement(sqrt_price, 1_000, &amount, token1, true, false)
+            .await
+            .is_err());
+    }
 
-        let token = ERC20::new(*token_address, client);
+    #[tokio::test]
+    async fn empty_pools_are_rejected() {
+        let (token0, token1) = tokens();
+        let amount = TokenAmount::new(U256::from(1_000u64), token0);
+        assert!(sim()
+            .simulate_curve_price_movement(U256::one() << 96, 0, &amount, token1, false, false)
+            .await
+            .is_err());
+        assert!(sim()
+            .simulate_curve_price_movement(U256::zero(), 1_000, &amount, token1, false, false)
+            .await
+            .is_err());
+    }
 
-        match token.decimals().await {
-            Ok(decimals) => Ok(decimals),
-            Err(e) => {
-                eprintln!(
-                    "[SimulateTradeLoop]: Failed to fetch decimals for token {}: {}",
-                    token_address, e
-                );
-                Ok(18)
-            }
-        }
+    #[tokio::test]
+    async fn large_liquidity_does_not_overflow() {
+        let (token0, token1) = tokens();
+        let sqrt_price = (U256::one() << 160) - 1;
+        let amount = TokenAmount::new(U256::exp10(18), token0);
+        let result = sim()
+            .simulate_curve_price_movement(sqrt_price, u128::MAX, &amount, token1, false, false)
+            .await
+            .unwrap();
+        assert!(result.sqrt_price_after < sqrt_price);
     }
 }
@@ -0,0 +1,434 @@
+use crate::chains;
+use crate::config::{RpcSettings, TickSettings};
+use crate::dexes::PoolVariant;
+use crate::pool_adapters;
+use crate::pools::PoolDescriptor;
+use crate::pools_abi::tick_lens::TickLens;
+use crate::pools_abi::uniswap_v3_ticks::{BurnFilter, MintFilter, UniswapV3Ticks};
+use crate::rpc::{self, RpcProvider};
+use crate::simulator::v3_math::tick_math::{MAX_TICK, MIN_TICK};
+use crate::ArbitrageDirection;
+use anyhow::{anyhow, Context, Result};
+use ethers::contract::EthEvent;
+use ethers::providers::Middleware;
+use ethers::types::{Address, Filter, Log, H256, U256};
+use log::{error, info};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// An initialized tick and the liquidity that changes when the price crosses it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PopulatedTick {
+    pub tick: i32,
+    pub liquidity_net: i128,
+    pub liquidity_gross: u128,
+}
+
+/// `TickLens.getPopulatedTicksInWord` results, `(tick, liquidityNet, liquidityGross)` each.
+fn from_lens(populated: Vec<(i32, i128, u128)>) -> Vec<PopulatedTick> {
+    populated
+        .into_iter()
+        .map(|(tick, liquidity_net, liquidity_gross)| PopulatedTick { tick, liquidity_net, liquidity_gross })
+        .collect()
+}
+
+/// `tickBitmap` word holding `tick`: one word covers 256 multiples of the spacing.
+pub fn word_position(tick: i32, tick_spacing: i32) -> i16 {
+    (tick.div_euclid(tick_spacing) >> 8) as i16
+}
+
+/// Ticks whose bits are set in `bitmap`, the `tickBitmap` word at `word`.
+pub fn ticks_in_word(word: i16, bitmap: U256, tick_spacing: i32) -> Vec<i32> {
+    (0..256)
+        .filter(|bit| bitmap.bit(*bit))
+        .map(|bit| ((word as i32) * 256 + bit as i32) * tick_spacing)
+        .collect()
+}
+
+/// Initialized ticks of one pool over a contiguous range of bitmap words, as of `block`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolTicks {
+    pub tick_spacing: i32,
+    pub block: u64,
+    pub words: RangeInclusive<i16>,
+    ticks: BTreeMap<i32, PopulatedTick>,
+}
+
+impl PoolTicks {
+    pub fn new(tick_spacing: i32, block: u64, words: RangeInclusive<i16>, ticks: Vec<PopulatedTick>) -> Self {
+        let ticks = ticks.into_iter().map(|t| (t.tick, t)).collect();
+        PoolTicks { tick_spacing, block, words, ticks }
+    }
+
+    pub fn covers(&self, tick: i32) -> bool {
+        self.words.contains(&word_position(tick, self.tick_spacing))
+    }
+
+    /// Next initialized tick at or below `tick` when `lte` (price falling), otherwise
+    /// strictly above it. `None` when there is none inside the fetched words, in which
+    /// case liquidity past the window is unknown rather than constant.
+    pub fn next_initialized(&self, tick: i32, lte: bool) -> Option<&PopulatedTick> {
+        let next = if lte {
+            self.ticks.range(..=tick).next_back()
+        } else {
+            self.ticks.range(tick + 1..).next()
+        };
+        next.map(|(_, t)| t).filter(|t| self.covers(t.tick))
+    }
+}
+
+/// Reads initialized ticks around a pool's current tick: `tickBitmap` for the words in
+/// range, then TickLens for the populated ones, or `ticks(int24)` where no lens is deployed.
+pub struct TickFetcher {
+    rpc_url: String,
+    lens: Option<Address>,
+    word_range: u16,
+}
+
+impl TickFetcher {
+    pub fn new(rpc_url: impl Into<String>, lens: Option<Address>, word_range: u16) -> Self {
+        TickFetcher { rpc_url: rpc_url.into(), lens, word_range }
+    }
+
+    /// Picks the lens configured for the chain behind `rpc_url`, if any.
+    pub async fn from_settings(rpc_url: &str, settings: &TickSettings) -> Result<Self> {
+        let chain_id = chains::fetch_chain_id(rpc_url).await?;
+        let lens = match chains::by_id(chain_id).and_then(|chain| settings.lens.get(chain.name)) {
+            Some(address) => Some(
+                address
+                    .parse::<Address>()
+                    .with_context(|| format!("Invalid TickLens address {} for chain {}", address, chain_id))?,
+            ),
+            None => None,
+        };
+        Ok(TickFetcher::new(rpc_url, lens, settings.word_range))
+    }
+
+    pub async fn fetch(&self, pool: &PoolDescriptor) -> Result<PoolTicks> {
+        if matches!(pool.variant, PoolVariant::UniswapV2 | PoolVariant::Algebra) {
+            return Err(anyhow!("{:?} pool {:?} has no Uniswap V3 tick bitmap", pool.variant, pool.address));
+        }
+        let client = rpc::provider(&self.rpc_url)?;
+        let block = client.get_block_number().await?;
+        let contract = UniswapV3Ticks::new(pool.address, client.clone());
+
+        let state = pool_adapters::fetch_pool_state_at(client.clone(), pool.variant, pool.address, block.into()).await?;
+        let tick_spacing = contract.tick_spacing().block(block).call().await?;
+        if tick_spacing <= 0 {
+            return Err(anyhow!("Invalid tick spacing {} for pool {:?}", tick_spacing, pool.address));
+        }
+
+        let current = word_position(state.tick, tick_spacing) as i32;
+        let range = self.word_range as i32;
+        let first = (current - range).max(word_position(MIN_TICK, tick_spacing) as i32) as i16;
+        let last = (current + range).min(word_position(MAX_TICK, tick_spacing) as i32) as i16;
+
+        let mut ticks = Vec::new();
+        for word in first..=last {
+            let bitmap = contract.tick_bitmap(word).block(block).call().await?;
+            if bitmap.is_zero() {
+                continue;
+            }
+            let populated = match self.lens {
+                Some(lens) => self.read_word_via_lens(client.clone(), lens, pool.address, word, block.as_u64()).await?,
+                None => self.read_word_via_pool(&contract, word, bitmap, tick_spacing, block.as_u64()).await?,
+            };
+            ticks.extend(populated);
+        }
+
+        info!(
+            "Ticks {:?}: {} initialized in words {}..={} at block {}",
+            pool.address,
+            ticks.len(),
+            first,
+            last,
+            block
+        );
+        Ok(PoolTicks::new(tick_spacing, block.as_u64(), first..=last, ticks))
+    }
+
+    async fn read_word_via_lens(
+        &self,
+        client: Arc<RpcProvider>,
+        lens: Address,
+        pool: Address,
+        word: i16,
+        block: u64,
+    ) -> Result<Vec<PopulatedTick>> {
+        let populated = TickLens::new(lens, client)
+            .get_populated_ticks_in_word(pool, word)
+            .block(block)
+            .call()
+            .await
+            .with_context(|| format!("getPopulatedTicksInWord({:?}, {}) failed", pool, word))?;
+        Ok(from_lens(populated))
+    }
+
+    async fn read_word_via_pool(
+        &self,
+        contract: &UniswapV3Ticks<RpcProvider>,
+        word: i16,
+        bitmap: U256,
+        tick_spacing: i32,
+        block: u64,
+    ) -> Result<Vec<PopulatedTick>> {
+        let mut populated = Vec::new();
+        for tick in ticks_in_word(word, bitmap, tick_spacing) {
+            let (liquidity_gross, liquidity_net, ..) = contract.ticks(tick).block(block).call().await?;
+            populated.push(PopulatedTick { tick, liquidity_net, liquidity_gross });
+        }
+        Ok(populated)
+    }
+
+    /// Cached ticks for `pool`, fetched on a miss.
+    pub async fn ticks(&self, pool: &PoolDescriptor) -> Result<PoolTicks> {
+        let chain_id = chains::fetch_chain_id(&self.rpc_url).await?;
+        if let Some(ticks) = cache().get(chain_id, pool.address) {
+            return Ok(ticks);
+        }
+        let ticks = self.fetch(pool).await?;
+        cache().insert(chain_id, pool.address, ticks.clone());
+        Ok(ticks)
+    }
+}
+
+/// Fills the cache with the ticks of every Uniswap/Pancake V3 pool in `directions`, so the
+/// snapshots sizing reads afterwards swap across them.
+pub async fn prefetch(directions: &[ArbitrageDirection], settings: &TickSettings) {
+    let mut by_provider: BTreeMap<&str, Vec<&PoolDescriptor>> = BTreeMap::new();
+    for dir in directions {
+        let pools = by_provider.entry(dir.provider.as_str()).or_default();
+        for pool in &dir.pools {
+            let has_bitmap = matches!(pool.variant, PoolVariant::UniswapV3 | PoolVariant::PancakeV3);
+            if has_bitmap && !pools.iter().any(|p| p.address == pool.address) {
+                pools.push(pool);
+            }
+        }
+    }
+
+    for (provider, pools) in by_provider {
+        let fetcher = match TickFetcher::from_settings(provider, settings).await {
+            Ok(fetcher) => fetcher,
+            Err(e) => {
+                error!("Ticks: no fetcher for {}: {e:#}", provider);
+                continue;
+            }
+        };
+        for pool in pools {
+            if let Err(e) = fetcher.ticks(pool).await {
+                error!("Ticks: fetching {:?} on {} failed: {e:#}", pool.address, provider);
+            }
+        }
+    }
+}
+
+/// Fetched ticks per `(chain_id, pool)`. Only Mint and Burn change a pool's ticks, so an
+/// entry stays valid until one of them is seen.
+pub struct TickCache {
+    entries: Mutex<HashMap<(u64, Address), PoolTicks>>,
+}
+
+impl TickCache {
+    pub fn new() -> Self {
+        TickCache { entries: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn get(&self, chain_id: u64, pool: Address) -> Option<PoolTicks> {
+        self.entries.lock().unwrap().get(&(chain_id, pool)).cloned()
+    }
+
+    pub fn insert(&self, chain_id: u64, pool: Address, ticks: PoolTicks) {
+        self.entries.lock().unwrap().insert((chain_id, pool), ticks);
+    }
+
+    /// Cached pools on `chain_id` and the block each was last known valid at.
+    pub fn pools(&self, chain_id: u64) -> Vec<(Address, u64)> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((chain, _), _)| *chain == chain_id)
+            .map(|((_, pool), ticks)| (*pool, ticks.block))
+            .collect()
+    }
+
+    /// Drops every entry of `watched` with a Mint or Burn in `logs` after the block it was
+    /// fetched at, and marks the rest valid through `head`. Returns how many were dropped.
+    pub fn apply_logs(&self, chain_id: u64, watched: &[Address], logs: &[Log], head: u64) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let mut dropped = 0;
+        for log in logs {
+            let block = log.block_number.map(|b| b.as_u64()).unwrap_or(head);
+            let key = (chain_id, log.address);
+            if entries.get(&key).is_some_and(|ticks| block > ticks.block) {
+                entries.remove(&key);
+                dropped += 1;
+            }
+        }
+        for pool in watched {
+            if let Some(ticks) = entries.get_mut(&(chain_id, *pool)) {
+                ticks.block = ticks.block.max(head);
+            }
+        }
+        dropped
+    }
+}
+
+impl Default for TickCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static CACHE: OnceLock<TickCache> = OnceLock::new();
+
+pub fn cache() -> &'static TickCache {
+    CACHE.get_or_init(TickCache::new)
+}
+
+fn liquidity_events() -> Vec<H256> {
+    vec![MintFilter::signature(), BurnFilter::signature()]
+}
+
+/// Checks the cached pools of one chain for Mint/Burn since they were fetched.
+async fn watch(chain_id: u64, rpc_url: &str) -> Result<()> {
+    let cached = cache().pools(chain_id);
+    let Some(from) = cached.iter().map(|(_, block)| block + 1).min() else {
+        return Ok(());
+    };
+    let client = rpc::provider(rpc_url)?;
+    let head = client.get_block_number().await?.as_u64();
+    if from > head {
+        return Ok(());
+    }
+
+    let watched: Vec<Address> = cached.iter().map(|(pool, _)| *pool).collect();
+    let filter = Filter::new()
+        .address(watched.clone())
+        .topic0(liquidity_events())
+        .from_block(from)
+        .to_block(head);
+    let logs = client.get_logs(&filter).await?;
+
+    let dropped = cache().apply_logs(chain_id, &watched, &logs, head);
+    if dropped > 0 {
+        let touched: HashSet<Address> = logs.iter().map(|log| log.address).collect();
+        info!("Ticks: chain {} blocks {}..={} invalidated {} pools {:?}", chain_id, from, head, dropped, touched);
+    }
+    Ok(())
+}
+
+/// Watches every chain with cached ticks for liquidity changes every `poll_every`.
+pub fn start(settings: &TickSettings, rpc: &RpcSettings) {
+    let period = settings.poll_interval();
+    let rpc = rpc.clone();
+    tokio::spawn(follow(rpc, period));
+}
+
+async fn follow(rpc: RpcSettings, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let chain_ids: HashSet<u64> = cache().entries.lock().unwrap().keys().map(|(chain, _)| *chain).collect();
+        for chain_id in chain_ids {
+            let Some(rpc_url) = chains::by_id(chain_id).and_then(|chain| rpc.primary_url(chain.name)) else {
+                error!("Ticks: no RPC endpoint for chain {}", chain_id);
+                continue;
+            };
+            if let Err(e) = watch(chain_id, &rpc_url).await {
+                error!("Ticks: chain {} watch failed: {e:#}", chain_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U64;
+
+    fn tick(tick: i32, liquidity_net: i128) -> PopulatedTick {
+        PopulatedTick { tick, liquidity_net, liquidity_gross: liquidity_net.unsigned_abs() }
+    }
+
+    #[test]
+    fn words_and_bits_map_back_to_ticks() {
+        assert_eq!(word_position(0, 60), 0);
+        assert_eq!(word_position(-1, 60), -1);
+        assert_eq!(word_position(-198080, 10), -78);
+        assert_eq!(word_position(256 * 60, 60), 1);
+
+        let bitmap = U256::one() | (U256::one() << 255);
+        assert_eq!(ticks_in_word(-1, bitmap, 60), vec![-256 * 60, -60]);
+        assert_eq!(ticks_in_word(2, U256::one() << 3, 10), vec![(512 + 3) * 10]);
+    }
+
+    #[test]
+    fn lens_return_data_decodes_to_signed_ticks() {
+        use crate::pools_abi::tick_lens::GetPopulatedTicksInWordReturn;
+        use ethers::abi::AbiDecode;
+        use ethers::utils::hex;
+
+        // getPopulatedTicksInWord(pool, -78) return data: three ticks of a spacing-10 pool
+        let data = hex::decode(concat!(
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "0000000000000000000000000000000000000000000000000000000000000003",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffcfa40",
+            "00000000000000000000000000000000000000000000000000001c67a274af22",
+            "00000000000000000000000000000000000000000000000000001c67a274af22",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffcfa9a",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffe46e57085f7",
+            "000000000000000000000000000000000000000000000000000001d2da29a573",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffcfcac",
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffe551781acae7",
+            "00000000000000000000000000000000000000000000000000001aae87e53519",
+        ))
+        .unwrap();
+        let decoded = GetPopulatedTicksInWordReturn::decode(data).unwrap();
+        let populated = from_lens(decoded.populated_ticks);
+
+        assert_eq!(populated.len(), 3);
+        assert_eq!(populated[0], PopulatedTick { tick: -198080, liquidity_net: 31231432765218, liquidity_gross: 31231432765218 });
+        assert_eq!(populated[1].liquidity_net, -1894526188041);
+        assert_eq!(populated[2].tick, -197460);
+        assert!(populated.iter().all(|t| word_position(t.tick, 10) == -78));
+    }
+
+    #[test]
+    fn next_initialized_stays_inside_fetched_words() {
+        // words -1..=0 with spacing 10 cover ticks -2560..=2550
+        let ticks = PoolTicks::new(10, 100, -1..=0, vec![tick(-2560, 5), tick(-20, 7), tick(30, -7)]);
+
+        assert_eq!(ticks.next_initialized(-20, true).unwrap().tick, -20);
+        assert_eq!(ticks.next_initialized(-20, false).unwrap().tick, 30);
+        assert_eq!(ticks.next_initialized(-21, true).unwrap().tick, -2560);
+        assert_eq!(ticks.next_initialized(30, false), None);
+        assert!(ticks.covers(2550));
+        assert!(!ticks.covers(2560));
+    }
+
+    #[test]
+    fn mint_or_burn_after_fetch_drops_the_pool() {
+        let cache = TickCache::new();
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        cache.insert(1, a, PoolTicks::new(60, 100, 0..=0, vec![]));
+        cache.insert(1, b, PoolTicks::new(60, 100, 0..=0, vec![]));
+
+        let log = |address, block: u64| Log {
+            address,
+            topics: vec![MintFilter::signature()],
+            block_number: Some(U64::from(block)),
+            ..Default::default()
+        };
+
+        // a's event predates its fetch; b's doesn't.
+        let dropped = cache.apply_logs(1, &[a, b], &[log(a, 100), log(b, 101)], 120);
+        assert_eq!(dropped, 1);
+        assert_eq!(cache.get(1, a).unwrap().block, 120);
+        assert!(cache.get(1, b).is_none());
+        assert_eq!(cache.pools(1), vec![(a, 120)]);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::chains;
+use crate::config::CacheSettings;
+use crate::pools_abi::erc20_abi::ERC20;
+use crate::rpc;
+use anyhow::{Context, Result};
+use ethers::types::Address;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub chain_id: u64,
+    pub address: Address,
+    pub decimals: u8,
+    pub symbol: String,
+    pub name: String,
+}
+
+/// ERC-20 metadata resolved once per `(chain_id, address)` and persisted between runs.
+pub struct TokenRegistry {
+    path: Option<PathBuf>,
+    entries: Mutex<HashMap<(u64, Address), TokenMetadata>>,
+    dirty: AtomicBool,
+}
+
+impl TokenRegistry {
+    /// In-memory only; used when `init` hasn't been called (tests, one-off tools).
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            entries: Mutex::new(HashMap::new()),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Loads `<cache.dir>/tokens.json` if it exists.
+    pub fn load(cache: &CacheSettings) -> Result<Self> {
+        let path = PathBuf::from(&cache.dir).join("tokens.json");
+        let mut entries = HashMap::new();
+
+        if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let tokens: Vec<TokenMetadata> = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            for token in tokens {
+                entries.insert((token.chain_id, token.address), token);
+            }
+        }
+
+        info!("Token registry: {} cached tokens from {}", entries.len(), path.display());
+        Ok(Self {
+            path: Some(path),
+            entries: Mutex::new(entries),
+            dirty: AtomicBool::new(false),
+        })
+    }
+
+    pub fn get(&self, chain_id: u64, address: Address) -> Option<TokenMetadata> {
+        self.entries.lock().unwrap().get(&(chain_id, address)).cloned()
+    }
+
+    pub fn insert(&self, token: TokenMetadata) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert((token.chain_id, token.address), token);
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Writes the cache file if anything was added since the last save.
+    pub fn save_if_dirty(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let mut tokens: Vec<TokenMetadata> = self.entries.lock().unwrap().values().cloned().collect();
+        tokens.sort_by_key(|t| (t.chain_id, t.address));
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&tokens)?)?;
+        info!("Saved {} tokens to {}", tokens.len(), path.display());
+        Ok(())
+    }
+}
+
+static REGISTRY: OnceLock<TokenRegistry> = OnceLock::new();
+
+/// Loads the on-disk cache and saves it every `cache.save_every`. Call once at startup.
+pub fn init(cache: &CacheSettings) -> Result<()> {
+    let loaded = TokenRegistry::load(cache)?;
+    if REGISTRY.set(loaded).is_err() {
+        warn!("Token registry already initialized");
+        return Ok(());
+    }
+
+    save_every(registry(), cache.save_interval());
+    Ok(())
+}
+
+/// Saves `registry` every `period` if anything was added in between.
+fn save_every(registry: &'static TokenRegistry, period: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = registry.save_if_dirty() {
+                error!("Failed to save token registry: {e:#}");
+            }
+        }
+    })
+}
+
+pub fn registry() -> &'static TokenRegistry {
+    REGISTRY.get_or_init(TokenRegistry::in_memory)
+}
+
+/// Returns metadata for `address` on the chain behind `rpc_url`, querying it on first use.
+///
+/// A failing `decimals()` is an error: guessing 18 silently breaks every price of a
+/// 6-decimal token. Symbol and name are informational and may be empty (e.g. MKR's
+/// `bytes32` symbol doesn't decode as a string).
+pub async fn metadata(rpc_url: &str, address: Address) -> Result<TokenMetadata> {
+    let chain_id = chains::fetch_chain_id(rpc_url).await?;
+    if let Some(token) = registry().get(chain_id, address) {
+        return Ok(token);
+    }
+
+    let token = ERC20::new(address, rpc::provider(rpc_url)?);
+    let decimals = token
+        .decimals()
+        .call()
+        .await
+        .with_context(|| format!("decimals() failed for token {:?} on chain {}", address, chain_id))?;
+    let symbol = token.symbol().call().await.unwrap_or_else(|e| {
+        warn!("symbol() failed for token {:?}: {}", address, e);
+        String::new()
+    });
+    let name = token.name().call().await.unwrap_or_else(|e| {
+        warn!("name() failed for token {:?}: {}", address, e);
+        String::new()
+    });
+
+    let metadata = TokenMetadata { chain_id, address, decimals, symbol, name };
+    info!("Token {:?} on chain {}: {} ({} decimals)", address, chain_id, metadata.symbol, decimals);
+    registry().insert(metadata.clone());
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::fixture::FixtureEntry;
+    use crate::rpc::{FixtureClient, RpcClient};
+    use serde_json::json;
+
+    fn temp_cache(name: &str) -> CacheSettings {
+        let dir = std::env::temp_dir().join(format!("tokens-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        CacheSettings { dir: dir.to_string_lossy().into_owned(), ..Default::default() }
+    }
+
+    fn usdc() -> TokenMetadata {
+        TokenMetadata {
+            chain_id: 1,
+            address: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap(),
+            decimals: 6,
+            symbol: "USDC".to_string(),
+            name: "USD Coin".to_string(),
+        }
+    }
+
+    #[test]
+    fn saved_tokens_load_back_and_clean_registries_skip_the_write() {
+        let cache = temp_cache("round-trip");
+        let path = PathBuf::from(&cache.dir).join("tokens.json");
+
+        let registry = TokenRegistry::load(&cache).unwrap();
+        registry.save_if_dirty().unwrap();
+        assert!(!path.exists());
+
+        registry.insert(usdc());
+        registry.save_if_dirty().unwrap();
+        let reloaded = TokenRegistry::load(&cache).unwrap();
+        assert_eq!(reloaded.get(1, usdc().address), Some(usdc()));
+        assert_eq!(reloaded.get(42161, usdc().address), None);
+
+        fs::remove_file(&path).unwrap();
+        registry.save_if_dirty().unwrap();
+        assert!(!path.exists());
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failing_decimals_is_an_error_not_18() {
+        let url = "fixture://tokens-no-decimals";
+        let entries = vec![FixtureEntry::new("eth_chainId", json!(null), json!("0x1"))];
+        let client = RpcClient::Fixture(FixtureClient::replay_entries(entries));
+
+        let err = rpc::scoped(url, client, metadata(url, Address::repeat_byte(0xde))).await.unwrap_err();
+        assert!(format!("{err:#}").contains("decimals() failed"), "{err:#}");
+        assert_eq!(registry().get(1, Address::repeat_byte(0xde)), None);
+    }
+
+    #[tokio::test]
+    async fn new_tokens_are_saved_on_the_configured_cadence() {
+        let cache = temp_cache("cadence");
+        let path = PathBuf::from(&cache.dir).join("tokens.json");
+        let registry: &'static TokenRegistry = Box::leak(Box::new(TokenRegistry::load(&cache).unwrap()));
+
+        let saver = save_every(registry, Duration::from_millis(50));
+        registry.insert(usdc());
+        assert!(!path.exists());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        saver.abort();
+        assert_eq!(TokenRegistry::load(&cache).unwrap().get(1, usdc().address), Some(usdc()));
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+}
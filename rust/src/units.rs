@@ -0,0 +1,244 @@
+use crate::simulator::amm::mul_div;
+use crate::simulator::exact;
+use crate::tokens::{self, TokenMetadata};
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use ethers::types::{Address, U256};
+use num_traits::Zero;
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+/// The part of a token's metadata arithmetic needs. Carried by every amount and price so
+/// decimals can't be mixed up between pools or sides of a swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenRef {
+    pub address: Address,
+    pub decimals: u8,
+}
+
+impl TokenRef {
+    pub fn new(address: Address, decimals: u8) -> Self {
+        TokenRef { address, decimals }
+    }
+
+    /// Looks the token up through the token registry.
+    pub async fn resolve(rpc_url: &str, address: Address) -> Result<Self> {
+        Ok(TokenRef::from(&tokens::metadata(rpc_url, address).await?))
+    }
+}
+
+impl From<&TokenMetadata> for TokenRef {
+    fn from(token: &TokenMetadata) -> Self {
+        TokenRef::new(token.address, token.decimals)
+    }
+}
+
+/// A raw on-chain amount of a specific token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenAmount {
+    pub raw: U256,
+    pub token: TokenRef,
+}
+
+fn same_token(a: TokenRef, b: TokenRef) -> Result<()> {
+    if a != b {
+        return Err(anyhow!("Token mismatch: {:?} vs {:?}", a, b));
+    }
+    Ok(())
+}
+
+impl TokenAmount {
+    pub fn new(raw: U256, token: TokenRef) -> Self {
+        TokenAmount { raw, token }
+    }
+
+    pub fn zero(token: TokenRef) -> Self {
+        TokenAmount::new(U256::zero(), token)
+    }
+
+    /// Whole tokens to raw units, rounding down to the token's last decimal.
+    pub fn from_decimal(amount: &BigDecimal, token: TokenRef) -> Result<Self> {
+        let raw = exact::to_raw(amount, token.decimals as u32)
+            .ok_or_else(|| anyhow!("{} doesn't fit a {}-decimal amount", amount, token.decimals))?;
+        Ok(TokenAmount::new(raw, token))
+    }
+
+    /// Goes through the shortest decimal form of `amount`, so `0.1` is exactly 10^17 wei
+    /// and not the binary float's 100000000000000005.
+    pub fn from_f64(amount: f64, token: TokenRef) -> Result<Self> {
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(anyhow!("Invalid token amount {}", amount));
+        }
+        TokenAmount::from_decimal(&BigDecimal::from_str(&amount.to_string())?, token)
+    }
+
+    pub fn to_decimal(self) -> BigDecimal {
+        exact::to_decimal(self.raw, self.token.decimals as u32)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        exact::to_f64(&self.to_decimal())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.raw.is_zero()
+    }
+
+    pub fn checked_add(&self, other: &TokenAmount) -> Result<Self> {
+        same_token(self.token, other.token)?;
+        let raw = self
+            .raw
+            .checked_add(other.raw)
+            .ok_or_else(|| anyhow!("{} + {} overflows", self, other))?;
+        Ok(TokenAmount::new(raw, self.token))
+    }
+
+    pub fn checked_sub(&self, other: &TokenAmount) -> Result<Self> {
+        same_token(self.token, other.token)?;
+        let raw = self
+            .raw
+            .checked_sub(other.raw)
+            .ok_or_else(|| anyhow!("{} - {} underflows", self, other))?;
+        Ok(TokenAmount::new(raw, self.token))
+    }
+
+    /// `self · numerator / denominator`, rounded down; e.g. `mul_div(997, 1000)` for a 0.3% fee.
+    pub fn mul_div(&self, numerator: U256, denominator: U256) -> Result<Self> {
+        Ok(TokenAmount::new(mul_div(self.raw, numerator, denominator)?, self.token))
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {:?}", self.to_decimal(), self.token.address)
+    }
+}
+
+/// A pool's token0, as a `Price` role.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token0;
+
+/// A pool's token1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token1;
+
+/// The token a swap takes in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct In;
+
+/// The token a swap pays out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Out;
+
+/// Whole `quote` tokens per whole `base` token, e.g. USDC per WETH with WETH as base.
+///
+/// `Base` and `Quote` are the roles the tokens play (`Token0`/`Token1`, `In`/`Out`). Which
+/// token fills a role is only known at runtime and is still checked; the roles let the
+/// compiler reject a price combined with its own inverse or passed the wrong way round.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Price<Base, Quote> {
+    pub base: TokenRef,
+    pub quote: TokenRef,
+    pub value: BigDecimal,
+    roles: PhantomData<(Base, Quote)>,
+}
+
+impl<Base, Quote> Price<Base, Quote> {
+    pub fn new(base: TokenRef, quote: TokenRef, value: BigDecimal) -> Self {
+        Price { base, quote, value, roles: PhantomData }
+    }
+
+    /// `quote / base`, e.g. from a pair's reserves or a swap's input and output.
+    pub fn from_amounts(base: &TokenAmount, quote: &TokenAmount) -> Result<Self> {
+        if base.token == quote.token {
+            return Err(anyhow!("Price of {:?} in itself", base.token.address));
+        }
+        if base.is_zero() {
+            return Err(anyhow!("Price against a zero amount of {:?}", base.token.address));
+        }
+        Ok(Price::new(base.token, quote.token, exact::ratio(&quote.to_decimal(), &base.to_decimal())?))
+    }
+
+    pub fn invert(&self) -> Result<Price<Quote, Base>> {
+        if self.value.is_zero() {
+            return Err(anyhow!("Can't invert a zero price"));
+        }
+        Ok(Price::new(self.quote, self.base, exact::ratio(&BigDecimal::from(1), &self.value)?))
+    }
+
+    /// Arithmetic mean of two prices of the same pair.
+    pub fn midpoint(&self, other: &Price<Base, Quote>) -> Result<Self> {
+        same_token(self.base, other.base)?;
+        same_token(self.quote, other.quote)?;
+        Ok(Price::new(self.base, self.quote, (&self.value + &other.value) / BigDecimal::from(2)))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        exact::to_f64(&self.value)
+    }
+}
+
+impl Price<Token0, Token1> {
+    /// Exact token1-per-token0 price of a concentrated-liquidity pool.
+    pub fn from_sqrt_price_x96(sqrt_price_x96: U256, token0: TokenRef, token1: TokenRef) -> Self {
+        let value = exact::sqrt_price_to_price(sqrt_price_x96, token0.decimals as u32, token1.decimals as u32);
+        Price::new(token0, token1, value)
+    }
+}
+
+impl<Base, Quote> fmt::Display for Price<Base, Quote> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:?}/{:?}", self.value.round(6), self.quote.address, self.base.address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weth() -> TokenRef {
+        TokenRef::new(Address::repeat_byte(0xa), 18)
+    }
+
+    fn usdc() -> TokenRef {
+        TokenRef::new(Address::repeat_byte(0xb), 6)
+    }
+
+    #[test]
+    fn amounts_refuse_to_mix_tokens() {
+        let one_eth = TokenAmount::from_f64(1.0, weth()).unwrap();
+        assert_eq!(one_eth.raw, U256::exp10(18));
+        assert_eq!(TokenAmount::from_f64(0.1, weth()).unwrap().raw, U256::exp10(17));
+        assert!(TokenAmount::from_f64(-1.0, weth()).is_err());
+
+        let usdc_amount = TokenAmount::from_f64(2500.0, usdc()).unwrap();
+        assert!(one_eth.checked_add(&usdc_amount).is_err());
+        assert!(TokenAmount::zero(weth()).checked_sub(&one_eth).is_err());
+        assert_eq!(one_eth.checked_add(&one_eth).unwrap().raw, U256::exp10(18) * 2);
+        assert_eq!(one_eth.mul_div(U256::from(997u64), U256::from(1000u64)).unwrap().raw, U256::exp10(15) * 997);
+    }
+
+    #[test]
+    fn prices_combine_with_their_own_tokens_only() {
+        let reserves = (TokenAmount::new(U256::exp10(21), weth()), TokenAmount::new(U256::from(2_500_000_000_000u64), usdc()));
+        let price: Price<Token0, Token1> = Price::from_amounts(&reserves.0, &reserves.1).unwrap();
+        assert_eq!(price.value, BigDecimal::from(2500));
+        assert!(Price::<Token0, Token1>::from_amounts(&reserves.0, &reserves.0).is_err());
+
+        let inverse = price.invert().unwrap();
+        assert_eq!((inverse.base, inverse.quote), (usdc(), weth()));
+        assert_eq!(inverse.value, BigDecimal::from_str("0.0004").unwrap());
+
+        // sqrtPriceX96 = 2^96 · √(2500 · 10^6 / 10^18)
+        let sqrt = U256::from(50u64) * (U256::one() << 96) / U256::exp10(6);
+        assert_eq!(Price::from_sqrt_price_x96(sqrt, weth(), usdc()).value.round(6), BigDecimal::from(2500));
+        // `price.midpoint(&inverse)` doesn't compile; same roles over other tokens fail at runtime.
+        let dai = TokenRef::new(Address::repeat_byte(0xc), 18);
+        let dai_reserve = TokenAmount::new(U256::exp10(24) * 25 / 10, dai);
+        let in_dai: Price<Token0, Token1> = Price::from_amounts(&reserves.0, &dai_reserve).unwrap();
+        assert_eq!(in_dai.value, price.value);
+        assert!(price.midpoint(&in_dai).is_err());
+        assert_eq!(price.midpoint(&inverse.invert().unwrap()).unwrap().value, BigDecimal::from(2500));
+    }
+}
@@ -0,0 +1,16 @@
+// Copies the Hardhat artifacts the Rust engine binds to into rust/abi, so `cargo build`
+// works without Node. Run through `npm run export-abi`, which compiles first.
+import fs from "fs";
+import path from "path";
+
+const CONTRACTS = ["FlashSwapArbitrageV2", "FlashSwapArbitrageV3", "ERC20Mock", "UniswapPoolMock", "SwapRouterMock"];
+const root = path.resolve(path.dirname(new URL(import.meta.url).pathname), "..");
+
+for (const name of CONTRACTS) {
+    const source = path.join(root, "artifacts", "contracts", `${name}.sol`, `${name}.json`);
+    const { _format, contractName, sourceName, abi, bytecode, deployedBytecode, linkReferences, deployedLinkReferences } =
+        JSON.parse(fs.readFileSync(source, "utf8"));
+    const artifact = { _format, contractName, sourceName, abi, bytecode, deployedBytecode, linkReferences, deployedLinkReferences };
+    fs.writeFileSync(path.join(root, "rust", "abi", `${name}.json`), JSON.stringify(artifact, null, 2) + "\n");
+    console.log(`rust/abi/${name}.json`);
+}
@@ -0,0 +1,145 @@
+const hardhat = require("hardhat");
+const { expect } = require("chai");
+
+// Same formula as FlashSwapArbitrageV2.getAmountOut.
+function getAmountOut(amountIn, reserveIn, reserveOut) {
+  const amountInWithFee = amountIn * 997n;
+  return (amountInWithFee * reserveOut) / (reserveIn * 1000n + amountInWithFee);
+}
+
+describe("FlashSwapArbitrageV2 minProfit", function () {
+  const unit = 10n ** 18n;
+  const amountIn = unit;
+
+  let contract;
+  let owner;
+  let tokenA, tokenB;
+  let pool0, pool1, pool2;
+  let profit;
+
+  async function pair(reserveA, reserveB) {
+    const Pair = await hardhat.ethers.getContractFactory("UniswapV2PairMock");
+    const pool = await Pair.deploy(tokenA.target, tokenB.target, 3000);
+    await pool.waitForDeployment();
+    await tokenA.transfer(pool.target, reserveA);
+    await tokenB.transfer(pool.target, reserveB);
+    await pool.sync();
+    return pool;
+  }
+
+  beforeEach(async () => {
+    // Local chain without the mainnet fork: the pools are mocks with known reserves.
+    await network.provider.request({ method: "hardhat_reset", params: [] });
+    [owner] = await hardhat.ethers.getSigners();
+
+    const Token = await hardhat.ethers.getContractFactory("ERC20Mock");
+    tokenA = await Token.deploy("Token A", "A", owner.address, 1_000_000n * unit);
+    tokenB = await Token.deploy("Token B", "B", owner.address, 1_000_000n * unit);
+
+    // Borrow A from pool0, sell it for ~2.1 B on pool1 and buy it back at ~1.9 B on pool2.
+    pool0 = await pair(1000n * unit, 1000n * unit);
+    pool1 = await pair(1000n * unit, 2100n * unit);
+    pool2 = await pair(1000n * unit, 1900n * unit);
+
+    const FlashSwapArbitrageV2 = await hardhat.ethers.getContractFactory("FlashSwapArbitrageV2");
+    contract = await FlashSwapArbitrageV2.deploy();
+    await contract.waitForDeployment();
+
+    const amountB = getAmountOut(amountIn, 1000n * unit, 2100n * unit);
+    const amountOut = getAmountOut(amountB, 1900n * unit, 1000n * unit);
+    const owed = amountIn + (amountIn * 3n) / 997n + 1n;
+    profit = amountOut - owed;
+  });
+
+  it("Should pay out a profit that reaches minProfit", async () => {
+    const before = await tokenA.balanceOf(owner.address);
+    const tx = await contract.initiateFlashSwap(
+      pool0.target, pool1.target, pool2.target, tokenA.target, tokenB.target, amountIn, profit
+    );
+    await tx.wait();
+
+    expect(await tokenA.balanceOf(owner.address)).to.equal(before + profit);
+  });
+
+  it("Should revert with Below min profit when the profit falls short", async () => {
+    await expect(
+      contract.initiateFlashSwap(
+        pool0.target, pool1.target, pool2.target, tokenA.target, tokenB.target, amountIn, profit + 1n
+      )
+    ).to.be.revertedWith("Below min profit");
+  });
+});
+
+describe("FlashSwapArbitrageV3 minProfit", function () {
+  const unit = 10n ** 18n;
+  const amountIn = unit;
+
+  let contract;
+  let owner;
+  let tokenA, tokenB;
+  let pool0, pool1, pool2;
+  let profit;
+
+  // UniswapPoolMock swaps at a fixed token1-per-token0 price scaled by 1e18.
+  function swapOut(price, zeroForOne, amount) {
+    return zeroForOne ? (amount * price) / unit : (amount * unit) / price;
+  }
+
+  async function pool(price) {
+    const Pool = await hardhat.ethers.getContractFactory("UniswapPoolMock");
+    const mock = await Pool.deploy();
+    await mock.waitForDeployment();
+    const [token0, token1] = tokenA.target < tokenB.target ? [tokenA, tokenB] : [tokenB, tokenA];
+    await mock.setTokens(token0.target, token1.target, price);
+    await tokenA.transfer(mock.target, 1000n * unit);
+    await tokenB.transfer(mock.target, 1000n * unit);
+    return mock;
+  }
+
+  beforeEach(async () => {
+    await network.provider.request({ method: "hardhat_reset", params: [] });
+    [owner] = await hardhat.ethers.getSigners();
+
+    const Token = await hardhat.ethers.getContractFactory("ERC20Mock");
+    tokenA = await Token.deploy("Token A", "A", owner.address, 1_000_000n * unit);
+    tokenB = await Token.deploy("Token B", "B", owner.address, 1_000_000n * unit);
+    const zeroForOne = tokenA.target < tokenB.target;
+
+    // B per A as each mock's token1-per-token0 price.
+    const price = (bPerA) => (zeroForOne ? bPerA : (unit * unit) / bPerA);
+    const [price0, price1, price2] = [price(21n * unit / 10n), price(2n * unit), price(19n * unit / 10n)];
+    pool0 = await pool(price0);
+    pool1 = await pool(price1);
+    pool2 = await pool(price2);
+
+    const FlashSwapArbitrageV3 = await hardhat.ethers.getContractFactory("FlashSwapArbitrageV3");
+    contract = await FlashSwapArbitrageV3.deploy();
+    await contract.waitForDeployment();
+
+    // The callback sells amountIn of the contract's own A on pool1 and repays pool0 from its
+    // balance, so it holds 2·amountIn beforehand. Selling pool0's B on pool2 brings back
+    // more A than that; the rest is the payout.
+    await tokenA.transfer(contract.target, 2n * amountIn);
+    const borrowedB = swapOut(price0, zeroForOne, amountIn);
+    const boughtBack = swapOut(price2, !zeroForOne, borrowedB);
+    profit = boughtBack - amountIn;
+  });
+
+  it("Should pay out a profit that reaches minProfit", async () => {
+    const before = await tokenA.balanceOf(owner.address);
+    const tx = await contract.flashSwap(
+      pool0.target, pool1.target, pool2.target, tokenA.target, tokenB.target, amountIn, profit
+    );
+    await tx.wait();
+
+    expect(await tokenA.balanceOf(owner.address)).to.equal(before + profit);
+  });
+
+  it("Should revert with Below min profit when the profit falls short", async () => {
+    await expect(
+      contract.flashSwap(
+        pool0.target, pool1.target, pool2.target, tokenA.target, tokenB.target, amountIn, profit + 1n
+      )
+    ).to.be.revertedWith("Below min profit");
+  });
+});
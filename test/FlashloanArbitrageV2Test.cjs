@@ -41,7 +41,7 @@ describe("FlashSwapArbitrageV2", function () {
   it("Should execute flash swap and return profit", async () => {
     const amountIn = hardhat.ethers.parseUnits("100000", 6);
     try {
-      const tx = await contract.initiateFlashSwap(pool0, pool1, pool2, token0, token1, amountIn
+      const tx = await contract.initiateFlashSwap(pool0, pool1, pool2, token0, token1, amountIn, 0
       );
 
       await tx.wait();
@@ -42,7 +42,7 @@ describe("FlashSwapArbitrageV3", function () {
         amountIn = hardhat.ethers.parseUnits("10", 6);
 
         try {
-            const tx = await contract.flashSwap(pool0, pool1, pool2, token0, token1, amountIn);
+            const tx = await contract.flashSwap(pool0, pool1, pool2, token0, token1, amountIn, 0);
             await tx.wait();
         } catch (err) {
             console.error("REVERT ERROR:", err);